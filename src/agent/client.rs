@@ -0,0 +1,188 @@
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail, eyre},
+};
+use std::{
+    fmt,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use super::protocol::{Request, Response};
+
+/// How long connecting, sending a request or waiting for its response may
+/// take. Clients run on the UI thread, so a hung agent must not block it.
+pub(crate) const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where an agent listens: a Unix socket path, or `host:port` for TCP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("address is empty".to_string());
+        }
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            return Ok(Address::Tcp(addr.to_string()));
+        }
+        // Paths contain a slash or have no port; anything else is host:port
+        let is_tcp = !s.contains('/')
+            && s.rsplit_once(':')
+                .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+        Ok(if is_tcp {
+            Address::Tcp(s.to_string())
+        } else {
+            Address::Unix(PathBuf::from(s))
+        })
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Unix(path) => write!(f, "{}", path.display()),
+            Address::Tcp(addr) => write!(f, "tcp://{addr}"),
+        }
+    }
+}
+
+/// An agent and the name its processes are shown under, written as
+/// `NAME=ADDR` or just `ADDR`. Names can't contain commas, since they are
+/// written to the host column of CSV recordings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteHost {
+    pub name: String,
+    pub address: Address,
+}
+
+impl FromStr for RemoteHost {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, address) = match s.split_once('=') {
+            Some((name, address)) => {
+                if name.is_empty() {
+                    return Err(format!("empty host name in {s:?}"));
+                }
+                (name.to_string(), address.parse()?)
+            }
+            None => {
+                let address: Address = s.parse()?;
+                let name = match &address {
+                    Address::Tcp(addr) => addr.clone(),
+                    // `web1.sock` is shown as `web1`
+                    Address::Unix(path) => path
+                        .file_stem()
+                        .map_or_else(|| s.to_string(), |stem| stem.to_string_lossy().into_owned()),
+                };
+                (name, address)
+            }
+        };
+        if name.contains(',') {
+            return Err(format!(
+                "host name {name:?} contains a comma; name it with NAME=ADDR"
+            ));
+        }
+        Ok(Self { name, address })
+    }
+}
+
+/// A connection to an agent, sending one request at a time.
+pub struct Client {
+    reader: BufReader<Box<dyn Read + Send>>,
+    writer: Box<dyn Write + Send>,
+}
+
+impl Client {
+    pub fn connect(address: &Address) -> Result<Self> {
+        let (reader, writer): (Box<dyn Read + Send>, Box<dyn Write + Send>) = match address {
+            Address::Tcp(addr) => {
+                let stream = connect_tcp(addr)
+                    .wrap_err_with(|| format!("failed to connect to agent at {address}"))?;
+                stream.set_nodelay(true)?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                (Box::new(stream.try_clone()?), Box::new(stream))
+            }
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                let stream = std::os::unix::net::UnixStream::connect(path)
+                    .wrap_err_with(|| format!("failed to connect to agent at {address}"))?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                (Box::new(stream.try_clone()?), Box::new(stream))
+            }
+            #[cfg(not(unix))]
+            Address::Unix(_) => bail!("Unix sockets are not supported on this platform"),
+        };
+        Ok(Self {
+            reader: BufReader::new(reader),
+            writer,
+        })
+    }
+
+    /// Sends a request and waits for its response. Error responses are
+    /// returned as `Ok(Response::Error { .. })`.
+    pub fn request(&mut self, request: &Request) -> Result<Response> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.flush()?;
+
+        let mut response = String::new();
+        if self.reader.read_line(&mut response)? == 0 {
+            bail!("agent closed the connection");
+        }
+        serde_json::from_str(&response).map_err(|e| eyre!("invalid response from agent: {e}"))
+    }
+}
+
+/// Connects to the first address `addr` resolves to that answers in time.
+fn connect_tcp(addr: &str) -> std::io::Result<TcpStream> {
+    let mut last_error = None;
+    for socket_addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&socket_addr, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "address resolved to nothing")
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_remote_hosts() {
+        let host: RemoteHost = "web1=10.0.0.1:7878".parse().unwrap();
+        assert_eq!(host.name, "web1");
+        assert_eq!(host.address, Address::Tcp("10.0.0.1:7878".to_string()));
+
+        let host: RemoteHost = "/run/web2.sock".parse().unwrap();
+        assert_eq!(host.name, "web2");
+        assert_eq!(host.address, Address::Unix(PathBuf::from("/run/web2.sock")));
+
+        let host: RemoteHost = "tcp://db:7878".parse().unwrap();
+        assert_eq!(host.name, "db:7878");
+    }
+
+    #[test]
+    fn rejects_host_names_with_commas() {
+        assert!("a,b=10.0.0.1:7878".parse::<RemoteHost>().is_err());
+        assert!("/run/a,b.sock".parse::<RemoteHost>().is_err());
+        assert!("a=/run/a,b.sock".parse::<RemoteHost>().is_ok());
+        assert!("=/run/a.sock".parse::<RemoteHost>().is_err());
+    }
+}
@@ -0,0 +1,691 @@
+//! Headless sampling controlled over a socket.
+//!
+//! The agent samples its watch targets like the TUI does and answers
+//! newline-delimited JSON requests, one response line per request:
+//!
+//! ```text
+//! $ echo '{"cmd":"add","pattern":"nginx"}' | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/catz.sock
+//! {"type":"targets","targets":[{"name":"nginx","pid":4242}]}
+//! $ echo '{"cmd":"mark","text":"deployed v2"}' | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/catz.sock
+//! {"type":"ok"}
+//! ```
+//!
+//! See [`protocol::Request`] for every command. The TUI takes `mark`
+//! requests too, on [`tui_socket_path`], see [`serve_marks`].
+
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail},
+};
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::TcpListener,
+    path::{Component, Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::Instant,
+};
+use sysinfo::Pid;
+
+use crate::{
+    config::Config,
+    domain::{
+        process::{ProcessManager, WatchTarget},
+        state::{AppMode, AppState},
+    },
+    recording::{RecordingFormat, sqlite::Database},
+    sampler::{RESOLVE_INTERVAL, SamplerEvent, SamplerHandle, Schedule},
+    session,
+    source::{MetricsSource, SourceKind},
+    utils,
+};
+
+mod client;
+pub mod protocol;
+
+#[cfg(test)]
+pub(crate) use client::TIMEOUT;
+pub use client::{Address, Client, RemoteHost};
+
+use protocol::{
+    FrameSnapshot, MarkerSnapshot, ProcessSnapshot, Request, Response, RunningProcess, TargetStatus,
+};
+
+/// Longest request line taken from a client, in bytes.
+const MAX_REQUEST_LINE: usize = 1 << 20;
+
+/// `$XDG_RUNTIME_DIR/catz.sock`, or `agent.sock` next to the sessions
+/// directory.
+pub fn default_socket_path() -> Option<PathBuf> {
+    runtime_path("catz.sock", "agent.sock")
+}
+
+/// `$XDG_RUNTIME_DIR/catz-tui.sock`, or `tui.sock` next to the sessions
+/// directory, where the TUI takes markers.
+pub fn tui_socket_path() -> Option<PathBuf> {
+    runtime_path("catz-tui.sock", "tui.sock")
+}
+
+fn runtime_path(runtime_name: &str, state_name: &str) -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| PathBuf::from(dir).join(runtime_name))
+        .or_else(|| Some(session::sessions_dir()?.parent()?.join(state_name)))
+}
+
+/// A bound socket that the agent, or the TUI for markers, accepts
+/// connections on.
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener, PathBuf),
+}
+
+impl Listener {
+    /// Binds to an address. A Unix socket left behind by a catz that is no
+    /// longer running is replaced; anything else at the path is an error.
+    pub fn bind(address: &Address) -> Result<Self> {
+        match address {
+            Address::Tcp(addr) => Ok(Listener::Tcp(
+                TcpListener::bind(addr).wrap_err_with(|| format!("failed to listen on {addr}"))?,
+            )),
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                use std::os::unix::{
+                    fs::FileTypeExt,
+                    net::{UnixListener, UnixStream},
+                };
+
+                if let Ok(metadata) = fs::symlink_metadata(path) {
+                    // Only ever replace a socket, never a file given by mistake
+                    if !metadata.file_type().is_socket() {
+                        bail!("{} exists and is not a socket", path.display());
+                    }
+                    if UnixStream::connect(path).is_ok() {
+                        bail!("another catz is already listening on {}", path.display());
+                    }
+                    fs::remove_file(path).wrap_err_with(|| {
+                        format!("failed to remove stale socket {}", path.display())
+                    })?;
+                }
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent)
+                        .wrap_err_with(|| format!("failed to create {}", parent.display()))?;
+                }
+                let listener = UnixListener::bind(path)
+                    .wrap_err_with(|| format!("failed to listen on {}", path.display()))?;
+                Ok(Listener::Unix(listener, path.clone()))
+            }
+            #[cfg(not(unix))]
+            Address::Unix(_) => bail!("Unix sockets are not supported on this platform, use TCP"),
+        }
+    }
+
+    pub fn address(&self) -> String {
+        match self {
+            Listener::Tcp(listener) => listener
+                .local_addr()
+                .map_or_else(|_| "tcp".to_string(), |addr| format!("tcp://{addr}")),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => path.display().to_string(),
+        }
+    }
+
+    /// Serves every incoming connection with `serve` on its own thread.
+    fn spawn<F>(self, serve: F)
+    where
+        F: Fn(Box<dyn Read + Send>, Box<dyn Write + Send>) + Clone + Send + 'static,
+    {
+        thread::spawn(move || match self {
+            Listener::Tcp(listener) => {
+                for stream in listener.incoming().flatten() {
+                    let _ = stream.set_nodelay(true);
+                    if let Ok(reader) = stream.try_clone() {
+                        let serve = serve.clone();
+                        thread::spawn(move || serve(Box::new(reader), Box::new(stream)));
+                    }
+                }
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => {
+                for stream in listener.incoming().flatten() {
+                    if let Ok(reader) = stream.try_clone() {
+                        let serve = serve.clone();
+                        thread::spawn(move || serve(Box::new(reader), Box::new(stream)));
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Takes `mark` requests on `listener` for something other than an agent,
+/// passing each marker's text to `mark`, which returns whether it was
+/// added. Other requests are refused.
+pub fn serve_marks<F>(listener: Listener, mark: F)
+where
+    F: Fn(String) -> bool + Clone + Send + 'static,
+{
+    listener.spawn(move |reader, mut writer| {
+        let mut reader = BufReader::new(reader);
+        while let Some(line) = read_request(&mut reader, &mut writer) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Request>(&line) {
+                Err(e) => Response::error(format!("invalid request: {e}")),
+                Ok(Request::Mark { text }) => match marker_text(&text) {
+                    None => Response::error("marker text is empty"),
+                    Some(text) => {
+                        if mark(text) {
+                            Response::Ok
+                        } else {
+                            Response::error("catz is shutting down")
+                        }
+                    }
+                },
+                Ok(_) => Response::error("only marks are taken here; control an agent instead"),
+            };
+            if write_response(&mut writer, &response).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+/// Marker text on a single line, or `None` if it is blank.
+fn marker_text(text: &str) -> Option<String> {
+    let text = text.lines().collect::<Vec<_>>().join(" ");
+    Some(text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+/// The next request line, or `None` once the client is gone. A line
+/// longer than [`MAX_REQUEST_LINE`] is answered with an error and ends the
+/// connection, as the rest of it can't be told apart from the next request.
+fn read_request(reader: &mut impl BufRead, writer: &mut impl Write) -> Option<String> {
+    match utils::read_line_capped(reader, MAX_REQUEST_LINE) {
+        Ok(line) => line,
+        Err(e) => {
+            if e.kind() == ErrorKind::InvalidData {
+                let _ = write_response(writer, &Response::error(format!("invalid request: {e}")));
+            }
+            None
+        }
+    }
+}
+
+fn write_response(writer: &mut impl Write, response: &Response) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(response)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+enum AgentEvent {
+    Request(Request, Sender<Response>),
+    Sampler(SamplerEvent),
+    /// Sent once the response to a `shutdown` request was written, or on
+    /// SIGINT and SIGTERM
+    Shutdown,
+}
+
+impl From<SamplerEvent> for AgentEvent {
+    fn from(event: SamplerEvent) -> Self {
+        AgentEvent::Sampler(event)
+    }
+}
+
+/// Samples watch targets without a terminal and answers control requests.
+pub struct Agent {
+    state: AppState,
+    source: SourceKind,
+    search: Box<dyn MetricsSource>,
+    /// Where the running recording is saved once stopped
+    recording: Option<PathBuf>,
+    events: Sender<AgentEvent>,
+    receiver: Receiver<AgentEvent>,
+}
+
+impl Agent {
+    pub fn new(config: Config, source: SourceKind) -> Self {
+        let (events, receiver) = mpsc::channel();
+        Self {
+            state: AppState::new(config),
+            search: source.open(),
+            source,
+            recording: None,
+            events,
+            receiver,
+        }
+    }
+
+    /// Stops serving on SIGINT and SIGTERM like on a `shutdown` request,
+    /// saving the running recording first. Can only be set up once per
+    /// process.
+    pub fn stop_on_signal(&self) -> Result<()> {
+        let events = self.events.clone();
+        ctrlc::set_handler(move || {
+            let _ = events.send(AgentEvent::Shutdown);
+        })
+        .wrap_err("failed to handle SIGINT and SIGTERM")
+    }
+
+    /// Watches every running process matching `pattern`, or the first one
+    /// named `pattern` to start if none is running.
+    pub fn watch(&mut self, pattern: &str, interval: Option<std::time::Duration>) {
+        let running = self.search.processes();
+        let matches = ProcessManager::find_matching_processes(pattern, &running);
+        let manager = self.state.process_manager();
+        if matches.is_empty() {
+            manager.add_target(WatchTarget {
+                name: pattern.to_string(),
+                pid: None,
+                alias: None,
+                group: None,
+                interval,
+                host: None,
+                from_watchlist: false,
+            });
+        }
+        for (pid, name) in matches {
+            manager.add_target(WatchTarget {
+                interval,
+                ..WatchTarget::new(name, pid)
+            });
+        }
+    }
+
+    /// Also writes every frame and marker to a new session in a database.
+    pub fn store_in(&mut self, database: Database) -> Result<()> {
+        self.state.store_in(database, "catz agent")
+    }
+
+    /// Serves requests until a `shutdown` request arrives.
+    pub fn serve(mut self, listeners: Vec<Listener>) -> Result<()> {
+        let events = self.events.clone();
+        let sampler = SamplerHandle::spawn(self.source.open(), events.clone());
+        sampler.update(Schedule::from_state(&self.state));
+
+        let mut socket_paths = Vec::new();
+        for listener in listeners {
+            #[cfg(unix)]
+            if let Listener::Unix(_, path) = &listener {
+                socket_paths.push(path.clone());
+            }
+            let (events, source) = (events.clone(), self.source.clone());
+            listener.spawn(move |reader, writer| {
+                serve_connection(reader, writer, &events, &source);
+            });
+        }
+        drop(events);
+
+        let mut last_resolve = Instant::now();
+        loop {
+            match self.receiver.recv_timeout(RESOLVE_INTERVAL) {
+                Ok(AgentEvent::Shutdown) => break,
+                Ok(AgentEvent::Request(request, reply)) => {
+                    let response = self.handle(request);
+                    let _ = reply.send(response);
+                    sampler.update(Schedule::from_state(&self.state));
+                }
+                Ok(AgentEvent::Sampler(SamplerEvent::Sample(sample))) => {
+                    if let Err(e) = self.state.record_frame(sample.taken_at, &sample.processes) {
+                        eprintln!("catz agent: {e}");
+                    }
+                    if self
+                        .state
+                        .apply_samples(&sample.sampled_pids, sample.processes)
+                    {
+                        sampler.update(Schedule::from_state(&self.state));
+                    }
+                }
+                Ok(AgentEvent::Sampler(SamplerEvent::Overhead(overhead))) => {
+                    self.state.overhead = overhead;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if last_resolve.elapsed() >= RESOLVE_INTERVAL
+                && self.state.watch_targets().iter().any(|t| t.pid.is_none())
+            {
+                let running = self.search.processes();
+                self.state.resolve_targets(&running);
+                sampler.update(Schedule::from_state(&self.state));
+                last_resolve = Instant::now();
+            }
+        }
+
+        for path in socket_paths {
+            let _ = fs::remove_file(path);
+        }
+        // Only still running when stopped by a signal
+        match self.finish_recording() {
+            Some(Response::Recording { path, samples }) => {
+                eprintln!("catz agent: saved {samples} samples to {}", path.display());
+            }
+            Some(Response::Error { message }) => eprintln!("catz agent: {message}"),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, request: Request) -> Response {
+        match request {
+            Request::Add { pattern, interval } => {
+                if pattern.trim().is_empty() {
+                    return Response::error("pattern is empty");
+                }
+                self.watch(pattern.trim(), interval);
+                self.targets()
+            }
+            Request::Remove { pid: Some(pid), .. } => {
+                let pid = Pid::from_u32(pid);
+                let manager = self.state.process_manager();
+                match manager.target_index(pid) {
+                    Some(index) => {
+                        manager.remove_target(index);
+                        self.targets()
+                    }
+                    None => Response::error(format!("process {pid} is not watched")),
+                }
+            }
+            Request::Remove {
+                index: Some(index), ..
+            } => match self.state.process_manager().remove_target(index) {
+                Some(_) => self.targets(),
+                None => Response::error(format!("there is no target {index}")),
+            },
+            Request::Remove {
+                name: Some(name), ..
+            } => match self.state.process_manager().remove_named(&name) {
+                0 => Response::error(format!("no target named {name:?}")),
+                _ => self.targets(),
+            },
+            Request::Remove { .. } => Response::error("remove needs a pid, an index or a name"),
+            Request::Targets => self.targets(),
+            Request::Current => Response::Current {
+                processes: self
+                    .state
+                    .processes()
+                    .iter()
+                    .map(ProcessSnapshot::from)
+                    .collect(),
+            },
+            Request::History { name, last } => {
+                let frames = self.state.history.frames();
+                let skip = last.map_or(0, |last| frames.len().saturating_sub(last));
+                let frames = frames
+                    .skip(skip)
+                    .map(|frame| {
+                        let mut snapshot = FrameSnapshot::from(frame);
+                        if let Some(name) = &name {
+                            snapshot.processes.retain(|p| {
+                                p.name == *name || p.alias.as_deref() == Some(name.as_str())
+                            });
+                        }
+                        snapshot
+                    })
+                    .collect();
+                Response::History {
+                    frames,
+                    markers: self
+                        .state
+                        .history
+                        .markers()
+                        .iter()
+                        .map(MarkerSnapshot::from)
+                        .collect(),
+                }
+            }
+            Request::RecordStart { path } => {
+                if let Some(path) = &self.recording {
+                    return Response::error(format!("already recording to {}", path.display()));
+                }
+                // Clients may be on other machines, so they only get to
+                // write inside the export directory
+                if !Path::new(&path)
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_)))
+                {
+                    return Response::error(format!(
+                        "{path:?} is not a file name inside the export directory {}",
+                        self.state.config.export.directory.display()
+                    ));
+                }
+                let path = self.state.config.export.resolve(&path);
+                if RecordingFormat::from_path(&path).is_none() {
+                    return Response::error(format!(
+                        "file name must end with .{}",
+                        RecordingFormat::EXTENSIONS.join(", .")
+                    ));
+                }
+                self.state.clear_stats();
+                self.state.mode = AppMode::Stats;
+                self.recording = Some(path);
+                Response::Ok
+            }
+            Request::RecordStop => self
+                .finish_recording()
+                .unwrap_or_else(|| Response::error("no recording is running")),
+            Request::Mark { text } => {
+                let Some(text) = marker_text(&text) else {
+                    return Response::error("marker text is empty");
+                };
+                match self.state.add_marker(&text) {
+                    // The marker is still kept in memory
+                    Ok(()) => Response::Ok,
+                    Err(e) => Response::error(e.to_string()),
+                }
+            }
+            // The connection stops the agent once this response is written
+            Request::Shutdown => self.finish_recording().unwrap_or(Response::Ok),
+            // Answered by the connection, see `serve_connection`
+            Request::Processes | Request::Sample { .. } => {
+                Response::error("processes and sample are not handled by the agent")
+            }
+        }
+    }
+
+    fn targets(&self) -> Response {
+        Response::Targets {
+            targets: self
+                .state
+                .watch_targets()
+                .iter()
+                .map(TargetStatus::from)
+                .collect(),
+        }
+    }
+
+    /// Saves the running recording, if any.
+    fn finish_recording(&mut self) -> Option<Response> {
+        let path = self.recording.take()?;
+        self.state.mode = AppMode::Normal;
+        let recording = self.state.stats_recording();
+        self.state.clear_stats();
+        Some(match recording.save(&path) {
+            Ok(()) => Response::Recording {
+                path,
+                samples: recording.samples.len(),
+            },
+            Err(e) => Response::error(format!("failed to save {}: {e}", path.display())),
+        })
+    }
+}
+
+/// Answers requests from one client until it disconnects. Listing and
+/// sampling arbitrary processes is done here with the connection's own
+/// source, so clients don't disturb the agent's CPU readings.
+fn serve_connection(
+    reader: impl Read,
+    mut writer: impl Write,
+    events: &Sender<AgentEvent>,
+    source: &SourceKind,
+) {
+    let mut own_source: Option<Box<dyn MetricsSource>> = None;
+    let mut shutdown = false;
+    let mut reader = BufReader::new(reader);
+    while let Some(line) = read_request(&mut reader, &mut writer) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Err(e) => Response::error(format!("invalid request: {e}")),
+            Ok(Request::Processes) => Response::Processes {
+                processes: own_source
+                    .get_or_insert_with(|| source.open())
+                    .processes()
+                    .into_iter()
+                    .map(|(pid, name)| RunningProcess {
+                        pid: pid.as_u32(),
+                        name,
+                    })
+                    .collect(),
+            },
+            Ok(Request::Sample { pids }) => {
+                let pids: Vec<Pid> = pids.into_iter().map(Pid::from_u32).collect();
+                Response::Samples {
+                    samples: own_source
+                        .get_or_insert_with(|| source.open())
+                        .sample(&pids)
+                        .into_iter()
+                        .map(ProcessSnapshot::from)
+                        .collect(),
+                }
+            }
+            Ok(request) => {
+                shutdown = matches!(request, Request::Shutdown);
+                let (reply, response) = mpsc::channel();
+                if events.send(AgentEvent::Request(request, reply)).is_err() {
+                    return;
+                }
+                match response.recv() {
+                    Ok(response) => response,
+                    Err(_) => return,
+                }
+            }
+        };
+        let written = write_response(&mut writer, &response).is_ok();
+        if shutdown {
+            let _ = events.send(AgentEvent::Shutdown);
+            return;
+        }
+        if !written {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::FakeSource;
+
+    fn agent() -> Agent {
+        let source = FakeSource::parse("processes = []").unwrap();
+        Agent::new(Config::default(), SourceKind::Fake(source))
+    }
+
+    #[test]
+    fn records_only_inside_the_export_directory() {
+        let mut agent = agent();
+        for path in ["/tmp/run.csv", "../run.csv", "runs/../../run.csv"] {
+            let response = agent.handle(Request::RecordStart {
+                path: path.to_string(),
+            });
+            assert!(matches!(response, Response::Error { .. }), "{path}");
+        }
+        let response = agent.handle(Request::RecordStart {
+            path: "runs/run.csv".to_string(),
+        });
+        assert!(matches!(response, Response::Ok));
+        assert_eq!(agent.recording, Some(PathBuf::from("./runs/run.csv")));
+    }
+
+    #[test]
+    fn removes_targets_waiting_for_a_process() {
+        let mut agent = agent();
+        agent.handle(Request::Add {
+            pattern: "nginx".to_string(),
+            interval: None,
+        });
+        assert_eq!(agent.state.watch_targets()[0].pid, None);
+
+        let response = agent.handle(Request::Remove {
+            pid: None,
+            index: Some(1),
+            name: None,
+        });
+        assert!(matches!(response, Response::Error { .. }));
+        let response = agent.handle(Request::Remove {
+            pid: None,
+            index: Some(0),
+            name: None,
+        });
+        assert!(matches!(response, Response::Targets { targets } if targets.is_empty()));
+    }
+
+    #[test]
+    fn takes_marks_for_the_tui() {
+        let listener = Listener::bind(&Address::Tcp("127.0.0.1:0".to_string())).unwrap();
+        let address: Address = listener.address().parse().unwrap();
+        let (marks, received) = mpsc::channel();
+        serve_marks(listener, move |text| marks.send(text).is_ok());
+
+        let mut client = Client::connect(&address).unwrap();
+        let mark = Request::Mark {
+            text: " deployed\nv2 ".to_string(),
+        };
+        assert!(matches!(client.request(&mark).unwrap(), Response::Ok));
+        assert_eq!(received.recv().unwrap(), "deployed v2");
+        let blank = Request::Mark {
+            text: " ".to_string(),
+        };
+        assert!(matches!(
+            client.request(&blank).unwrap(),
+            Response::Error { .. }
+        ));
+        assert!(matches!(
+            client.request(&Request::Targets).unwrap(),
+            Response::Error { .. }
+        ));
+    }
+
+    #[test]
+    fn rejects_requests_longer_than_a_line_may_be() {
+        let listener = Listener::bind(&Address::Tcp("127.0.0.1:0".to_string())).unwrap();
+        let Listener::Tcp(tcp) = &listener else {
+            unreachable!();
+        };
+        let address = tcp.local_addr().unwrap();
+        serve_marks(listener, |_| true);
+
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        stream.write_all(&vec![b'x'; MAX_REQUEST_LINE + 1]).unwrap();
+        let mut response = String::new();
+        BufReader::new(stream)
+            .read_to_string(&mut response)
+            .unwrap();
+        assert!(response.contains("longer than"), "{response}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn never_replaces_files_that_are_not_sockets() {
+        let path = env::temp_dir().join(format!("catz-not-a-socket-{}", std::process::id()));
+        fs::write(&path, "notes").unwrap();
+        let result = Listener::bind(&Address::Unix(path.clone()));
+        let content = fs::read_to_string(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+        assert_eq!(content.unwrap(), "notes");
+    }
+
+    #[test]
+    fn connection_requests_are_errors() {
+        let response = agent().handle(Request::Processes);
+        assert!(matches!(response, Response::Error { .. }));
+    }
+}
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+use sysinfo::Pid;
+
+use crate::{
+    domain::{
+        history::{Frame, Marker},
+        process::{ProcessInfo, WatchTarget},
+    },
+    source::ProcessSample,
+};
+
+/// One request per line, e.g. `{"cmd":"add","pattern":"nginx"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// Watches every running process matching the pattern, or waits for
+    /// one named like it if none is running
+    Add {
+        pattern: String,
+        #[serde(
+            default,
+            with = "humantime_serde",
+            skip_serializing_if = "Option::is_none"
+        )]
+        interval: Option<Duration>,
+    },
+    /// Stops watching a process by PID, the target at an index of the
+    /// `targets` list, which also reaches targets waiting for a process, or
+    /// every target with a name or alias
+    Remove {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pid: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    Targets,
+    /// Latest sample of every watched process
+    Current,
+    /// Sampled frames, optionally only the last few and one process
+    History {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last: Option<usize>,
+    },
+    /// Starts recording to a file in the export directory, e.g.
+    /// `run.csv` or `nightly/run.csv`; other paths are refused
+    RecordStart {
+        path: String,
+    },
+    RecordStop,
+    /// Drops a marker such as "deployed v2" into the history and the
+    /// running recording
+    Mark {
+        text: String,
+    },
+    /// Every process running on the agent's host
+    Processes,
+    /// Reads the given processes right away, independent of the watch list
+    Sample {
+        pids: Vec<u32>,
+    },
+    Shutdown,
+}
+
+/// One response line per request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    Error {
+        message: String,
+    },
+    Targets {
+        targets: Vec<TargetStatus>,
+    },
+    Current {
+        processes: Vec<ProcessSnapshot>,
+    },
+    History {
+        frames: Vec<FrameSnapshot>,
+        markers: Vec<MarkerSnapshot>,
+    },
+    Processes {
+        processes: Vec<RunningProcess>,
+    },
+    Samples {
+        samples: Vec<ProcessSnapshot>,
+    },
+    Recording {
+        path: PathBuf,
+        samples: usize,
+    },
+}
+
+impl Response {
+    pub fn error(message: impl Into<String>) -> Self {
+        Response::Error {
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetStatus {
+    #[serde(flatten)]
+    pub target: WatchTarget,
+    /// `None` while no running process matches
+    pub pid: Option<u32>,
+}
+
+impl From<&WatchTarget> for TargetStatus {
+    fn from(target: &WatchTarget) -> Self {
+        Self {
+            target: target.clone(),
+            pid: target.pid.map(|pid| pid.as_u32()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessSnapshot {
+    pub pid: u32,
+    pub name: String,
+    pub cpu_usage: f32,
+    pub memory_mb: f64,
+    /// Missing from agents that predate thread and I/O sampling
+    #[serde(default)]
+    pub threads: u32,
+    #[serde(default)]
+    pub read_kb_s: f64,
+    #[serde(default)]
+    pub write_kb_s: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+impl From<&ProcessInfo> for ProcessSnapshot {
+    fn from(process: &ProcessInfo) -> Self {
+        Self {
+            pid: process.local_pid(),
+            name: process.name.clone(),
+            cpu_usage: process.cpu_usage,
+            memory_mb: process.memory_mb,
+            threads: process.threads,
+            read_kb_s: process.read_kb_s,
+            write_kb_s: process.write_kb_s,
+            alias: process.alias.clone(),
+            group: process.group.clone(),
+            host: process.host.clone(),
+        }
+    }
+}
+
+impl From<ProcessSample> for ProcessSnapshot {
+    fn from(sample: ProcessSample) -> Self {
+        Self {
+            pid: sample.pid.as_u32(),
+            name: sample.name,
+            cpu_usage: sample.cpu_usage,
+            memory_mb: sample.memory_mb,
+            threads: sample.threads,
+            read_kb_s: sample.read_kb_s,
+            write_kb_s: sample.write_kb_s,
+            alias: None,
+            group: None,
+            host: None,
+        }
+    }
+}
+
+impl From<ProcessSnapshot> for ProcessSample {
+    fn from(snapshot: ProcessSnapshot) -> Self {
+        Self {
+            pid: Pid::from_u32(snapshot.pid),
+            name: snapshot.name,
+            cpu_usage: snapshot.cpu_usage,
+            memory_mb: snapshot.memory_mb,
+            threads: snapshot.threads,
+            read_kb_s: snapshot.read_kb_s,
+            write_kb_s: snapshot.write_kb_s,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameSnapshot {
+    /// Milliseconds since the agent started
+    pub elapsed_ms: u64,
+    pub processes: Vec<ProcessSnapshot>,
+}
+
+impl From<&Frame> for FrameSnapshot {
+    fn from(frame: &Frame) -> Self {
+        Self {
+            elapsed_ms: frame.elapsed.as_millis() as u64,
+            processes: frame.processes.iter().map(ProcessSnapshot::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkerSnapshot {
+    /// Milliseconds since the agent started
+    pub elapsed_ms: u64,
+    pub text: String,
+}
+
+impl From<&Marker> for MarkerSnapshot {
+    fn from(marker: &Marker) -> Self {
+        Self {
+            elapsed_ms: marker.elapsed.as_millis() as u64,
+            text: marker.text.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a line and checks that writing it back gives the same JSON.
+    fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(line: &str) -> T {
+        let parsed: T = serde_json::from_str(line).unwrap();
+        let written = serde_json::to_value(&parsed).unwrap();
+        assert_eq!(
+            written,
+            serde_json::from_str::<serde_json::Value>(line).unwrap()
+        );
+        parsed
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        for line in [
+            r#"{"cmd":"add","pattern":"nginx"}"#,
+            r#"{"cmd":"remove","pid":42}"#,
+            r#"{"cmd":"remove","index":1}"#,
+            r#"{"cmd":"remove","name":"nginx"}"#,
+            r#"{"cmd":"targets"}"#,
+            r#"{"cmd":"current"}"#,
+            r#"{"cmd":"history","name":"nginx","last":10}"#,
+            r#"{"cmd":"record_start","path":"run.csv"}"#,
+            r#"{"cmd":"record_stop"}"#,
+            r#"{"cmd":"mark","text":"deployed v2"}"#,
+            r#"{"cmd":"processes"}"#,
+            r#"{"cmd":"sample","pids":[1,2]}"#,
+            r#"{"cmd":"shutdown"}"#,
+        ] {
+            round_trip::<Request>(line);
+        }
+
+        let add: Request = round_trip(r#"{"cmd":"add","pattern":"nginx","interval":"500ms"}"#);
+        assert!(matches!(
+            add,
+            Request::Add { interval: Some(interval), .. } if interval == Duration::from_millis(500)
+        ));
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        for line in [
+            r#"{"type":"ok"}"#,
+            r#"{"type":"error","message":"pattern is empty"}"#,
+            r#"{"type":"targets","targets":[{"name":"nginx","pid":4242},{"name":"redis","pid":null}]}"#,
+            r#"{"type":"current","processes":[{"pid":1,"name":"a","cpu_usage":1.5,"memory_mb":2.0,"threads":3,"read_kb_s":0.5,"write_kb_s":0.0,"host":"web1"}]}"#,
+            r#"{"type":"history","frames":[{"elapsed_ms":500,"processes":[]}],"markers":[{"elapsed_ms":250,"text":"go"}]}"#,
+            r#"{"type":"processes","processes":[{"pid":1,"name":"init"}]}"#,
+            r#"{"type":"samples","samples":[]}"#,
+            r#"{"type":"recording","path":"run.csv","samples":3}"#,
+        ] {
+            round_trip::<Response>(line);
+        }
+    }
+
+    #[test]
+    fn reads_samples_of_agents_without_io() {
+        let line = r#"{"type":"samples","samples":[{"pid":1,"name":"a","cpu_usage":1.5,"memory_mb":2.0}]}"#;
+        let Response::Samples { samples } = serde_json::from_str(line).unwrap() else {
+            panic!("not a samples response");
+        };
+        assert_eq!(samples[0].read_kb_s, 0.0);
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"reboot"}"#).is_err());
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"add"}"#).is_err());
+    }
+}
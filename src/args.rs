@@ -1,4 +1,14 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    agent::{Address, RemoteHost},
+    domain::history::Metric,
+    session::DEFAULT_SESSION,
+    utils,
+};
+
+use crate::commands::compare::Threshold;
 
 #[derive(Parser)]
 #[command(
@@ -7,7 +17,355 @@ use clap::Parser;
     about = "Monitor CPU and memory usage of specific processes"
 )]
 pub struct Args {
-    /// Update interval in seconds
-    #[arg(short, long, default_value = "2")]
-    pub interval: u64,
+    /// Sampling interval such as 500ms or 2s; a bare number is seconds
+    /// [default: 2s, or the config file's `interval`]
+    #[arg(short, long, value_parser = utils::parse_interval)]
+    pub interval: Option<Duration>,
+
+    /// Config file to use instead of ~/.config/catz/config.toml
+    #[arg(short, long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Start by watching the processes of a watchlist from the config file
+    #[arg(short, long, value_name = "NAME")]
+    pub watchlist: Option<String>,
+
+    /// Named session whose watch targets and layout are restored on start and saved on exit
+    #[arg(long, value_name = "NAME", default_value = DEFAULT_SESSION)]
+    pub session: String,
+
+    /// Start with an empty watch list and don't save the session on exit
+    #[arg(long, conflicts_with = "session")]
+    pub no_session: bool,
+
+    /// Read processes from a procfs tree such as /proc instead of through sysinfo
+    #[arg(long, value_name = "DIR")]
+    pub proc_root: Option<PathBuf>,
+
+    /// Watch scripted processes from a fixture file instead of real ones
+    #[arg(long, value_name = "PATH", conflicts_with = "proc_root")]
+    pub fixture: Option<PathBuf>,
+
+    /// Also serve a live dashboard for browsers on this address, e.g.
+    /// 127.0.0.1:9000, from the TUI or `catz connect`
+    #[arg(long, value_name = "ADDR")]
+    pub web: Option<String>,
+
+    /// Also store every sample and marker in this SQLite database, adding a
+    /// session per run, for long captures and `catz query`. Works with the
+    /// TUI, `catz connect` and `catz agent`
+    #[arg(long, value_name = "PATH")]
+    pub db: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Sample without a terminal and serve a control socket for scripts
+    Agent(AgentArgs),
+    /// Watch a process and fail when it exceeds a CPU or memory budget
+    Assert(AssertArgs),
+    /// Run a command repeatedly and report wall time, CPU time and peak RSS
+    Bench(BenchArgs),
+    /// Run the TUI against processes of one or more `catz agent`s
+    Connect(ConnectArgs),
+    /// Compare two recordings and fail when a regression threshold is exceeded
+    Compare(CompareArgs),
+    /// Drop a marker such as "deployed v2" into a running agent's or TUI's history
+    Mark(MarkArgs),
+    /// Render a chart of a recording as SVG or PNG
+    Plot(PlotArgs),
+    /// Run aggregations such as peak RSS per process per hour over a --db database
+    Query(QueryArgs),
+    /// Play back a saved recording in the TUI
+    Replay(ReplayArgs),
+    /// Export a recording as an HTML report with charts and statistics
+    Report(ReportArgs),
+    /// Export a recording as Chrome trace JSON for Perfetto or chrome://tracing
+    Trace(TraceArgs),
+}
+
+#[derive(clap::Args)]
+pub struct BenchArgs {
+    /// Number of runs per command
+    #[arg(short = 'n', long, default_value = "10")]
+    pub runs: usize,
+
+    /// Second command to compare against the first, e.g. --compare "./app-v2 --flag"
+    #[arg(long, value_name = "COMMAND")]
+    pub compare: Option<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: OutputFormat,
+
+    /// Keep going when a run exits with a non-zero status
+    #[arg(long)]
+    pub ignore_failure: bool,
+
+    /// Show the benchmarked command's stdout and stderr
+    #[arg(long)]
+    pub show_output: bool,
+
+    /// Command to benchmark
+    #[arg(last = true, required = true, value_name = "COMMAND")]
+    pub command: Vec<String>,
+}
+
+#[derive(clap::Args)]
+pub struct AgentArgs {
+    /// Unix socket to listen on [default: $XDG_RUNTIME_DIR/catz.sock]
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// Also listen on a TCP address such as 127.0.0.1:7070. Requests are not
+    /// authenticated, so keep this on localhost or behind an SSH tunnel
+    #[arg(long, value_name = "ADDR")]
+    pub tcp: Option<String>,
+
+    /// Only listen on --tcp
+    #[arg(long, requires = "tcp", conflicts_with = "socket")]
+    pub no_socket: bool,
+
+    /// Process name patterns to watch from the start, in addition to --watchlist
+    #[arg(value_name = "PATTERN")]
+    pub patterns: Vec<String>,
+}
+
+#[derive(clap::Args)]
+pub struct ConnectArgs {
+    /// Agent addresses: Unix socket paths or host:port for TCP, optionally
+    /// named as NAME=ADDR. Several agents are shown in one table
+    #[arg(value_name = "ADDR", required = true)]
+    pub hosts: Vec<RemoteHost>,
+
+    /// Named session to restore and save; remote targets aren't saved by default
+    #[arg(long, value_name = "NAME")]
+    pub session: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct MarkArgs {
+    /// Marker text; several words are joined with spaces
+    #[arg(value_name = "TEXT", required = true)]
+    pub text: Vec<String>,
+
+    /// Agent to mark: a Unix socket path or host:port for TCP
+    /// [default: $XDG_RUNTIME_DIR/catz.sock]
+    #[arg(long, value_name = "ADDR")]
+    pub agent: Option<Address>,
+
+    /// Mark the running TUI instead of an agent
+    #[arg(long, conflicts_with = "agent")]
+    pub tui: bool,
+}
+
+#[derive(clap::Args)]
+pub struct AssertArgs {
+    /// Watch a running process instead of starting a command
+    #[arg(long, conflicts_with = "command")]
+    pub pid: Option<u32>,
+
+    /// Maximum resident memory at any sample, e.g. "500MB" or "2GB"
+    #[arg(long, value_name = "SIZE", value_parser = utils::parse_size_mb)]
+    pub max_rss: Option<f64>,
+
+    /// Maximum CPU usage averaged over all samples, in percent of one CPU
+    #[arg(long, value_name = "PERCENT")]
+    pub max_avg_cpu: Option<f64>,
+
+    /// Maximum CPU usage at any sample, in percent of one CPU
+    #[arg(long, value_name = "PERCENT")]
+    pub max_cpu: Option<f64>,
+
+    /// Stop watching after this long; required with --pid. A started
+    /// command is killed once this passes, otherwise it runs until it exits
+    #[arg(short, long, value_parser = utils::parse_duration, required_unless_present = "command")]
+    pub duration: Option<Duration>,
+
+    /// Sampling interval
+    #[arg(short, long, value_parser = utils::parse_interval, default_value = "100ms")]
+    pub interval: Duration,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: OutputFormat,
+
+    /// Command to start and watch
+    #[arg(last = true, value_name = "COMMAND", required_unless_present = "pid")]
+    pub command: Vec<String>,
+}
+
+#[derive(clap::Args)]
+pub struct CompareArgs {
+    /// Recording to compare against
+    pub baseline: PathBuf,
+
+    /// Recording under test
+    pub candidate: PathBuf,
+
+    /// Maximum allowed increase of mean CPU usage per process, e.g. "10%" or "5" (points)
+    #[arg(long, value_name = "LIMIT", value_parser = Threshold::parse_cpu)]
+    pub max_cpu_increase: Option<Threshold>,
+
+    /// Maximum allowed increase of peak memory per process, e.g. "50MB" or "20%"
+    #[arg(long, value_name = "LIMIT", value_parser = Threshold::parse_memory)]
+    pub max_rss_increase: Option<Threshold>,
+
+    /// Fail when a process is only present in one of the recordings
+    #[arg(long)]
+    pub fail_on_missing: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: OutputFormat,
+}
+
+#[derive(clap::Args)]
+pub struct PlotArgs {
+    /// Recording to plot (.csv, .json, .ndjson or .db)
+    pub file: PathBuf,
+
+    /// Metric to plot
+    #[arg(long, value_enum, default_value = "cpu")]
+    pub metric: PlotMetric,
+
+    /// Image to write, .svg or .png [default: <recording>-<metric>.svg]
+    #[arg(short, long, value_name = "PATH")]
+    pub out: Option<PathBuf>,
+
+    /// Only plot these processes, by name or NAME@HOST; repeatable
+    #[arg(short, long = "process", value_name = "NAME")]
+    pub processes: Vec<String>,
+
+    /// Use a logarithmic y axis
+    #[arg(long)]
+    pub log: bool,
+
+    /// Image width in pixels, from 16 to 8192
+    #[arg(long, default_value = "960", value_parser = clap::value_parser!(u32).range(16..=8192))]
+    pub width: u32,
+
+    /// Image height in pixels, from 16 to 8192
+    #[arg(long, default_value = "480", value_parser = clap::value_parser!(u32).range(16..=8192))]
+    pub height: u32,
+
+    /// Chart title [default: the metric and the recording's name]
+    #[arg(long)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PlotMetric {
+    /// CPU usage in percent of one CPU
+    Cpu,
+    /// Resident memory in MB
+    #[value(alias = "memory")]
+    Rss,
+    /// Number of threads
+    Threads,
+    /// Disk reads in KB/s
+    Read,
+    /// Disk writes in KB/s
+    Write,
+}
+
+impl PlotMetric {
+    pub fn metric(self) -> Metric {
+        match self {
+            PlotMetric::Cpu => Metric::Cpu,
+            PlotMetric::Rss => Metric::Memory,
+            PlotMetric::Threads => Metric::Threads,
+            PlotMetric::Read => Metric::DiskRead,
+            PlotMetric::Write => Metric::DiskWrite,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub struct QueryArgs {
+    /// Database written with --db or by saving a recording as .db
+    pub database: PathBuf,
+
+    /// What to list or aggregate
+    #[arg(value_enum, default_value = "sessions")]
+    pub query: Query,
+
+    /// Time buckets to aggregate samples over
+    #[arg(long, value_enum, default_value = "hour")]
+    pub per: Bucket,
+
+    /// Only include this session
+    #[arg(long, value_name = "ID")]
+    pub session: Option<i64>,
+
+    /// Only include processes with this name; repeatable
+    #[arg(short, long = "process", value_name = "NAME")]
+    pub processes: Vec<String>,
+
+    /// Run this SQL statement instead, e.g. "SELECT name, COUNT(*) FROM processes GROUP BY name"
+    #[arg(long, conflicts_with_all = ["query", "per", "session", "processes"])]
+    pub sql: Option<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "table")]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Query {
+    /// Stored capture sessions
+    Sessions,
+    /// Markers of every session
+    Markers,
+    /// Highest resident memory per process
+    PeakRss,
+    /// Average resident memory per process
+    MeanRss,
+    /// Highest CPU usage per process
+    PeakCpu,
+    /// Average CPU usage per process
+    MeanCpu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Bucket {
+    Minute,
+    Hour,
+    Day,
+    Session,
+}
+
+#[derive(clap::Args)]
+pub struct ReplayArgs {
+    /// Recording to load (.csv, .json, .ndjson, or .db for its latest session)
+    pub file: PathBuf,
+}
+
+#[derive(clap::Args)]
+pub struct ReportArgs {
+    /// Recording to export (.csv, .json, .ndjson or .db)
+    pub file: PathBuf,
+
+    /// HTML file to write [default: the recording's name with .html]
+    #[arg(short, long, value_name = "PATH")]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct TraceArgs {
+    /// Recording to export (.csv, .json, .ndjson or .db)
+    pub file: PathBuf,
+
+    /// Trace file to write [default: the recording's name with .trace.json]
+    #[arg(short, long, value_name = "PATH")]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
 }
@@ -0,0 +1,328 @@
+//! The `catz` command line: the TUI and every subcommand.
+
+use clap::Parser;
+use color_eyre::{Result, eyre::bail};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    agent::{self, Address, Client, Listener},
+    args::{Args, Command},
+    commands,
+    config::Config,
+    domain::{
+        column::{Column, SortOrder},
+        process::{MAX_HOSTS, ProcessManager, WatchTarget},
+        replay::ReplayState,
+        state::AppState,
+    },
+    event::{self, AppEvent},
+    recording::{Recording, sqlite::Database},
+    sampler::{RESOLVE_INTERVAL, SamplerEvent, SamplerHandle, Schedule},
+    session::Session,
+    source::{FakeSource, SourceKind},
+    terminal::{self, TerminalHandler},
+    ui,
+    web::WebServer,
+};
+
+/// Longest wait between redraws when nothing changed, e.g. to count down
+/// a running burst.
+const IDLE_REDRAW: Duration = Duration::from_secs(1);
+/// How often replay playback advances.
+const REPLAY_TICK: Duration = Duration::from_millis(50);
+
+fn run(
+    mut state: AppState,
+    source: SourceKind,
+    watch_patterns: &[String],
+    web: Option<&str>,
+) -> Result<()> {
+    let web = web.map(WebServer::start).transpose()?;
+    if let Some(web) = &web {
+        state.status_message = Some(format!("Dashboard at {}", web.url()));
+    }
+
+    // Only used to search the process list; sampling happens on its own thread
+    let mut search = source.open();
+    let running = search.processes();
+
+    if let Some(session) = state
+        .session_name
+        .as_deref()
+        .map(Session::load)
+        .transpose()?
+        .flatten()
+    {
+        session.restore(&mut state, &running);
+    }
+    for pattern in watch_patterns {
+        for (pid, name) in ProcessManager::find_matching_processes(pattern, &running) {
+            let host = state.host_of(pid);
+            state.process_manager().add_target(WatchTarget {
+                host,
+                from_watchlist: true,
+                ..WatchTarget::new(name, pid)
+            });
+        }
+    }
+
+    let mut terminal = TerminalHandler::new(state.config.mouse)?;
+    terminal::setup_panic_hook()?;
+
+    let (events, receiver) = mpsc::channel();
+    event::spawn_input_thread(events.clone());
+    let marks_socket = match state.replay {
+        Some(_) => None,
+        None => listen_for_marks(&mut state, &events),
+    };
+    let sampler = SamplerHandle::spawn(source.open(), events);
+    sampler.update(Schedule::from_state(&state));
+
+    let mut view = ui::ViewState::default();
+    let mut needs_redraw = true;
+    let mut last_draw = Instant::now();
+    let mut last_resolve = Instant::now();
+    loop {
+        if needs_redraw || last_draw.elapsed() >= IDLE_REDRAW {
+            terminal
+                .terminal
+                .draw(|f| ui::render(f, &state, &mut view))?;
+            needs_redraw = false;
+            last_draw = Instant::now();
+        }
+
+        let timeout = match &state.replay {
+            Some(_) => REPLAY_TICK,
+            None => IDLE_REDRAW.saturating_sub(last_draw.elapsed()),
+        };
+        match receiver.recv_timeout(timeout) {
+            Ok(AppEvent::Key(key)) => {
+                event::handle_key_events(key, &mut state, search.as_mut(), view.page_rows)?;
+                sampler.update(Schedule::from_state(&state));
+                needs_redraw = true;
+            }
+            Ok(AppEvent::Mouse(mouse)) => {
+                let size = terminal.terminal.size()?;
+                needs_redraw |= event::handle_mouse_event(mouse, &mut state, size);
+            }
+            Ok(AppEvent::Resize) => needs_redraw = true,
+            Ok(AppEvent::Mark(text)) => {
+                event::add_marker(&mut state, &text);
+                needs_redraw = true;
+            }
+            Ok(AppEvent::Sampler(SamplerEvent::Sample(sample))) => {
+                if let Err(e) = state.record_frame(sample.taken_at, &sample.processes) {
+                    state.status_message = Some(e.to_string());
+                }
+                if let (Some(web), Some(frame)) = (&web, state.history.frames().last()) {
+                    web.publish(frame);
+                }
+                if state.apply_samples(&sample.sampled_pids, sample.processes) {
+                    sampler.update(Schedule::from_state(&state));
+                }
+                needs_redraw = true;
+            }
+            Ok(AppEvent::Sampler(SamplerEvent::Overhead(overhead))) => {
+                state.overhead = overhead;
+                needs_redraw = true;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if state.should_quit {
+            break;
+        }
+
+        if let Some(replay) = &mut state.replay {
+            if replay.advance() {
+                state.sync_replay();
+                needs_redraw = true;
+            }
+        } else if last_resolve.elapsed() >= RESOLVE_INTERVAL
+            && state.watch_targets().iter().any(|t| t.pid.is_none())
+        {
+            let running = search.processes();
+            state.resolve_targets(&running);
+            sampler.update(Schedule::from_state(&state));
+            last_resolve = Instant::now();
+        }
+    }
+
+    terminal.cleanup()?;
+    if let Some(path) = marks_socket {
+        let _ = fs::remove_file(path);
+    }
+    if let Some(name) = &state.session_name {
+        Session::capture(&state).save(name)?;
+    }
+    Ok(())
+}
+
+/// Takes markers from `catz mark --tui`, returning the socket to remove on
+/// exit. Only one TUI at a time can take them.
+fn listen_for_marks(state: &mut AppState, events: &Sender<AppEvent>) -> Option<PathBuf> {
+    if !cfg!(unix) {
+        return None;
+    }
+    let path = agent::tui_socket_path()?;
+    match Listener::bind(&Address::Unix(path.clone())) {
+        Ok(listener) => {
+            let events = events.clone();
+            agent::serve_marks(listener, move |text| {
+                events.send(AppEvent::Mark(text)).is_ok()
+            });
+            Some(path)
+        }
+        Err(e) => {
+            state.status_message = Some(format!("Not taking markers from `catz mark --tui`: {e}"));
+            None
+        }
+    }
+}
+
+/// Parses the command line and runs what it asks for, as the `catz`
+/// binary does.
+pub fn main() -> Result<()> {
+    color_eyre::install()?;
+    let mut args = Args::parse();
+    if args.web.is_some() && !matches!(args.command, None | Some(Command::Connect(_))) {
+        bail!("--web only works with the TUI and `catz connect`");
+    }
+    if args.db.is_some()
+        && !matches!(
+            args.command,
+            None | Some(Command::Connect(_) | Command::Agent(_))
+        )
+    {
+        bail!("--db only works with the TUI, `catz connect` and `catz agent`");
+    }
+    match args.command.take() {
+        Some(Command::Assert(assert_args)) => commands::assert::run(assert_args),
+        Some(Command::Bench(bench_args)) => commands::bench::run(bench_args),
+        Some(Command::Compare(compare_args)) => commands::compare::run(compare_args),
+        Some(Command::Mark(mark_args)) => commands::mark::run(mark_args),
+        Some(Command::Plot(plot_args)) => commands::plot::run(plot_args),
+        Some(Command::Query(query_args)) => commands::query::run(query_args),
+        Some(Command::Report(report_args)) => {
+            commands::report::run(report_args, load_config(&args)?)
+        }
+        Some(Command::Trace(trace_args)) => commands::trace::run(trace_args, load_config(&args)?),
+        Some(Command::Replay(replay_args)) => {
+            let mut config = Config::load(args.config.as_deref())?;
+            let recording = Recording::load(&replay_args.file)?;
+            if recording.samples.iter().any(|s| s.host.is_some())
+                && !config.columns.contains(&Column::Host)
+            {
+                config.columns.insert(0, Column::Host);
+            }
+            let replay = ReplayState::new(
+                replay_args.file.display().to_string(),
+                recording.into_history()?,
+            );
+            // Replays show the recording only, so there is nothing to sample
+            run(
+                AppState::new_replay(config, replay),
+                SourceKind::Fake(FakeSource::empty()),
+                &[],
+                None,
+            )
+        }
+        Some(Command::Connect(connect_args)) => {
+            let hosts = connect_args.hosts;
+            if hosts.len() > MAX_HOSTS {
+                bail!("at most {MAX_HOSTS} agents can be connected at once");
+            }
+            for (i, host) in hosts.iter().enumerate() {
+                if hosts[..i].iter().any(|h| h.name == host.name) {
+                    bail!("host name {} is used more than once", host.name);
+                }
+                // Fail early with a clear error instead of an empty table
+                Client::connect(&host.address)?;
+            }
+            let mut config = load_config(&args)?;
+            let watch_patterns = watch_patterns(&args, &config)?;
+            let mut sort = SortOrder::default();
+            if hosts.len() > 1 {
+                if !config.columns.contains(&Column::Host) {
+                    config.columns.insert(0, Column::Host);
+                }
+                sort.column = Some(Column::Host);
+            }
+            let mut state = AppState::new(config);
+            state.sort = sort;
+            state.session_name = connect_args.session;
+            state.hosts = hosts.iter().map(|host| host.name.clone()).collect();
+            store_in_database(&args, &mut state, "catz connect")?;
+            run(
+                state,
+                SourceKind::Remote(hosts),
+                &watch_patterns,
+                args.web.as_deref(),
+            )
+        }
+        Some(Command::Agent(agent_args)) => {
+            let config = load_config(&args)?;
+            let watch_patterns = watch_patterns(&args, &config)?;
+            commands::agent::run(
+                agent_args,
+                config,
+                source_kind(&args)?,
+                &watch_patterns,
+                args.db.as_deref(),
+            )
+        }
+        None => {
+            let config = load_config(&args)?;
+            let watch_patterns = watch_patterns(&args, &config)?;
+            let mut state = AppState::new(config);
+            if !args.no_session {
+                state.session_name = Some(args.session.clone());
+            }
+            store_in_database(&args, &mut state, "catz")?;
+            run(
+                state,
+                source_kind(&args)?,
+                &watch_patterns,
+                args.web.as_deref(),
+            )
+        }
+    }
+}
+
+/// The config file with command-line overrides applied.
+fn load_config(args: &Args) -> Result<Config> {
+    let mut config = Config::load(args.config.as_deref())?;
+    if let Some(interval) = args.interval {
+        config.update_interval = interval;
+    }
+    Ok(config)
+}
+
+/// Starts a session in the `--db` database, if one was given.
+fn store_in_database(args: &Args, state: &mut AppState, source: &str) -> Result<()> {
+    if let Some(path) = &args.db {
+        state.store_in(Database::open(path)?, source)?;
+    }
+    Ok(())
+}
+
+fn watch_patterns(args: &Args, config: &Config) -> Result<Vec<String>> {
+    Ok(match &args.watchlist {
+        Some(name) => config.watchlist(name)?.to_vec(),
+        None => Vec::new(),
+    })
+}
+
+fn source_kind(args: &Args) -> Result<SourceKind> {
+    Ok(match (&args.proc_root, &args.fixture) {
+        (Some(root), _) => SourceKind::Procfs(root.clone()),
+        (_, Some(fixture)) => SourceKind::Fake(FakeSource::load(fixture)?),
+        _ => SourceKind::Sysinfo,
+    })
+}
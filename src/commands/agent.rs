@@ -0,0 +1,49 @@
+use color_eyre::{Result, eyre::eyre};
+use std::path::Path;
+
+use crate::{
+    agent::{self, Address, Agent, Listener},
+    config::Config,
+    recording::sqlite::Database,
+    source::SourceKind,
+};
+
+use crate::args::AgentArgs;
+
+pub fn run(
+    args: AgentArgs,
+    config: Config,
+    source: SourceKind,
+    patterns: &[String],
+    database: Option<&Path>,
+) -> Result<()> {
+    let mut addresses = Vec::new();
+    if !args.no_socket {
+        let path = match args.socket {
+            Some(path) => path,
+            None => agent::default_socket_path()
+                .ok_or_else(|| eyre!("cannot locate a socket directory, use --socket"))?,
+        };
+        addresses.push(Address::Unix(path));
+    }
+    if let Some(addr) = args.tcp {
+        addresses.push(Address::Tcp(addr));
+    }
+    let listeners = addresses
+        .iter()
+        .map(Listener::bind)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut agent = Agent::new(config, source);
+    agent.stop_on_signal()?;
+    if let Some(path) = database {
+        agent.store_in(Database::open(path)?)?;
+    }
+    for pattern in patterns.iter().chain(&args.patterns) {
+        agent.watch(pattern, None);
+    }
+    for listener in &listeners {
+        eprintln!("catz agent listening on {}", listener.address());
+    }
+    agent.serve(listeners)
+}
@@ -0,0 +1,115 @@
+use color_eyre::{
+    Result,
+    eyre::{bail, eyre},
+};
+use std::{
+    process::{Child, Command},
+    thread,
+    time::Instant,
+};
+
+use crate::guard::{Bytes, GuardError, GuardReport, ResourceGuard};
+
+use crate::args::{AssertArgs, OutputFormat};
+
+pub fn run(args: AssertArgs) -> Result<()> {
+    let mut child = match args.command.split_first() {
+        Some((program, rest)) => Some(
+            Command::new(program)
+                .args(rest)
+                .spawn()
+                .map_err(|e| eyre!("failed to start {program}: {e}"))?,
+        ),
+        None => None,
+    };
+    let pid = match (&child, args.pid) {
+        (Some(child), _) => child.id(),
+        (None, Some(pid)) => pid,
+        (None, None) => bail!("either --pid or a command is required"),
+    };
+
+    let mut guard = ResourceGuard::watch(pid).interval(args.interval);
+    if let Some(mb) = args.max_rss {
+        guard = guard.max_rss(Bytes((mb * 1024.0 * 1024.0) as u64));
+    }
+    if let Some(limit) = args.max_avg_cpu {
+        guard = guard.max_avg_cpu(limit);
+    }
+    if let Some(limit) = args.max_cpu {
+        guard = guard.max_cpu(limit);
+    }
+
+    match &mut child {
+        Some(child) => wait_for_child(child, &args)?,
+        None => {
+            // `--duration` is required with `--pid`
+            guard.wait(args.duration.unwrap_or_default());
+        }
+    }
+
+    let report = match guard.finish() {
+        Ok(report) => report,
+        Err(GuardError::Exceeded(report)) => *report,
+        Err(e @ GuardError::NoSamples { .. }) => bail!("{e}; did it exit right away?"),
+    };
+    match args.format {
+        OutputFormat::Table => print_table(&report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    if !report.passed() {
+        bail!("{} exceeded its limits", report.name);
+    }
+    Ok(())
+}
+
+/// Waits for the command to exit, killing it once `--duration` passes.
+fn wait_for_child(child: &mut Child, args: &AssertArgs) -> Result<()> {
+    // A deadline too far away to represent is as good as none
+    let deadline = args
+        .duration
+        .and_then(|duration| Instant::now().checked_add(duration));
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                eprintln!("warning: command exited with {status}");
+            }
+            return Ok(());
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            child.kill()?;
+            child.wait()?;
+            return Ok(());
+        }
+        thread::sleep(args.interval);
+    }
+}
+
+fn print_table(report: &GuardReport) {
+    println!(
+        "{} ({}): {} samples over {:.1}s{}",
+        report.name,
+        report.pid,
+        report.samples,
+        report.duration_secs,
+        if report.exited { ", exited" } else { "" },
+    );
+    println!("  {:<12} {:>10} {:>10}", "Metric", "Measured", "Limit");
+    let limit = |limit: Option<f64>| limit.map_or("-".to_string(), |l| format!("{l:.1}"));
+    for (label, measured, budget) in [
+        ("Peak RSS MB", report.peak_rss_mb, report.budget.max_rss_mb),
+        ("Avg CPU%", report.cpu_usage.mean, report.budget.max_avg_cpu),
+        ("Peak CPU%", report.cpu_usage.max, report.budget.max_cpu),
+    ] {
+        println!("  {:<12} {:>10.1} {:>10}", label, measured, limit(budget));
+    }
+
+    println!();
+    if report.passed() {
+        println!("PASS: within budget");
+    } else {
+        for violation in &report.violations {
+            println!("FAIL: {violation}");
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use color_eyre::{
+    Result,
+    eyre::{bail, eyre},
+};
+use serde::Serialize;
+use std::{
+    process::{Command, Stdio},
+    time::Instant,
+};
+
+use crate::domain::stats::{self, Comparison, Summary};
+
+use crate::args::{BenchArgs, OutputFormat};
+
+#[derive(Debug, Clone, Copy)]
+struct RunMeasurement {
+    wall_secs: f64,
+    cpu_secs: f64,
+    peak_rss_mb: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct BenchResult {
+    command: String,
+    runs: usize,
+    wall_time_secs: Summary,
+    cpu_time_secs: Summary,
+    peak_rss_mb: Summary,
+}
+
+#[derive(Debug, Serialize)]
+struct BenchComparison {
+    wall_time_secs: Option<Comparison>,
+    cpu_time_secs: Option<Comparison>,
+    peak_rss_mb: Option<Comparison>,
+}
+
+#[derive(Debug, Serialize)]
+struct BenchReport {
+    benchmarks: Vec<BenchResult>,
+    /// Statistics of the second command relative to the first
+    comparison: Option<BenchComparison>,
+}
+
+pub fn run(args: BenchArgs) -> Result<()> {
+    if args.runs == 0 {
+        bail!("--runs must be at least 1");
+    }
+
+    let mut commands = vec![args.command.clone()];
+    if let Some(compare) = &args.compare {
+        let argv = shlex::split(compare)
+            .filter(|argv| !argv.is_empty())
+            .ok_or_else(|| eyre!("invalid --compare command: {compare:?}"))?;
+        commands.push(argv);
+    }
+
+    let mut measurements = Vec::new();
+    for argv in &commands {
+        let label = shlex::try_join(argv.iter().map(String::as_str))?;
+        eprintln!("Benchmarking {label} ({} runs)", args.runs);
+        let mut runs = Vec::with_capacity(args.runs);
+        for _ in 0..args.runs {
+            runs.push(measure_run(argv, &args)?);
+        }
+        measurements.push((label, runs));
+    }
+
+    let report = build_report(&measurements);
+    match args.format {
+        OutputFormat::Table => print_table(&report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+fn build_report(measurements: &[(String, Vec<RunMeasurement>)]) -> BenchReport {
+    let series = |runs: &[RunMeasurement], f: fn(&RunMeasurement) -> f64| -> Vec<f64> {
+        runs.iter().map(f).collect()
+    };
+
+    let benchmarks = measurements
+        .iter()
+        .map(|(command, runs)| BenchResult {
+            command: command.clone(),
+            runs: runs.len(),
+            wall_time_secs: summarize(&series(runs, |m| m.wall_secs)),
+            cpu_time_secs: summarize(&series(runs, |m| m.cpu_secs)),
+            peak_rss_mb: summarize(&series(runs, |m| m.peak_rss_mb)),
+        })
+        .collect();
+
+    let comparison = match measurements {
+        [(_, a), (_, b)] => Some(BenchComparison {
+            wall_time_secs: stats::welch_t_test(
+                &series(a, |m| m.wall_secs),
+                &series(b, |m| m.wall_secs),
+            ),
+            cpu_time_secs: stats::welch_t_test(
+                &series(a, |m| m.cpu_secs),
+                &series(b, |m| m.cpu_secs),
+            ),
+            peak_rss_mb: stats::welch_t_test(
+                &series(a, |m| m.peak_rss_mb),
+                &series(b, |m| m.peak_rss_mb),
+            ),
+        }),
+        _ => None,
+    };
+
+    BenchReport {
+        benchmarks,
+        comparison,
+    }
+}
+
+fn summarize(samples: &[f64]) -> Summary {
+    // `run` guarantees at least one run per command
+    Summary::from_samples(samples).expect("benchmark has at least one run")
+}
+
+fn print_table(report: &BenchReport) {
+    for (i, bench) in report.benchmarks.iter().enumerate() {
+        println!("Benchmark {}: {}", (b'A' + i as u8) as char, bench.command);
+        println!(
+            "  {:<15} {:>12} {:>12} {:>12} {:>12}",
+            "Metric", "Mean", "Stddev", "Min", "Max"
+        );
+        for (label, summary) in [
+            ("Wall time (s)", &bench.wall_time_secs),
+            ("CPU time (s)", &bench.cpu_time_secs),
+            ("Peak RSS (MB)", &bench.peak_rss_mb),
+        ] {
+            println!(
+                "  {:<15} {:>12.3} {:>12.3} {:>12.3} {:>12.3}",
+                label, summary.mean, summary.stddev, summary.min, summary.max
+            );
+        }
+        println!();
+    }
+
+    if let Some(comparison) = &report.comparison {
+        println!("Comparison: B relative to A");
+        println!(
+            "  {:<15} {:>12} {:>12}  Result",
+            "Metric", "B / A", "p-value"
+        );
+        for (label, result) in [
+            ("Wall time", &comparison.wall_time_secs),
+            ("CPU time", &comparison.cpu_time_secs),
+            ("Peak RSS", &comparison.peak_rss_mb),
+        ] {
+            match result {
+                Some(c) => println!(
+                    "  {:<15} {:>11.3}x {:>12.4}  {}",
+                    label,
+                    c.ratio,
+                    c.p_value,
+                    if c.significant {
+                        "significant"
+                    } else {
+                        "not significant"
+                    }
+                ),
+                None => println!(
+                    "  {:<15} {:>12} {:>12}  needs at least 2 runs",
+                    label, "-", "-"
+                ),
+            }
+        }
+    }
+}
+
+fn measure_run(argv: &[String], args: &BenchArgs) -> Result<RunMeasurement> {
+    let (program, rest) = argv
+        .split_first()
+        .ok_or_else(|| eyre!("no command given"))?;
+    let mut command = Command::new(program);
+    command.args(rest);
+    if !args.show_output {
+        command.stdout(Stdio::null()).stderr(Stdio::null());
+    }
+
+    let start = Instant::now();
+    let child = command
+        .spawn()
+        .map_err(|e| eyre!("failed to start {program}: {e}"))?;
+    let (success, cpu_secs, peak_rss_mb) = wait_with_usage(child)?;
+    let wall_secs = start.elapsed().as_secs_f64();
+
+    if !success && !args.ignore_failure {
+        bail!("{program} exited with a non-zero status (use --ignore-failure to continue)");
+    }
+
+    Ok(RunMeasurement {
+        wall_secs,
+        cpu_secs,
+        peak_rss_mb,
+    })
+}
+
+/// Waits for the child and returns (success, CPU seconds, peak RSS in MB).
+#[cfg(unix)]
+fn wait_with_usage(child: std::process::Child) -> Result<(bool, f64, f64)> {
+    let pid = child.id() as libc::pid_t;
+    let mut status = 0;
+    // SAFETY: rusage is plain old data and wait4 fully initialises it on success
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let ret = unsafe { libc::wait4(pid, &mut status, 0, &mut usage) };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    let timeval_secs = |tv: libc::timeval| tv.tv_sec as f64 + tv.tv_usec as f64 / 1_000_000.0;
+    let cpu_secs = timeval_secs(usage.ru_utime) + timeval_secs(usage.ru_stime);
+    // ru_maxrss is reported in bytes on macOS and kilobytes elsewhere
+    let peak_rss_mb = if cfg!(target_os = "macos") {
+        usage.ru_maxrss as f64 / 1024.0 / 1024.0
+    } else {
+        usage.ru_maxrss as f64 / 1024.0
+    };
+    let success = libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0;
+    Ok((success, cpu_secs, peak_rss_mb))
+}
+
+#[cfg(not(unix))]
+fn wait_with_usage(mut child: std::process::Child) -> Result<(bool, f64, f64)> {
+    // Resource usage of reaped children is only available on unix
+    let status = child.wait()?;
+    Ok((status.success(), 0.0, 0.0))
+}
@@ -0,0 +1,407 @@
+use color_eyre::{Result, eyre::bail};
+use serde::Serialize;
+
+use crate::{
+    domain::stats::Summary,
+    recording::{RecordedSample, Recording},
+    utils,
+};
+
+use crate::args::{CompareArgs, OutputFormat};
+
+/// Allowed increase of a metric between baseline and candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Threshold {
+    /// Relative to the baseline value, in percent
+    Percent(f64),
+    /// In the metric's own unit (CPU percentage points or MB)
+    Absolute(f64),
+}
+
+impl Threshold {
+    fn exceeded_by(&self, baseline: f64, candidate: f64) -> bool {
+        match *self {
+            Threshold::Percent(percent) => candidate > baseline * (1.0 + percent / 100.0),
+            Threshold::Absolute(limit) => candidate - baseline > limit,
+        }
+    }
+
+    /// Parses a CPU limit, "10%" relative to the baseline or "5" in
+    /// percentage points.
+    pub fn parse_cpu(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        match Self::parse_percent(input)? {
+            Some(threshold) => Ok(threshold),
+            None => input
+                .parse::<f64>()
+                .ok()
+                .filter(|points| points.is_finite() && *points >= 0.0)
+                .map(Threshold::Absolute)
+                .ok_or_else(|| {
+                    format!("invalid CPU limit {input:?}, expected e.g. 10% or 5 (points)")
+                }),
+        }
+    }
+
+    /// Parses a memory limit, "20%" relative to the baseline or a size
+    /// such as "50MB".
+    pub fn parse_memory(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        match Self::parse_percent(input)? {
+            Some(threshold) => Ok(threshold),
+            None => utils::parse_size_mb(input).map(Threshold::Absolute),
+        }
+    }
+
+    fn parse_percent(input: &str) -> Result<Option<Self>, String> {
+        let Some(percent) = input.strip_suffix('%') else {
+            return Ok(None);
+        };
+        percent
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|percent| percent.is_finite() && *percent >= 0.0)
+            .map(|percent| Some(Threshold::Percent(percent)))
+            .ok_or_else(|| format!("invalid percentage {input:?}"))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MetricSummary {
+    /// Number of distinct PIDs seen, more than one means the process restarted
+    instances: usize,
+    cpu_usage: Summary,
+    memory_mb: Summary,
+}
+
+/// Recording a process was seen in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Side {
+    Baseline,
+    Candidate,
+}
+
+#[derive(Debug, Serialize)]
+struct ProcessDiff {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    baseline: Option<MetricSummary>,
+    candidate: Option<MetricSummary>,
+    /// Set when the process is missing from the other recording
+    only_in: Option<Side>,
+    cpu_exceeded: bool,
+    rss_exceeded: bool,
+}
+
+impl ProcessDiff {
+    fn failed(&self, fail_on_missing: bool) -> bool {
+        self.cpu_exceeded || self.rss_exceeded || fail_on_missing && self.only_in.is_some()
+    }
+
+    fn label(&self) -> String {
+        match &self.group {
+            Some(group) => format!("{} ({group})", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CompareReport {
+    max_cpu_increase: Option<Threshold>,
+    max_rss_increase: Option<Threshold>,
+    fail_on_missing: bool,
+    processes: Vec<ProcessDiff>,
+    passed: bool,
+}
+
+pub fn run(args: CompareArgs) -> Result<()> {
+    let baseline = Recording::load(&args.baseline)?;
+    let candidate = Recording::load(&args.candidate)?;
+
+    let report = build_report(&baseline, &candidate, &args);
+    match args.format {
+        OutputFormat::Table => print_table(&report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    if !report.passed {
+        bail!("the candidate failed the comparison");
+    }
+    Ok(())
+}
+
+/// Name and group a process is aligned by across recordings.
+type ProcessKey<'a> = (&'a str, Option<&'a str>);
+
+fn key(sample: &RecordedSample) -> ProcessKey<'_> {
+    (&sample.name, sample.group.as_deref())
+}
+
+fn summarize(recording: &Recording, process: ProcessKey) -> Option<MetricSummary> {
+    let samples: Vec<&RecordedSample> = recording
+        .samples
+        .iter()
+        .filter(|s| key(s) == process)
+        .collect();
+    let cpu: Vec<f64> = samples.iter().map(|s| s.cpu_usage as f64).collect();
+    let memory: Vec<f64> = samples.iter().map(|s| s.memory_mb).collect();
+    let mut pids: Vec<u32> = samples.iter().map(|s| s.pid).collect();
+    pids.sort_unstable();
+    pids.dedup();
+    Some(MetricSummary {
+        instances: pids.len(),
+        cpu_usage: Summary::from_samples(&cpu)?,
+        memory_mb: Summary::from_samples(&memory)?,
+    })
+}
+
+fn build_report(baseline: &Recording, candidate: &Recording, args: &CompareArgs) -> CompareReport {
+    // In order of first appearance, baseline first
+    let mut keys: Vec<ProcessKey> = Vec::new();
+    for sample in baseline.samples.iter().chain(&candidate.samples) {
+        if !keys.contains(&key(sample)) {
+            keys.push(key(sample));
+        }
+    }
+
+    let processes: Vec<ProcessDiff> = keys
+        .into_iter()
+        .map(|process| {
+            let before = summarize(baseline, process);
+            let after = summarize(candidate, process);
+            let only_in = match (&before, &after) {
+                (Some(_), None) => Some(Side::Baseline),
+                (None, Some(_)) => Some(Side::Candidate),
+                _ => None,
+            };
+            let (cpu_exceeded, rss_exceeded) = match (&before, &after) {
+                (Some(before), Some(after)) => (
+                    args.max_cpu_increase.is_some_and(|t| {
+                        t.exceeded_by(before.cpu_usage.mean, after.cpu_usage.mean)
+                    }),
+                    args.max_rss_increase
+                        .is_some_and(|t| t.exceeded_by(before.memory_mb.max, after.memory_mb.max)),
+                ),
+                _ => (false, false),
+            };
+            ProcessDiff {
+                name: process.0.to_string(),
+                group: process.1.map(String::from),
+                baseline: before,
+                candidate: after,
+                only_in,
+                cpu_exceeded,
+                rss_exceeded,
+            }
+        })
+        .collect();
+
+    let passed = processes.iter().all(|p| !p.failed(args.fail_on_missing));
+    CompareReport {
+        max_cpu_increase: args.max_cpu_increase,
+        max_rss_increase: args.max_rss_increase,
+        fail_on_missing: args.fail_on_missing,
+        processes,
+        passed,
+    }
+}
+
+fn print_table(report: &CompareReport) {
+    println!(
+        "{:<24} {:>10} {:>10} {:>9}   {:>11} {:>11} {:>9}",
+        "Process", "CPU% base", "CPU% cand", "Δ", "Peak MB base", "Peak MB cand", "Δ"
+    );
+
+    for process in &report.processes {
+        let (Some(before), Some(after)) = (&process.baseline, &process.candidate) else {
+            let side = match process.only_in {
+                Some(Side::Baseline) => "baseline",
+                _ => "candidate",
+            };
+            let failed = process.failed(report.fail_on_missing);
+            println!(
+                "{:<24} only present in {side}{}",
+                process.label(),
+                if failed { "  FAIL" } else { "" },
+            );
+            continue;
+        };
+        let failed = process.failed(report.fail_on_missing);
+        println!(
+            "{:<24} {:>10.1} {:>10.1} {:>+9.1}   {:>11.1} {:>11.1} {:>+9.1}{}",
+            process.label(),
+            before.cpu_usage.mean,
+            after.cpu_usage.mean,
+            after.cpu_usage.mean - before.cpu_usage.mean,
+            before.memory_mb.max,
+            after.memory_mb.max,
+            after.memory_mb.max - before.memory_mb.max,
+            if failed { "  FAIL" } else { "" },
+        );
+    }
+
+    println!();
+    if report.passed {
+        println!("PASS: no thresholds exceeded");
+    } else {
+        for process in &report.processes {
+            if process.cpu_exceeded {
+                println!("FAIL: {} mean CPU increase exceeds limit", process.label());
+            }
+            if process.rss_exceeded {
+                println!(
+                    "FAIL: {} peak memory increase exceeds limit",
+                    process.label()
+                );
+            }
+            if report.fail_on_missing && process.only_in.is_some() {
+                println!("FAIL: {} is missing from a recording", process.label());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(name: &str, group: Option<&str>, cpu_usage: f32, memory_mb: f64) -> RecordedSample {
+        RecordedSample {
+            name: name.to_string(),
+            pid: 42,
+            cpu_usage,
+            memory_mb,
+            threads: 0,
+            read_kb_s: 0.0,
+            write_kb_s: 0.0,
+            elapsed_ms: 0,
+            host: None,
+            group: group.map(String::from),
+        }
+    }
+
+    fn recording(samples: Vec<RecordedSample>) -> Recording {
+        Recording {
+            samples,
+            ..Recording::default()
+        }
+    }
+
+    fn args(fail_on_missing: bool) -> CompareArgs {
+        CompareArgs {
+            baseline: "baseline.csv".into(),
+            candidate: "candidate.csv".into(),
+            max_cpu_increase: Some(Threshold::Percent(10.0)),
+            max_rss_increase: Some(Threshold::Absolute(50.0)),
+            fail_on_missing,
+            format: OutputFormat::Json,
+        }
+    }
+
+    #[test]
+    fn flags_processes_over_their_limits() {
+        let baseline = recording(vec![
+            sample("nginx", None, 10.0, 100.0),
+            sample("nginx", None, 30.0, 120.0),
+            sample("redis", None, 50.0, 300.0),
+        ]);
+        let candidate = recording(vec![
+            sample("nginx", None, 22.0, 170.0),
+            sample("redis", None, 50.0, 400.0),
+        ]);
+        let report = build_report(&baseline, &candidate, &args(false));
+        assert!(!report.passed);
+        let [nginx, redis] = &report.processes[..] else {
+            panic!("expected nginx and redis");
+        };
+        assert_eq!(nginx.name, "nginx");
+        assert_eq!(nginx.baseline.as_ref().unwrap().cpu_usage.mean, 20.0);
+        assert!(!nginx.cpu_exceeded);
+        assert!(!nginx.rss_exceeded);
+        assert!(!redis.cpu_exceeded);
+        assert!(redis.rss_exceeded);
+    }
+
+    #[test]
+    fn aligns_processes_by_name_and_group() {
+        let baseline = recording(vec![
+            sample("worker", Some("api"), 10.0, 100.0),
+            sample("worker", Some("jobs"), 40.0, 100.0),
+        ]);
+        let candidate = recording(vec![
+            sample("worker", Some("jobs"), 10.0, 100.0),
+            sample("worker", Some("api"), 40.0, 100.0),
+        ]);
+        let report = build_report(&baseline, &candidate, &args(false));
+        let groups: Vec<_> = report
+            .processes
+            .iter()
+            .map(|p| p.group.as_deref())
+            .collect();
+        assert_eq!(groups, [Some("api"), Some("jobs")]);
+        assert!(report.processes[0].cpu_exceeded);
+        assert!(!report.processes[1].cpu_exceeded);
+        assert_eq!(report.processes[0].label(), "worker (api)");
+    }
+
+    #[test]
+    fn flags_processes_missing_from_a_recording() {
+        let baseline = recording(vec![
+            sample("nginx", None, 10.0, 100.0),
+            sample("cron", None, 1.0, 10.0),
+        ]);
+        let candidate = recording(vec![
+            sample("nginx", None, 10.0, 100.0),
+            sample("sidecar", None, 1.0, 10.0),
+        ]);
+        let report = build_report(&baseline, &candidate, &args(false));
+        let only_in: Vec<_> = report.processes.iter().map(|p| p.only_in).collect();
+        assert_eq!(only_in, [None, Some(Side::Baseline), Some(Side::Candidate)]);
+        assert!(report.passed);
+
+        let report = build_report(&baseline, &candidate, &args(true));
+        assert!(!report.passed);
+        let report = build_report(&baseline, &baseline, &args(true));
+        assert!(report.passed);
+    }
+
+    #[test]
+    fn parses_cpu_limits_as_percentages() {
+        assert_eq!(Threshold::parse_cpu("10%"), Ok(Threshold::Percent(10.0)));
+        assert_eq!(Threshold::parse_cpu(" 2.5 % "), Ok(Threshold::Percent(2.5)));
+        assert_eq!(Threshold::parse_cpu("5"), Ok(Threshold::Absolute(5.0)));
+        for input in ["", "5MB", "1g", "fast", "-5", "NaN", "inf%", "%"] {
+            assert!(Threshold::parse_cpu(input).is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn parses_memory_limits_as_sizes() {
+        assert_eq!(Threshold::parse_memory("20%"), Ok(Threshold::Percent(20.0)));
+        assert_eq!(
+            Threshold::parse_memory("50MB"),
+            Ok(Threshold::Absolute(50.0))
+        );
+        assert_eq!(
+            Threshold::parse_memory("1GiB"),
+            Ok(Threshold::Absolute(1024.0))
+        );
+        assert!(Threshold::parse_memory("5 parsecs").is_err());
+    }
+
+    #[test]
+    fn exceeds_relative_and_absolute_limits() {
+        let percent = Threshold::Percent(10.0);
+        assert!(!percent.exceeded_by(50.0, 55.0));
+        assert!(percent.exceeded_by(50.0, 55.1));
+        let absolute = Threshold::Absolute(5.0);
+        assert!(!absolute.exceeded_by(50.0, 55.0));
+        assert!(absolute.exceeded_by(50.0, 55.1));
+        assert!(!absolute.exceeded_by(50.0, 20.0));
+    }
+}
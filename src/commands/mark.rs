@@ -0,0 +1,31 @@
+use color_eyre::{
+    Result,
+    eyre::{bail, eyre},
+};
+
+use crate::agent::{
+    self, Address, Client,
+    protocol::{Request, Response},
+};
+
+use crate::args::MarkArgs;
+
+pub fn run(args: MarkArgs) -> Result<()> {
+    let address = match args.agent {
+        Some(address) => address,
+        None if args.tui => Address::Unix(
+            agent::tui_socket_path().ok_or_else(|| eyre!("cannot locate the TUI's socket"))?,
+        ),
+        None => Address::Unix(
+            agent::default_socket_path()
+                .ok_or_else(|| eyre!("cannot locate the agent's socket, use --agent"))?,
+        ),
+    };
+    let mut client = Client::connect(&address)?;
+    match client.request(&Request::Mark {
+        text: args.text.join(" "),
+    })? {
+        Response::Error { message } => bail!("{address}: {message}"),
+        _ => Ok(()),
+    }
+}
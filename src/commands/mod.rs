@@ -0,0 +1,9 @@
+pub mod agent;
+pub mod assert;
+pub mod bench;
+pub mod compare;
+pub mod mark;
+pub mod plot;
+pub mod query;
+pub mod report;
+pub mod trace;
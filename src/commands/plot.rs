@@ -0,0 +1,83 @@
+use color_eyre::{Result, eyre::bail};
+use std::path::PathBuf;
+
+use crate::{
+    domain::process::ProcessInfo,
+    recording::Recording,
+    report::{
+        chart::{ImageFormat, LineChart},
+        label, metric_label,
+    },
+};
+
+use crate::args::{PlotArgs, PlotMetric};
+
+pub fn run(args: PlotArgs) -> Result<()> {
+    let out = args.out.clone().unwrap_or_else(|| default_out(&args));
+    let Some(format) = ImageFormat::from_path(&out) else {
+        bail!("{}: must end with .svg or .png", out.display());
+    };
+
+    let history = Recording::load(&args.file)?.into_history()?;
+    let programs: Vec<ProcessInfo> = history
+        .programs()
+        .into_iter()
+        .filter(|program| {
+            args.processes.is_empty() || args.processes.iter().any(|p| matches(program, p))
+        })
+        .collect();
+    if programs.is_empty() {
+        if args.processes.is_empty() {
+            bail!("{} has no samples", args.file.display());
+        }
+        bail!(
+            "no process in {} matches {}",
+            args.file.display(),
+            args.processes.join(", ")
+        );
+    }
+
+    let metric = args.metric.metric();
+    let title = args
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("{} - {}", metric_label(metric), args.file.display()));
+    let markers = history
+        .markers()
+        .iter()
+        .map(|marker| (marker.elapsed.as_secs_f64(), marker.text.clone()))
+        .collect();
+    let chart = programs
+        .iter()
+        .fold(
+            LineChart::new(title, metric_label(metric)),
+            |chart, program| chart.series(label(program), history.program_series(program, metric)),
+        )
+        .markers(markers)
+        .log_scale(args.log)
+        .size(args.width, args.height);
+    chart.save(&out, format)?;
+    println!("Wrote {}", out.display());
+    Ok(())
+}
+
+/// `<recording>-<metric>.svg` next to the recording.
+fn default_out(args: &PlotArgs) -> PathBuf {
+    let stem = args.file.file_stem().unwrap_or_default().to_string_lossy();
+    let metric = match args.metric {
+        PlotMetric::Cpu => "cpu",
+        PlotMetric::Rss => "rss",
+        PlotMetric::Threads => "threads",
+        PlotMetric::Read => "read",
+        PlotMetric::Write => "write",
+    };
+    args.file.with_file_name(format!("{stem}-{metric}.svg"))
+}
+
+/// Whether a process matches a `NAME` or `NAME@HOST` filter.
+fn matches(program: &ProcessInfo, filter: &str) -> bool {
+    match filter.split_once('@') {
+        Some((name, host)) => program.name == name && program.host.as_deref() == Some(host),
+        None => program.name == filter,
+    }
+}
@@ -0,0 +1,286 @@
+use color_eyre::Result;
+use rusqlite::ToSql;
+use serde_json::{Map, Value};
+
+use crate::recording::sqlite::{Database, QueryRows};
+
+use crate::args::{Bucket, OutputFormat, Query, QueryArgs};
+
+/// Wall-clock seconds of a sample or marker row aliased `t`.
+const SECONDS: &str = "(ss.started_ms + t.elapsed_ms) / 1000";
+
+pub fn run(args: QueryArgs) -> Result<()> {
+    let database = Database::open_read_only(&args.database)?;
+    let rows = match &args.sql {
+        Some(sql) => database.query(sql, &[])?,
+        None => {
+            let (sql, params) = build_query(&args);
+            let params: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+            database.query(&sql, &params)?
+        }
+    };
+    match args.format {
+        OutputFormat::Table => print_table(&rows),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&to_json(rows))?),
+    }
+    Ok(())
+}
+
+/// SQL and parameters of one of the built-in queries.
+fn build_query(args: &QueryArgs) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut filters = Vec::new();
+    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+    if let Some(session) = args.session {
+        params.push(Box::new(session));
+        filters.push(format!("ss.id = ?{}", params.len()));
+    }
+    if !args.processes.is_empty() && args.query != Query::Sessions && args.query != Query::Markers {
+        let mut placeholders = Vec::new();
+        for name in &args.processes {
+            params.push(Box::new(name.clone()));
+            placeholders.push(format!("?{}", params.len()));
+        }
+        filters.push(format!("p.name IN ({})", placeholders.join(", ")));
+    }
+    let filter = if filters.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", filters.join(" AND "))
+    };
+
+    let sql = match args.query {
+        Query::Sessions => format!(
+            "SELECT ss.id AS session,
+                    strftime('%Y-%m-%d %H:%M:%S', ss.started_ms / 1000, 'unixepoch', 'localtime') AS started,
+                    ROUND(MAX(t.elapsed_ms) / 1000.0, 1) AS duration_s,
+                    COUNT(DISTINCT p.id) AS processes,
+                    COUNT(t.process_id) AS samples,
+                    ss.source
+             FROM sessions ss
+             LEFT JOIN processes p ON p.session_id = ss.id
+             LEFT JOIN samples t ON t.process_id = p.id
+             {filter}
+             GROUP BY ss.id
+             ORDER BY ss.id"
+        ),
+        Query::Markers => format!(
+            "SELECT ss.id AS session,
+                    strftime('%Y-%m-%d %H:%M:%S', {SECONDS}, 'unixepoch', 'localtime') AS time,
+                    t.text AS marker
+             FROM markers t
+             JOIN sessions ss ON ss.id = t.session_id
+             {filter}
+             ORDER BY ss.id, t.elapsed_ms"
+        ),
+        Query::PeakRss | Query::MeanRss | Query::PeakCpu | Query::MeanCpu => {
+            let (aggregate, column, alias) = match args.query {
+                Query::PeakRss => ("MAX", "memory_mb", "peak_rss_mb"),
+                Query::MeanRss => ("AVG", "memory_mb", "mean_rss_mb"),
+                Query::PeakCpu => ("MAX", "cpu_usage", "peak_cpu"),
+                _ => ("AVG", "cpu_usage", "mean_cpu"),
+            };
+            let (bucket, bucket_name) = match args.per {
+                Bucket::Minute => (local_time("%Y-%m-%d %H:%M"), "minute"),
+                Bucket::Hour => (local_time("%Y-%m-%d %H:00"), "hour"),
+                Bucket::Day => (local_time("%Y-%m-%d"), "day"),
+                Bucket::Session => ("ss.id".to_string(), "session"),
+            };
+            format!(
+                "SELECT {bucket} AS {bucket_name},
+                        NULLIF(p.host, '') AS host,
+                        p.name AS process,
+                        ROUND({aggregate}(t.{column}), 1) AS {alias},
+                        COUNT(*) AS samples
+                 FROM samples t
+                 JOIN processes p ON p.id = t.process_id
+                 JOIN sessions ss ON ss.id = p.session_id
+                 {filter}
+                 GROUP BY 1, p.host, p.name
+                 ORDER BY 1, p.host, p.name"
+            )
+        }
+    };
+    (sql, params)
+}
+
+fn local_time(format: &str) -> String {
+    format!("strftime('{format}', {SECONDS}, 'unixepoch', 'localtime')")
+}
+
+fn print_table(rows: &QueryRows) {
+    let cells: Vec<Vec<String>> = rows
+        .rows
+        .iter()
+        .map(|row| row.iter().map(format_cell).collect())
+        .collect();
+    let widths: Vec<usize> = rows
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([column.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    // Numbers are right-aligned, judged by the first row
+    let numeric: Vec<bool> = (0..rows.columns.len())
+        .map(|i| rows.rows.first().is_some_and(|row| row[i].is_number()))
+        .collect();
+
+    let line = |values: &[String]| {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                if numeric[i] {
+                    format!("{value:>width$}", width = widths[i])
+                } else {
+                    format!("{value:<width$}", width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", line(&rows.columns));
+    for row in &cells {
+        println!("{}", line(row));
+    }
+    if cells.is_empty() {
+        println!("(no rows)");
+    }
+}
+
+fn format_cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(text) => text.clone(),
+        Value::Number(number) if number.is_f64() => {
+            format!("{:.1}", number.as_f64().unwrap_or(0.0))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Rows as objects keyed by column name.
+fn to_json(rows: QueryRows) -> Vec<Map<String, Value>> {
+    rows.rows
+        .into_iter()
+        .map(|row| rows.columns.iter().cloned().zip(row).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process, time::UNIX_EPOCH};
+
+    use crate::recording::{RecordedMarker, RecordedSample, Recording};
+
+    use super::*;
+
+    fn sample(name: &str, cpu_usage: f32, memory_mb: f64, elapsed_ms: u64) -> RecordedSample {
+        RecordedSample {
+            host: None,
+            name: name.to_string(),
+            pid: 42,
+            cpu_usage,
+            memory_mb,
+            threads: 0,
+            read_kb_s: 0.0,
+            write_kb_s: 0.0,
+            elapsed_ms,
+            group: None,
+        }
+    }
+
+    /// A database with two sessions, queried with `args`.
+    fn query(
+        name: &str,
+        query: Query,
+        per: Bucket,
+        configure: impl FnOnce(&mut QueryArgs),
+    ) -> QueryRows {
+        let dir = env::temp_dir().join(format!("catz-query-{}-{name}", process::id()));
+        let path = dir.join("catz.db");
+        let _ = fs::remove_dir_all(&dir);
+        let mut database = Database::open(&path).unwrap();
+        for cpu in [10.0, 30.0] {
+            let recording = Recording {
+                samples: vec![
+                    sample("nginx", cpu, 100.0, 0),
+                    sample("nginx", cpu * 2.0, 300.0, 1000),
+                    sample("redis", 1.0, 50.0, 0),
+                ],
+                markers: vec![RecordedMarker {
+                    elapsed_ms: 500,
+                    text: format!("at {cpu}"),
+                }],
+                started: Some(UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000)),
+                thresholds: None,
+            };
+            database.insert_recording(&recording, "test").unwrap();
+        }
+
+        let mut args = QueryArgs {
+            database: PathBuf::from(&path),
+            query,
+            per,
+            session: None,
+            processes: Vec::new(),
+            sql: None,
+            format: OutputFormat::Table,
+        };
+        configure(&mut args);
+        let (sql, params) = build_query(&args);
+        let params: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let rows = database.query(&sql, &params).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        rows
+    }
+
+    #[test]
+    fn lists_sessions() {
+        let rows = query("sessions", Query::Sessions, Bucket::Hour, |_| ());
+        assert_eq!(rows.columns[0], "session");
+        assert_eq!(rows.rows.len(), 2);
+        // Two nginx samples and one redis sample per session
+        assert_eq!(rows.rows[0][3], 2);
+        assert_eq!(rows.rows[0][4], 3);
+    }
+
+    #[test]
+    fn filters_markers_by_session() {
+        let rows = query("markers", Query::Markers, Bucket::Hour, |args| {
+            args.session = Some(2);
+        });
+        assert_eq!(rows.rows.len(), 1);
+        assert_eq!(rows.rows[0][2], "at 30");
+    }
+
+    #[test]
+    fn aggregates_per_bucket_and_process() {
+        let rows = query("peak", Query::PeakCpu, Bucket::Session, |args| {
+            args.processes = vec!["nginx".to_string()];
+        });
+        assert_eq!(
+            rows.columns,
+            ["session", "host", "process", "peak_cpu", "samples"]
+        );
+        assert_eq!(rows.rows.len(), 2);
+        assert_eq!(rows.rows[0][3], 20.0);
+        assert_eq!(rows.rows[1][3], 60.0);
+
+        let rows = query("mean", Query::MeanRss, Bucket::Day, |_| ());
+        // Both sessions fall on the same day
+        assert_eq!(rows.rows.len(), 2);
+        assert_eq!(rows.rows[0][2], "nginx");
+        assert_eq!(rows.rows[0][3], 200.0);
+        assert_eq!(rows.rows[0][4], 4);
+        assert_eq!(rows.rows[1][2], "redis");
+    }
+}
@@ -0,0 +1,15 @@
+use color_eyre::Result;
+
+use crate::{config::Config, recording::Recording, report::Report};
+
+use crate::args::ReportArgs;
+
+pub fn run(args: ReportArgs, config: Config) -> Result<()> {
+    let recording = Recording::load(&args.file)?;
+    let out = args.out.unwrap_or_else(|| args.file.with_extension("html"));
+    Report::new(recording, args.file.display().to_string())?
+        .thresholds(config.thresholds)
+        .save(&out)?;
+    println!("Wrote {}", out.display());
+    Ok(())
+}
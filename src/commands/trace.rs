@@ -0,0 +1,17 @@
+use color_eyre::Result;
+
+use crate::{config::Config, recording::Recording, report::trace::Trace};
+
+use crate::args::TraceArgs;
+
+pub fn run(args: TraceArgs, config: Config) -> Result<()> {
+    let recording = Recording::load(&args.file)?;
+    let out = args
+        .out
+        .unwrap_or_else(|| args.file.with_extension("trace.json"));
+    Trace::new(recording)?
+        .thresholds(config.thresholds)
+        .save(&out)?;
+    println!("Wrote {}", out.display());
+    Ok(())
+}
@@ -1,25 +1,498 @@
-use std::time::Duration;
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail, eyre},
+};
+use crossterm::event::KeyCode;
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{domain::column::Column, recording::RecordingFormat, utils};
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub update_interval: Duration,
-    pub default_save_path: String,
+    pub export: ExportConfig,
+    pub columns: Vec<Column>,
+    pub thresholds: Thresholds,
+    pub keybindings: Keybindings,
+    pub theme: Theme,
+    pub watchlists: BTreeMap<String, Vec<String>>,
+    /// Sampling intervals overriding `update_interval`, by process name
+    pub intervals: BTreeMap<String, Duration>,
+    pub burst: BurstConfig,
+    /// Capture the mouse so table headers can be clicked to sort. Off by
+    /// default, as it keeps the terminal from selecting text
+    pub mouse: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            update_interval: Duration::from_secs(1),
-            default_save_path: String::from("stats"),
+            update_interval: Duration::from_secs(2),
+            export: ExportConfig::default(),
+            columns: Column::DEFAULT.to_vec(),
+            thresholds: Thresholds::default(),
+            keybindings: Keybindings::default(),
+            theme: Theme::default(),
+            watchlists: BTreeMap::new(),
+            intervals: BTreeMap::new(),
+            burst: BurstConfig::default(),
+            mouse: false,
         }
     }
 }
 
 impl Config {
-    pub fn new(update_interval_secs: u64) -> Self {
+    /// Loads the config file given with `--config`, or the one at the
+    /// default location if it exists, falling back to defaults otherwise.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to read config file {}", path.display()))?;
+        Self::parse(&content).wrap_err_with(|| format!("invalid config file {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: ConfigFile = toml::from_str(content)?;
+        let defaults = Self::default();
+
+        let update_interval = file
+            .interval
+            .map_or(defaults.update_interval, |Interval(interval)| interval);
+        let columns = file.columns.unwrap_or(defaults.columns);
+        if columns.is_empty() {
+            bail!("columns must list at least one column");
+        }
+        for (i, column) in columns.iter().enumerate() {
+            if columns[..i].contains(column) {
+                bail!("column {:?} is listed more than once", column.header());
+            }
+        }
+        for (name, patterns) in &file.watchlists {
+            if patterns.is_empty() {
+                bail!("watchlist {name:?} has no patterns");
+            }
+        }
+        let keybindings = file.keybindings;
+        keybindings.validate()?;
+
+        Ok(Self {
+            update_interval,
+            export: file.export,
+            columns,
+            thresholds: file.thresholds,
+            keybindings,
+            theme: file.theme,
+            watchlists: file.watchlists,
+            intervals: file
+                .intervals
+                .into_iter()
+                .map(|(name, Interval(interval))| (name, interval))
+                .collect(),
+            burst: file.burst,
+            mouse: file.mouse,
+        })
+    }
+
+    pub fn watchlist(&self, name: &str) -> Result<&[String]> {
+        self.watchlists.get(name).map(Vec::as_slice).ok_or_else(|| {
+            let known: Vec<&str> = self.watchlists.keys().map(String::as_str).collect();
+            eyre!(
+                "unknown watchlist {name:?} (configured: {})",
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            )
+        })
+    }
+}
+
+/// `$XDG_CONFIG_HOME/catz/config.toml`, or `~/.config/catz/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("catz").join("config.toml"))
+}
+
+/// On-disk layout of the config file; every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    /// Sampling interval, either seconds or a duration such as "500ms"
+    interval: Option<Interval>,
+    export: ExportConfig,
+    columns: Option<Vec<Column>>,
+    thresholds: Thresholds,
+    keybindings: Keybindings,
+    theme: Theme,
+    watchlists: BTreeMap<String, Vec<String>>,
+    intervals: BTreeMap<String, Interval>,
+    burst: BurstConfig,
+    mouse: bool,
+}
+
+/// An interval given as whole seconds or as a string such as "250ms".
+#[derive(Debug)]
+struct Interval(Duration);
+
+impl<'de> Deserialize<'de> for Interval {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_interval(deserializer).map(Interval)
+    }
+}
+
+fn deserialize_interval<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Seconds(u64),
+        Text(String),
+    }
+    let text = match Value::deserialize(deserializer)? {
+        Value::Seconds(secs) => secs.to_string(),
+        Value::Text(text) => text,
+    };
+    utils::parse_interval(&text).map_err(serde::de::Error::custom)
+}
+
+/// High-frequency sampling started from the TUI for a short while.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BurstConfig {
+    /// Interval used for every target while a burst is running
+    #[serde(deserialize_with = "deserialize_interval")]
+    pub interval: Duration,
+    /// How long a burst lasts
+    #[serde(deserialize_with = "deserialize_interval")]
+    pub duration: Duration,
+}
+
+impl Default for BurstConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(100),
+            duration: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    /// Directory that relative file names in the save prompt are resolved against
+    pub directory: PathBuf,
+    /// Format used when the file name has no extension
+    #[serde(deserialize_with = "deserialize_format")]
+    pub format: RecordingFormat,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
         Self {
-            update_interval: Duration::from_secs(update_interval_secs),
-            ..Default::default()
+            directory: PathBuf::from("."),
+            format: RecordingFormat::Csv,
+        }
+    }
+}
+
+impl ExportConfig {
+    /// Resolves a file name typed in the save prompt to the path to write.
+    pub fn resolve(&self, filename: &str) -> PathBuf {
+        let mut path = PathBuf::from(filename);
+        if path.extension().is_none() {
+            path.set_extension(self.format.extension());
         }
+        if path.is_relative() {
+            path = self.directory.join(path);
+        }
+        path
+    }
+}
+
+fn deserialize_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<RecordingFormat, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    RecordingFormat::from_extension(&name).ok_or_else(|| {
+        serde::de::Error::custom(format!(
+            "unknown export format {name:?}, expected one of: {}",
+            RecordingFormat::EXTENSIONS.join(", ")
+        ))
+    })
+}
+
+/// Values above which table cells are highlighted. Recordings keep the
+/// ones in effect while they were made, for their alerts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thresholds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<f64>,
+}
+
+impl Thresholds {
+    pub fn is_set(&self) -> bool {
+        self.cpu.is_some() || self.memory_mb.is_some()
+    }
+}
+
+/// Single-character keys for the main table actions. Every key of the
+/// table is listed, so that rebinding one can't shadow another.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keybindings {
+    pub add: char,
+    pub remove: char,
+    pub stats: char,
+    pub signal: char,
+    pub mark: char,
+    pub alias: char,
+    pub group: char,
+    pub interval: char,
+    pub burst: char,
+    pub sort: char,
+    pub reverse_sort: char,
+    pub columns: char,
+    pub filter: char,
+    pub next_match: char,
+    pub previous_match: char,
+    pub save_session: char,
+    pub quit: char,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            add: 'a',
+            remove: 'd',
+            stats: 's',
+            signal: 'k',
+            mark: 'm',
+            alias: 'r',
+            group: 'g',
+            interval: 'i',
+            burst: 'b',
+            sort: 'o',
+            reverse_sort: 'O',
+            columns: 'c',
+            filter: '/',
+            next_match: 'n',
+            previous_match: 'N',
+            save_session: 'w',
+            quit: 'q',
+        }
+    }
+}
+
+impl Keybindings {
+    fn bindings(&self) -> [(&'static str, char, char); 17] {
+        let defaults = Self::default();
+        [
+            ("add", self.add, defaults.add),
+            ("remove", self.remove, defaults.remove),
+            ("stats", self.stats, defaults.stats),
+            ("signal", self.signal, defaults.signal),
+            ("mark", self.mark, defaults.mark),
+            ("alias", self.alias, defaults.alias),
+            ("group", self.group, defaults.group),
+            ("interval", self.interval, defaults.interval),
+            ("burst", self.burst, defaults.burst),
+            ("sort", self.sort, defaults.sort),
+            ("reverse_sort", self.reverse_sort, defaults.reverse_sort),
+            ("columns", self.columns, defaults.columns),
+            ("filter", self.filter, defaults.filter),
+            ("next_match", self.next_match, defaults.next_match),
+            (
+                "previous_match",
+                self.previous_match,
+                defaults.previous_match,
+            ),
+            ("save_session", self.save_session, defaults.save_session),
+            ("quit", self.quit, defaults.quit),
+        ]
+    }
+
+    fn validate(&self) -> Result<()> {
+        let bindings = self.bindings();
+        for (i, (action, key, _)) in bindings.iter().enumerate() {
+            if key.is_whitespace() || key.is_control() {
+                bail!("keybinding for {action} must be a printable character");
+            }
+            if let Some((other, _, _)) = bindings[..i].iter().find(|(_, k, _)| k == key) {
+                bail!("key {key:?} is bound to both {other} and {action}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Maps a configured key to the built-in key of its action, so the
+    /// event handler only has to know the defaults. Built-in keys that were
+    /// rebound elsewhere are disabled.
+    pub fn translate(&self, key: KeyCode) -> KeyCode {
+        let KeyCode::Char(c) = key else {
+            return key;
+        };
+        let bindings = self.bindings();
+        if let Some((_, _, default)) = bindings.iter().find(|(_, bound, _)| *bound == c) {
+            return KeyCode::Char(*default);
+        }
+        if bindings.iter().any(|(_, _, default)| *default == c) {
+            return KeyCode::Null;
+        }
+        key
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Table headers and the status line
+    pub accent: Color,
+    /// Selected table row
+    pub selection: Color,
+    /// Values above a threshold
+    pub warning: Color,
+    pub cpu_chart: Color,
+    pub memory_chart: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            accent: Color::Yellow,
+            selection: Color::Yellow,
+            warning: Color::Red,
+            cpu_chart: Color::Cyan,
+            memory_chart: Color::Magenta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The error of an invalid config, with its causes.
+    fn error(content: &str) -> String {
+        format!("{:#}", Config::parse(content).unwrap_err())
+    }
+
+    #[test]
+    fn parses_intervals_as_seconds_or_durations() {
+        assert_eq!(
+            Config::parse("").unwrap().update_interval,
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            Config::parse("interval = 5").unwrap().update_interval,
+            Duration::from_secs(5)
+        );
+        let config = Config::parse(
+            "interval = \"250ms\"\n\
+             [intervals]\nnginx = \"1.5s\"\npostgres = 10\n\
+             [burst]\ninterval = \"50ms\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.update_interval, Duration::from_millis(250));
+        assert_eq!(config.intervals["nginx"], Duration::from_millis(1500));
+        assert_eq!(config.intervals["postgres"], Duration::from_secs(10));
+        assert_eq!(config.burst.interval, Duration::from_millis(50));
+        assert_eq!(config.burst.duration, Duration::from_secs(10));
+
+        assert!(error("interval = 0").contains("greater than zero"));
+        assert!(error("interval = \"2h\"").contains("longer than the maximum"));
+        assert!(error("[intervals]\nnginx = \"soon\"").contains("invalid duration"));
+    }
+
+    #[test]
+    fn looks_up_watchlists_by_name() {
+        let config =
+            Config::parse("[watchlists]\nweb = [\"nginx\", \"php-fpm\"]\ndb = [\"postgres\"]\n")
+                .unwrap();
+        assert_eq!(config.watchlist("web").unwrap(), ["nginx", "php-fpm"]);
+        let unknown = config.watchlist("cache").unwrap_err().to_string();
+        assert_eq!(unknown, "unknown watchlist \"cache\" (configured: db, web)");
+
+        let unknown = Config::default().watchlist("web").unwrap_err().to_string();
+        assert_eq!(unknown, "unknown watchlist \"web\" (configured: none)");
+        assert!(error("[watchlists]\nweb = []").contains("watchlist \"web\" has no patterns"));
+    }
+
+    #[test]
+    fn validates_columns() {
+        let config = Config::parse("columns = [\"host\", \"name\", \"read\"]").unwrap();
+        assert_eq!(config.columns, [Column::Host, Column::Name, Column::Read]);
+        assert_eq!(Config::parse("").unwrap().columns, Column::DEFAULT);
+
+        assert!(error("columns = []").contains("columns must list at least one column"));
+        assert!(
+            error("columns = [\"cpu\", \"pid\", \"cpu\"]")
+                .contains("column \"CPU%\" is listed more than once")
+        );
+        assert!(error("columns = [\"gpu\"]").contains("unknown variant"));
+    }
+
+    #[test]
+    fn resolves_save_paths_against_the_export_config() {
+        let default = ExportConfig::default();
+        assert_eq!(default.resolve("stats"), Path::new("./stats.csv"));
+        assert_eq!(default.resolve("stats.json"), Path::new("./stats.json"));
+
+        let config =
+            Config::parse("[export]\ndirectory = \"/tmp/runs\"\nformat = \"ndjson\"\n").unwrap();
+        let export = &config.export;
+        assert_eq!(export.resolve("stats"), Path::new("/tmp/runs/stats.ndjson"));
+        assert_eq!(
+            export.resolve("sub/stats.db"),
+            Path::new("/tmp/runs/sub/stats.db")
+        );
+        assert_eq!(export.resolve("/var/stats"), Path::new("/var/stats.ndjson"));
+        assert!(error("[export]\nformat = \"xlsx\"").contains("unknown export format \"xlsx\""));
+    }
+
+    #[test]
+    fn captures_the_mouse_only_when_asked() {
+        assert!(!Config::parse("").unwrap().mouse);
+        assert!(Config::parse("mouse = true").unwrap().mouse);
+    }
+
+    #[test]
+    fn translates_rebound_keys_to_their_defaults() {
+        let config = Config::parse("[keybindings]\nadd = 'x'\ncolumns = 'C'\n").unwrap();
+        let keys = &config.keybindings;
+        assert_eq!(keys.translate(KeyCode::Char('x')), KeyCode::Char('a'));
+        assert_eq!(keys.translate(KeyCode::Char('C')), KeyCode::Char('c'));
+        assert_eq!(keys.translate(KeyCode::Char('a')), KeyCode::Null);
+        assert_eq!(keys.translate(KeyCode::Char('/')), KeyCode::Char('/'));
+        assert_eq!(keys.translate(KeyCode::Enter), KeyCode::Enter);
+    }
+
+    #[test]
+    fn rejects_keys_bound_twice() {
+        // The column picker has a key of its own, so it can't be taken over
+        let error = Config::parse("[keybindings]\nadd = 'c'\n").unwrap_err();
+        assert!(
+            format!("{error:#}").contains("bound to both add and columns"),
+            "{error:#}"
+        );
+        assert!(Config::parse("[keybindings]\nfilter = ' '\n").is_err());
     }
 }
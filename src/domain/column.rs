@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::domain::process::ProcessInfo;
+
+/// A column of the process table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Name,
+    Pid,
+    Cpu,
+    Memory,
+    Group,
+    Host,
+    Threads,
+    /// Disk reads in KB/s
+    Read,
+    /// Disk writes in KB/s
+    Write,
+}
+
+impl Column {
+    pub const DEFAULT: [Column; 4] = [Column::Name, Column::Pid, Column::Cpu, Column::Memory];
+    pub const ALL: [Column; 9] = [
+        Column::Name,
+        Column::Pid,
+        Column::Cpu,
+        Column::Memory,
+        Column::Threads,
+        Column::Read,
+        Column::Write,
+        Column::Group,
+        Column::Host,
+    ];
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            Column::Name => "Name",
+            Column::Pid => "PID",
+            Column::Cpu => "CPU%",
+            Column::Memory => "Memory(MB)",
+            Column::Group => "Group",
+            Column::Host => "Host",
+            Column::Threads => "Threads",
+            Column::Read => "Read(KB/s)",
+            Column::Write => "Write(KB/s)",
+        }
+    }
+
+    /// Whether values are numbers, which are right-aligned.
+    pub fn is_numeric(&self) -> bool {
+        !matches!(self, Column::Name | Column::Group | Column::Host)
+    }
+
+    /// The process's value in this column, as shown in the table.
+    pub fn format(&self, process: &ProcessInfo) -> String {
+        match self {
+            Column::Name => process.display_name().to_string(),
+            Column::Pid => process.local_pid().to_string(),
+            Column::Cpu => format!("{:.1}", process.cpu_usage),
+            Column::Memory => format!("{:.1}", process.memory_mb),
+            Column::Group => process.group.clone().unwrap_or_else(|| "-".to_string()),
+            Column::Host => process.host.clone().unwrap_or_else(|| "-".to_string()),
+            // Sources that can't count threads report 0
+            Column::Threads => match process.threads {
+                0 => "-".to_string(),
+                threads => threads.to_string(),
+            },
+            Column::Read => format!("{:.1}", process.read_kb_s),
+            Column::Write => format!("{:.1}", process.write_kb_s),
+        }
+    }
+
+    /// Relative width used to share the table width between visible columns.
+    pub fn weight(&self) -> u16 {
+        match self {
+            Column::Name => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn compare(&self, a: &ProcessInfo, b: &ProcessInfo) -> Ordering {
+        match self {
+            Column::Name => a.display_name().cmp(b.display_name()),
+            // As shown, so processes on several hosts interleave by PID
+            Column::Pid => a.local_pid().cmp(&b.local_pid()),
+            Column::Cpu => a.cpu_usage.total_cmp(&b.cpu_usage),
+            Column::Memory => a.memory_mb.total_cmp(&b.memory_mb),
+            Column::Group => a.group.cmp(&b.group),
+            Column::Host => a.host.cmp(&b.host),
+            Column::Threads => a.threads.cmp(&b.threads),
+            Column::Read => a.read_kb_s.total_cmp(&b.read_kb_s),
+            Column::Write => a.write_kb_s.total_cmp(&b.write_kb_s),
+        }
+    }
+}
+
+/// Ordering of the process table; `column: None` keeps the order in which
+/// processes were added.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortOrder {
+    pub column: Option<Column>,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl SortOrder {
+    pub fn sort(&self, processes: &mut [ProcessInfo]) {
+        if let Some(column) = self.column {
+            processes.sort_by(|a, b| {
+                let ordering = column.compare(a, b);
+                if self.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+    }
+
+    /// Moves to the next visible column, wrapping back to insertion order.
+    pub fn cycle(&mut self, columns: &[Column]) {
+        let next = match self
+            .column
+            .and_then(|c| columns.iter().position(|&v| v == c))
+        {
+            Some(i) => columns.get(i + 1).copied(),
+            None => columns.first().copied(),
+        };
+        self.column = next;
+    }
+
+    /// Sorts by `column`, or reverses the order if already sorted by it.
+    pub fn toggle(&mut self, column: Column) {
+        if self.column == Some(column) {
+            self.descending = !self.descending;
+        } else {
+            self.column = Some(column);
+            self.descending = false;
+        }
+    }
+
+    pub fn describe(&self) -> Option<String> {
+        self.column.map(|column| {
+            format!(
+                "{} {}",
+                column.header(),
+                if self.descending { "↓" } else { "↑" }
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::process::host_pid;
+    use sysinfo::Pid;
+
+    fn process(name: &str, pid: u32, cpu: f32, memory_mb: f64) -> ProcessInfo {
+        ProcessInfo::new(name.to_string(), Pid::from_u32(pid), cpu, memory_mb)
+    }
+
+    #[test]
+    fn formats_values_by_type() {
+        let nginx = ProcessInfo {
+            threads: 12,
+            read_kb_s: 1.25,
+            write_kb_s: 0.0,
+            ..process("nginx", 4242, 12.345, 100.0)
+        };
+        let formatted: Vec<String> = Column::ALL.iter().map(|c| c.format(&nginx)).collect();
+        assert_eq!(
+            formatted,
+            [
+                "nginx", "4242", "12.3", "100.0", "12", "1.2", "0.0", "-", "-"
+            ]
+        );
+        // Unknown thread counts aren't shown as 0
+        assert_eq!(Column::Threads.format(&process("sh", 1, 0.0, 0.0)), "-");
+    }
+
+    #[test]
+    fn shows_pids_as_integers_on_their_own_host() {
+        let remote = ProcessInfo {
+            host: Some("web1".to_string()),
+            ..ProcessInfo::new("nginx".to_string(), host_pid(3, 4242).unwrap(), 0.0, 0.0)
+        };
+        assert_eq!(Column::Pid.format(&remote), "4242");
+        assert_eq!(Column::Host.format(&remote), "web1");
+    }
+
+    #[test]
+    fn compares_by_the_column_value() {
+        use Ordering::{Equal, Greater, Less};
+
+        let a = ProcessInfo {
+            threads: 8,
+            read_kb_s: 5.0,
+            write_kb_s: 1.0,
+            ..process("nginx", 20, 50.0, 10.0)
+        };
+        let b = ProcessInfo {
+            threads: 2,
+            read_kb_s: 1.0,
+            write_kb_s: 7.0,
+            alias: Some("api".to_string()),
+            ..process("zeta", 100, 5.0, 30.0)
+        };
+        let orderings: Vec<Ordering> = Column::ALL.iter().map(|c| c.compare(&a, &b)).collect();
+        assert_eq!(
+            orderings,
+            [
+                Greater, Less, Greater, Less, Greater, Greater, Less, Equal, Equal
+            ]
+        );
+    }
+
+    #[test]
+    fn sorts_ascending_and_descending() {
+        let mut processes = vec![
+            process("b", 2, 10.0, 0.0),
+            process("a", 1, 30.0, 0.0),
+            process("c", 3, 20.0, 0.0),
+        ];
+        let mut sort = SortOrder::default();
+        sort.toggle(Column::Cpu);
+        sort.sort(&mut processes);
+        let names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["b", "c", "a"]);
+        assert_eq!(sort.describe().as_deref(), Some("CPU% ↑"));
+
+        sort.toggle(Column::Cpu);
+        sort.sort(&mut processes);
+        let names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["a", "c", "b"]);
+        assert_eq!(sort.describe().as_deref(), Some("CPU% ↓"));
+    }
+
+    #[test]
+    fn cycles_through_visible_columns() {
+        let columns = [Column::Name, Column::Cpu];
+        let mut sort = SortOrder::default();
+        sort.cycle(&columns);
+        assert_eq!(sort.column, Some(Column::Name));
+        sort.cycle(&columns);
+        assert_eq!(sort.column, Some(Column::Cpu));
+        sort.cycle(&columns);
+        assert_eq!(sort.column, None);
+        assert_eq!(sort.describe(), None);
+    }
+}
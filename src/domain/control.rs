@@ -0,0 +1,390 @@
+use std::io;
+use sysinfo::Pid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Term,
+    Kill,
+    Int,
+    Hup,
+    Stop,
+    Cont,
+    Usr1,
+    Usr2,
+}
+
+impl Signal {
+    pub const ALL: [Signal; 8] = [
+        Signal::Term,
+        Signal::Kill,
+        Signal::Int,
+        Signal::Hup,
+        Signal::Stop,
+        Signal::Cont,
+        Signal::Usr1,
+        Signal::Usr2,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Signal::Term => "SIGTERM",
+            Signal::Kill => "SIGKILL",
+            Signal::Int => "SIGINT",
+            Signal::Hup => "SIGHUP",
+            Signal::Stop => "SIGSTOP",
+            Signal::Cont => "SIGCONT",
+            Signal::Usr1 => "SIGUSR1",
+            Signal::Usr2 => "SIGUSR2",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Signal::Term => "terminate gracefully",
+            Signal::Kill => "kill immediately",
+            Signal::Int => "interrupt",
+            Signal::Hup => "hang up / reload",
+            Signal::Stop => "pause",
+            Signal::Cont => "resume",
+            Signal::Usr1 => "user-defined 1",
+            Signal::Usr2 => "user-defined 2",
+        }
+    }
+
+    #[cfg(unix)]
+    fn raw(&self) -> libc::c_int {
+        match self {
+            Signal::Term => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+            Signal::Int => libc::SIGINT,
+            Signal::Hup => libc::SIGHUP,
+            Signal::Stop => libc::SIGSTOP,
+            Signal::Cont => libc::SIGCONT,
+            Signal::Usr1 => libc::SIGUSR1,
+            Signal::Usr2 => libc::SIGUSR2,
+        }
+    }
+}
+
+/// The PID as libc takes it. 0 and negative PIDs stand for process groups
+/// or the caller itself, so they are refused rather than passed on.
+#[cfg(unix)]
+fn raw_pid(pid: Pid) -> io::Result<libc::pid_t> {
+    libc::pid_t::try_from(pid.as_u32())
+        .ok()
+        .filter(|&pid| pid > 0)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{pid} is not a valid PID"),
+            )
+        })
+}
+
+#[cfg(unix)]
+pub fn send_signal(pid: Pid, signal: Signal) -> io::Result<()> {
+    let pid = raw_pid(pid)?;
+    // SAFETY: kill has no memory-safety preconditions
+    if unsafe { libc::kill(pid, signal.raw()) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_signal(_pid: Pid, _signal: Signal) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "signals are only supported on unix",
+    ))
+}
+
+/// Niceness bounds accepted by `set_priority`.
+pub const NICE_RANGE: std::ops::RangeInclusive<i32> = -20..=19;
+
+/// Scheduling parameters shown in the detail view. Each field is `None`
+/// when the platform can't report it or the query failed.
+#[derive(Debug, Clone, Default)]
+pub struct SchedulingInfo {
+    pub nice: Option<i32>,
+    pub policy: Option<&'static str>,
+    pub affinity: Option<Vec<usize>>,
+}
+
+impl SchedulingInfo {
+    pub fn query(pid: Pid) -> Self {
+        Self {
+            nice: get_priority(pid).ok(),
+            policy: scheduling_policy(pid).ok(),
+            affinity: cpu_affinity(pid).ok(),
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn get_priority(pid: Pid) -> io::Result<i32> {
+    // -1 is a valid niceness, so errors are only detectable through errno
+    let pid = raw_pid(pid)? as libc::id_t;
+    // SAFETY: errno is thread-local; getpriority has no memory-safety preconditions
+    unsafe {
+        *errno_location() = 0;
+        let nice = libc::getpriority(libc::PRIO_PROCESS, pid);
+        if nice == -1 && *errno_location() != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(nice)
+    }
+}
+
+#[cfg(unix)]
+pub fn set_priority(pid: Pid, nice: i32) -> io::Result<()> {
+    if !NICE_RANGE.contains(&nice) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "nice value must be between {} and {}",
+                NICE_RANGE.start(),
+                NICE_RANGE.end()
+            ),
+        ));
+    }
+    let pid = raw_pid(pid)? as libc::id_t;
+    // SAFETY: setpriority has no memory-safety preconditions
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid, nice) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn errno_location() -> *mut libc::c_int {
+    unsafe { libc::__errno_location() }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+unsafe fn errno_location() -> *mut libc::c_int {
+    unsafe { libc::__error() }
+}
+
+#[cfg(target_os = "linux")]
+pub fn scheduling_policy(pid: Pid) -> io::Result<&'static str> {
+    let pid = raw_pid(pid)?;
+    // SAFETY: sched_getscheduler has no memory-safety preconditions
+    let policy = unsafe { libc::sched_getscheduler(pid) };
+    Ok(match policy {
+        libc::SCHED_OTHER => "SCHED_OTHER",
+        libc::SCHED_FIFO => "SCHED_FIFO",
+        libc::SCHED_RR => "SCHED_RR",
+        libc::SCHED_BATCH => "SCHED_BATCH",
+        libc::SCHED_IDLE => "SCHED_IDLE",
+        libc::SCHED_DEADLINE => "SCHED_DEADLINE",
+        -1 => return Err(io::Error::last_os_error()),
+        _ => "unknown",
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub fn cpu_affinity(pid: Pid) -> io::Result<Vec<usize>> {
+    let pid = raw_pid(pid)?;
+    // SAFETY: cpu_set_t is plain old data and is only read after sched_getaffinity fills it
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        let size = std::mem::size_of::<libc::cpu_set_t>();
+        if libc::sched_getaffinity(pid, size, &mut set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((0..CPU_SETSIZE)
+            .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+            .collect())
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_cpu_affinity(pid: Pid, cpus: &[usize]) -> io::Result<()> {
+    if let Some(cpu) = cpus.iter().find(|&&cpu| cpu >= CPU_SETSIZE) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("CPU {cpu} is out of range"),
+        ));
+    }
+    let pid = raw_pid(pid)?;
+    // SAFETY: cpu_set_t is plain old data and every index was bounds-checked above
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        let size = std::mem::size_of::<libc::cpu_set_t>();
+        if libc::sched_setaffinity(pid, size, &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn get_priority(_pid: Pid) -> io::Result<i32> {
+    Err(unsupported("process priority"))
+}
+
+#[cfg(not(unix))]
+pub fn set_priority(_pid: Pid, _nice: i32) -> io::Result<()> {
+    Err(unsupported("process priority"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn scheduling_policy(_pid: Pid) -> io::Result<&'static str> {
+    Err(unsupported("scheduling policy"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn cpu_affinity(_pid: Pid) -> io::Result<Vec<usize>> {
+    Err(unsupported("CPU affinity"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_cpu_affinity(_pid: Pid, _cpus: &[usize]) -> io::Result<()> {
+    Err(unsupported("CPU affinity"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{what} is not supported on this platform"),
+    )
+}
+
+/// Number of CPUs an affinity mask can hold.
+#[cfg(target_os = "linux")]
+const CPU_SETSIZE: usize = libc::CPU_SETSIZE as usize;
+#[cfg(not(target_os = "linux"))]
+const CPU_SETSIZE: usize = 1024;
+
+/// Parses a CPU list such as "0-3,6" into sorted, deduplicated CPU indices.
+pub fn parse_cpu_list(input: &str) -> Result<Vec<usize>, String> {
+    let mut cpus = Vec::new();
+    for part in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        // Checked before ranges are expanded, so "0-99999999999" can't
+        // allocate a huge list
+        let parse = |s: &str| {
+            let cpu = s
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid CPU {s:?}"))?;
+            if cpu >= CPU_SETSIZE {
+                return Err(format!("CPU {cpu} is out of range (0-{})", CPU_SETSIZE - 1));
+            }
+            Ok(cpu)
+        };
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("invalid CPU range {part:?}"));
+                }
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(parse(part)?),
+        }
+    }
+    if cpus.is_empty() {
+        return Err("CPU list is empty".to_string());
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+/// Formats CPU indices compactly, e.g. `[0, 1, 2, 3, 6]` as "0-3,6".
+pub fn format_cpu_list(cpus: &[usize]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut iter = cpus.iter().copied().peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end + 1)) {
+            end += 1;
+            iter.next();
+        }
+        ranges.push(if start == end {
+            start.to_string()
+        } else {
+            format!("{start}-{end}")
+        });
+    }
+    ranges.join(",")
+}
+
+/// Explains an error from a process control call in terms of what the user can do.
+pub fn describe_error(error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => {
+            "permission denied (the process belongs to another user, or the change needs root)"
+                .to_string()
+        }
+        _ => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cpu_lists() {
+        assert_eq!(parse_cpu_list("0-3,6"), Ok(vec![0, 1, 2, 3, 6]));
+        assert_eq!(parse_cpu_list(" 6, 2-3 ,2,"), Ok(vec![2, 3, 6]));
+        assert_eq!(parse_cpu_list("5-5"), Ok(vec![5]));
+    }
+
+    #[test]
+    fn rejects_invalid_cpu_lists() {
+        for input in ["", ",", "a", "1-", "-1", "3-1", "0-1-2"] {
+            assert!(parse_cpu_list(input).is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_cpus_out_of_range() {
+        let last = CPU_SETSIZE - 1;
+        assert_eq!(parse_cpu_list(&last.to_string()), Ok(vec![last]));
+        assert!(parse_cpu_list(&CPU_SETSIZE.to_string()).is_err());
+        assert!(parse_cpu_list("0-99999999999").is_err());
+        assert!(parse_cpu_list(&format!("0-{}", usize::MAX)).is_err());
+    }
+
+    #[test]
+    fn formats_cpu_lists() {
+        assert_eq!(format_cpu_list(&[0, 1, 2, 3, 6]), "0-3,6");
+        assert_eq!(format_cpu_list(&[1, 3]), "1,3");
+        assert_eq!(format_cpu_list(&[]), "");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_pids_that_stand_for_several_processes() {
+        for pid in [0, 1 << 31, u32::MAX] {
+            let error = send_signal(Pid::from_u32(pid), Signal::Kill).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{pid}");
+            assert!(set_priority(Pid::from_u32(pid), 0).is_err(), "{pid}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sends_signals_to_processes() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = Pid::from_u32(child.id());
+        send_signal(pid, Signal::Stop).unwrap();
+        send_signal(pid, Signal::Cont).unwrap();
+        send_signal(pid, Signal::Term).unwrap();
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGTERM));
+    }
+}
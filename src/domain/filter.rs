@@ -0,0 +1,252 @@
+use crate::domain::process::ProcessInfo;
+
+/// A filter typed after `/` in the process table. Whitespace separates
+/// terms that must all match: a bare word matches names and aliases
+/// containing it, ignoring case, and `field<op>value` compares a field.
+/// Text fields equal the value with `=` and contain it with `~=`.
+///
+/// ```text
+/// nginx cpu>20
+/// mem>=512 host=web1
+/// group!=batch pid<1000 host~=web
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    /// The expression as typed
+    pub text: String,
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Name(String),
+    Number(NumberField, Comparison, f64),
+    /// Compared ignoring case, with one of `=`, `!=`, `~=` and `!~`
+    Text(TextField, Comparison, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumberField {
+    Cpu,
+    Memory,
+    Pid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextField {
+    Name,
+    Group,
+    Host,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+    /// Text contains the value
+    Contains,
+    /// Text doesn't contain the value
+    Lacks,
+}
+
+impl Comparison {
+    const OPERATORS: [(&'static str, Comparison); 9] = [
+        ("~=", Comparison::Contains),
+        ("!~", Comparison::Lacks),
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        ("!=", Comparison::NotEqual),
+        ("==", Comparison::Equal),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ];
+
+    fn holds(&self, a: f64, b: f64) -> bool {
+        match self {
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Equal => a == b,
+            Comparison::NotEqual => a != b,
+            Comparison::GreaterOrEqual => a >= b,
+            Comparison::Greater => a > b,
+            Comparison::Contains | Comparison::Lacks => false,
+        }
+    }
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let terms = input
+            .split_whitespace()
+            .map(parse_term)
+            .collect::<Result<Vec<_>, _>>()?;
+        if terms.is_empty() {
+            return Err("filter is empty".to_string());
+        }
+        Ok(Self {
+            text: input.trim().to_string(),
+            terms,
+        })
+    }
+
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Name(word) => {
+                contains(&process.name, word)
+                    || process.alias.as_deref().is_some_and(|a| contains(a, word))
+            }
+            Term::Number(field, comparison, value) => {
+                let actual = match field {
+                    NumberField::Cpu => process.cpu_usage as f64,
+                    NumberField::Memory => process.memory_mb,
+                    NumberField::Pid => process.local_pid() as f64,
+                };
+                comparison.holds(actual, *value)
+            }
+            Term::Text(field, comparison, value) => {
+                let text = match field {
+                    TextField::Name => Some(&process.name),
+                    TextField::Group => process.group.as_ref(),
+                    TextField::Host => process.host.as_ref(),
+                };
+                // Names also match by their alias
+                let alias = match field {
+                    TextField::Name => process.alias.as_ref(),
+                    _ => None,
+                };
+                let found = text.into_iter().chain(alias).any(|text| match comparison {
+                    Comparison::Contains | Comparison::Lacks => contains(text, value),
+                    _ => text.to_lowercase() == *value,
+                });
+                found == matches!(comparison, Comparison::Equal | Comparison::Contains)
+            }
+        })
+    }
+}
+
+fn parse_term(term: &str) -> Result<Term, String> {
+    // The first operator in the term, the longest one where several start
+    // there, so `>=` isn't read as `>`
+    let Some((index, operator, comparison)) = Comparison::OPERATORS
+        .iter()
+        .filter_map(|&(operator, comparison)| {
+            term.find(operator)
+                .map(|index| (index, operator, comparison))
+        })
+        .min_by_key(|&(index, operator, _)| (index, std::cmp::Reverse(operator.len())))
+    else {
+        return Ok(Term::Name(term.to_lowercase()));
+    };
+    let field = term[..index].to_lowercase();
+    let value = &term[index + operator.len()..];
+    if value.is_empty() {
+        return Err(format!("missing value in {term:?}"));
+    }
+
+    let number_field = match field.as_str() {
+        "cpu" => Some(NumberField::Cpu),
+        "mem" | "memory" | "rss" => Some(NumberField::Memory),
+        "pid" => Some(NumberField::Pid),
+        _ => None,
+    };
+    if let Some(field) = number_field {
+        if matches!(comparison, Comparison::Contains | Comparison::Lacks) {
+            return Err(format!(
+                "numbers can't be compared with ~= or !~ in {term:?}"
+            ));
+        }
+        let value = value
+            .parse::<f64>()
+            .map_err(|_| format!("{value:?} is not a number in {term:?}"))?;
+        return Ok(Term::Number(field, comparison, value));
+    }
+
+    let text_field = match field.as_str() {
+        "name" => TextField::Name,
+        "group" => TextField::Group,
+        "host" => TextField::Host,
+        _ => {
+            return Err(format!(
+                "unknown field {field:?}, expected cpu, mem, pid, name, group or host"
+            ));
+        }
+    };
+    match comparison {
+        Comparison::Equal | Comparison::NotEqual | Comparison::Contains | Comparison::Lacks => {
+            Ok(Term::Text(text_field, comparison, value.to_lowercase()))
+        }
+        _ => Err(format!("{field} can only be compared with =, !=, ~= or !~")),
+    }
+}
+
+/// Whether `text` contains the lowercase `word`, ignoring case.
+fn contains(text: &str, word: &str) -> bool {
+    text.to_lowercase().contains(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sysinfo::Pid;
+
+    fn process(name: &str, host: Option<&str>) -> ProcessInfo {
+        ProcessInfo {
+            alias: Some("frontend".to_string()),
+            group: Some("web".to_string()),
+            host: host.map(str::to_string),
+            ..ProcessInfo::new(name.to_string(), Pid::from_u32(42), 25.0, 512.0)
+        }
+    }
+
+    fn matches(filter: &str, process: &ProcessInfo) -> bool {
+        Filter::parse(filter).unwrap().matches(process)
+    }
+
+    #[test]
+    fn matches_words_and_numbers() {
+        let nginx = process("nginx", None);
+        assert!(matches("NGI", &nginx));
+        assert!(matches("front cpu>20", &nginx));
+        assert!(matches("mem>=512 pid==42", &nginx));
+        assert!(!matches("nginx cpu>=30", &nginx));
+        assert!(!matches("pid!=42", &nginx));
+    }
+
+    #[test]
+    fn compares_text_exactly_or_by_substring() {
+        let web1 = process("nginx", Some("web1.example.com"));
+        assert!(matches("host=WEB1.example.com", &web1));
+        assert!(!matches("host=web1", &web1));
+        assert!(matches("host~=web1", &web1));
+        assert!(matches("host!=web1 host!~web2", &web1));
+        assert!(!matches("host!~web", &web1));
+        assert!(matches("name=frontend group=web", &web1));
+        assert!(!matches("name=front", &web1));
+        let ungrouped = ProcessInfo {
+            group: None,
+            ..process("nginx", None)
+        };
+        assert!(!matches("group=web", &ungrouped));
+        assert!(matches("group!=web", &ungrouped));
+    }
+
+    #[test]
+    fn keeps_the_text_as_typed() {
+        let filter = Filter::parse("  nginx cpu>20 ").unwrap();
+        assert_eq!(filter.text, "nginx cpu>20");
+    }
+
+    #[test]
+    fn rejects_invalid_filters() {
+        for input in [
+            "", "   ", "cpu>", "cpu>lots", "cpu~=5", "host>web", "size>5", "=web",
+        ] {
+            assert!(Filter::parse(input).is_err(), "{input:?} was accepted");
+        }
+    }
+}
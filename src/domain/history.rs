@@ -0,0 +1,204 @@
+use crate::domain::process::ProcessInfo;
+use std::{collections::VecDeque, time::Duration};
+use sysinfo::Pid;
+
+/// All monitored processes as sampled at one tick.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Time since the start of the session or recording
+    pub elapsed: Duration,
+    pub processes: Vec<ProcessInfo>,
+}
+
+/// A named point in time, e.g. "deployed v2".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    /// Time since the start of the session or recording
+    pub elapsed: Duration,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Cpu,
+    Memory,
+    Threads,
+    DiskRead,
+    DiskWrite,
+}
+
+impl Metric {
+    pub fn value(&self, process: &ProcessInfo) -> f64 {
+        match self {
+            Metric::Cpu => process.cpu_usage as f64,
+            Metric::Memory => process.memory_mb,
+            Metric::Threads => process.threads as f64,
+            Metric::DiskRead => process.read_kb_s,
+            Metric::DiskWrite => process.write_kb_s,
+        }
+    }
+}
+
+/// Time series of sampled frames, optionally bounded to the most recent ones,
+/// and the markers dropped along the way.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    frames: VecDeque<Frame>,
+    markers: Vec<Marker>,
+    capacity: Option<usize>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            markers: Vec::new(),
+            capacity: Some(capacity),
+        }
+    }
+
+    pub fn push(&mut self, frame: Frame) {
+        if let Some(capacity) = self.capacity
+            && self.frames.len() >= capacity
+        {
+            self.frames.pop_front();
+            // Markers older than the remaining frames go with them
+            if let Some(first) = self.frames.front() {
+                self.markers
+                    .retain(|marker| marker.elapsed >= first.elapsed);
+            }
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Adds a marker, keeping markers in time order.
+    pub fn add_marker(&mut self, marker: Marker) {
+        let index = self
+            .markers
+            .partition_point(|other| other.elapsed <= marker.elapsed);
+        self.markers.insert(index, marker);
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    pub fn frames(&self) -> impl DoubleEndedIterator<Item = &Frame> + ExactSizeIterator {
+        self.frames.iter()
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.markers.clear();
+    }
+
+    /// Elapsed time of the first and last frame.
+    pub fn time_range(&self) -> Option<(Duration, Duration)> {
+        Some((self.frames.front()?.elapsed, self.frames.back()?.elapsed))
+    }
+
+    /// Every program in the history, in order of first appearance.
+    pub fn programs(&self) -> Vec<ProcessInfo> {
+        let mut programs: Vec<ProcessInfo> = Vec::new();
+        for frame in &self.frames {
+            for process in &frame.processes {
+                if !programs.iter().any(|p| p.same_program(process)) {
+                    programs.push(process.clone());
+                }
+            }
+        }
+        programs
+    }
+
+    /// `(seconds, value)` points of one process within the first `limit` frames.
+    pub fn series(&self, pid: Pid, metric: Metric, limit: usize) -> Vec<(f64, f64)> {
+        self.frames
+            .iter()
+            .take(limit)
+            .filter_map(|frame| {
+                let process = frame.processes.iter().find(|p| p.pid == pid)?;
+                Some((frame.elapsed.as_secs_f64(), metric.value(process)))
+            })
+            .collect()
+    }
+
+    /// `(seconds, value)` points of one program, following it across
+    /// restarts.
+    pub fn program_series(&self, program: &ProcessInfo, metric: Metric) -> Vec<(f64, f64)> {
+        self.frames
+            .iter()
+            .filter_map(|frame| {
+                let process = frame.processes.iter().find(|p| p.same_program(program))?;
+                Some((frame.elapsed.as_secs_f64(), metric.value(process)))
+            })
+            .collect()
+    }
+
+    /// Index of the frame where the process hit its highest value.
+    pub fn peak_index(&self, pid: Pid, metric: Metric) -> Option<usize> {
+        self.frames
+            .iter()
+            .enumerate()
+            .filter_map(|(i, frame)| {
+                let process = frame.processes.iter().find(|p| p.pid == pid)?;
+                Some((i, metric.value(process)))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn series_follow_one_pid_and_program_series_the_program() {
+        let mut history = History::new();
+        for (i, pid) in [1, 1, 2].into_iter().enumerate() {
+            let process =
+                |pid, cpu| ProcessInfo::new("nginx".to_string(), Pid::from_u32(pid), cpu, 1.0);
+            let mut processes = vec![process(pid, i as f32)];
+            if i == 0 {
+                processes.push(process(3, 99.0));
+            }
+            history.push(Frame {
+                elapsed: Duration::from_secs(i as u64),
+                processes,
+            });
+        }
+        assert_eq!(
+            history.series(Pid::from_u32(1), Metric::Cpu, 10),
+            [(0.0, 0.0), (1.0, 1.0)]
+        );
+        assert_eq!(
+            history.series(Pid::from_u32(1), Metric::Cpu, 1),
+            [(0.0, 0.0)]
+        );
+        assert_eq!(history.peak_index(Pid::from_u32(3), Metric::Cpu), Some(0));
+        assert_eq!(history.peak_index(Pid::from_u32(4), Metric::Cpu), None);
+
+        // The program is followed through the restart as PID 2
+        let program = &history.programs()[0];
+        assert_eq!(
+            history.program_series(program, Metric::Cpu),
+            [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]
+        );
+    }
+}
@@ -1,2 +1,8 @@
+pub(crate) mod column;
+pub(crate) mod control;
+pub(crate) mod filter;
+pub mod history;
 pub mod process;
-pub mod state;
+pub(crate) mod replay;
+pub(crate) mod state;
+pub mod stats;
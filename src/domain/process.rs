@@ -1,11 +1,49 @@
-use sysinfo::{Pid, System};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use sysinfo::Pid;
+
+/// Bits of a PID holding the process's own PID when processes of several
+/// hosts share one table; the bits above hold the host's index. Linux PIDs
+/// stay below 2^22.
+const HOST_PID_BITS: u32 = 22;
+
+/// Most hosts whose processes can share one table, see [`host_pid`].
+pub const MAX_HOSTS: usize = 1 << (u32::BITS - HOST_PID_BITS);
+
+/// A PID that is unique across hosts, for process `pid` on host number
+/// `host`. `None` when either doesn't fit in its bits.
+pub fn host_pid(host: usize, pid: u32) -> Option<Pid> {
+    (host < MAX_HOSTS && pid < 1 << HOST_PID_BITS)
+        .then(|| Pid::from_u32(((host as u32) << HOST_PID_BITS) | pid))
+}
+
+/// Splits a PID made by [`host_pid`] into the host number and the PID on
+/// that host.
+pub fn split_host_pid(pid: Pid) -> (usize, u32) {
+    let pid = pid.as_u32();
+    (
+        (pid >> HOST_PID_BITS) as usize,
+        pid & ((1 << HOST_PID_BITS) - 1),
+    )
+}
 
 #[derive(Debug, Clone)]
 pub struct ProcessInfo {
     pub name: String,
+    /// Unique across hosts, see [`host_pid`]
     pub pid: Pid,
     pub cpu_usage: f32,
     pub memory_mb: f64,
+    /// 0 when unknown
+    pub threads: u32,
+    /// Disk reads in KB per second
+    pub read_kb_s: f64,
+    /// Disk writes in KB per second
+    pub write_kb_s: f64,
+    pub alias: Option<String>,
+    pub group: Option<String>,
+    /// Host the process runs on, when it isn't local
+    pub host: Option<String>,
 }
 
 impl ProcessInfo {
@@ -15,13 +53,135 @@ impl ProcessInfo {
             pid,
             cpu_usage,
             memory_mb,
+            threads: 0,
+            read_kb_s: 0.0,
+            write_kb_s: 0.0,
+            alias: None,
+            group: None,
+            host: None,
+        }
+    }
+
+    /// Copies the alias, group and host of the target watching this process.
+    pub fn with_labels(mut self, target: &WatchTarget) -> Self {
+        self.alias = target.alias.clone();
+        self.group = target.group.clone();
+        self.host = target.host.clone();
+        self
+    }
+
+    /// The PID on the process's own host.
+    pub fn local_pid(&self) -> u32 {
+        match self.host {
+            Some(_) => split_host_pid(self.pid).1,
+            None => self.pid.as_u32(),
+        }
+    }
+
+    /// Whether `other` is a sample of the same program on the same host.
+    pub fn same_program(&self, other: &ProcessInfo) -> bool {
+        self.name == other.name && self.host == other.host
+    }
+
+    /// The alias if one was set, otherwise the process name.
+    pub fn display_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// A process the user asked to monitor. The PID is `None` while no running
+/// process matches, e.g. right after restoring a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchTarget {
+    pub name: String,
+    #[serde(skip)]
+    pub pid: Option<Pid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Overrides the sampling interval for this target
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub interval: Option<Duration>,
+    /// Host the process runs on, when it isn't local
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Added by `--watchlist`, which adds it again on every run,
+    /// so it isn't saved in the session
+    #[serde(skip)]
+    pub from_watchlist: bool,
+}
+
+impl WatchTarget {
+    pub fn new(name: String, pid: Pid) -> Self {
+        Self {
+            name,
+            pid: Some(pid),
+            alias: None,
+            group: None,
+            interval: None,
+            host: None,
+            from_watchlist: false,
+        }
+    }
+}
+
+/// Totals of the processes sharing a name across several hosts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostTotal {
+    pub name: String,
+    pub hosts: usize,
+    pub cpu_usage: f32,
+    pub memory_mb: f64,
+}
+
+impl HostTotal {
+    /// Totals per display name, for names running on more than one host.
+    pub fn across_hosts(processes: &[ProcessInfo]) -> Vec<Self> {
+        let mut totals: Vec<(Self, Vec<&str>)> = Vec::new();
+        for process in processes {
+            let Some(host) = process.host.as_deref() else {
+                continue;
+            };
+            let name = process.display_name();
+            let i = match totals.iter().position(|(t, _)| t.name == name) {
+                Some(i) => i,
+                None => {
+                    totals.push((
+                        Self {
+                            name: name.to_string(),
+                            hosts: 0,
+                            cpu_usage: 0.0,
+                            memory_mb: 0.0,
+                        },
+                        Vec::new(),
+                    ));
+                    totals.len() - 1
+                }
+            };
+            let (total, hosts) = &mut totals[i];
+            total.cpu_usage += process.cpu_usage;
+            total.memory_mb += process.memory_mb;
+            if !hosts.contains(&host) {
+                hosts.push(host);
+                total.hosts += 1;
+            }
         }
+        totals
+            .into_iter()
+            .map(|(total, _)| total)
+            .filter(|total| total.hosts > 1)
+            .collect()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ProcessManager {
-    monitored_processes: Vec<(String, Pid)>,
+    monitored_processes: Vec<WatchTarget>,
     current_processes: Vec<ProcessInfo>,
 }
 
@@ -34,19 +194,102 @@ impl ProcessManager {
     }
 
     pub fn add_process(&mut self, name: String, pid: Pid) {
-        self.monitored_processes.push((name, pid));
+        self.add_target(WatchTarget::new(name, pid));
+    }
+
+    /// Adds a target unless the same process is already watched.
+    pub fn add_target(&mut self, target: WatchTarget) {
+        let duplicate = self.monitored_processes.iter().any(|t| {
+            t.name == target.name
+                && t.host == target.host
+                && (t.pid == target.pid || target.pid.is_none())
+        });
+        if !duplicate {
+            self.monitored_processes.push(target);
+        }
+    }
+
+    /// Index of the target watching `pid` in
+    /// [`ProcessManager::get_monitored_processes`].
+    pub fn target_index(&self, pid: Pid) -> Option<usize> {
+        self.monitored_processes
+            .iter()
+            .position(|t| t.pid == Some(pid))
+    }
+
+    /// Stops watching the target at `index`, whether or not it is bound to
+    /// a running process.
+    pub fn remove_target(&mut self, index: usize) -> Option<WatchTarget> {
+        if index >= self.monitored_processes.len() {
+            return None;
+        }
+        let target = self.monitored_processes.remove(index);
+        if let Some(pid) = target.pid {
+            self.current_processes.retain(|p| p.pid != pid);
+        }
+        Some(target)
+    }
+
+    /// Removes every target with the given name or alias, returning how
+    /// many there were.
+    pub fn remove_named(&mut self, name: &str) -> usize {
+        let before = self.monitored_processes.len();
+        self.monitored_processes
+            .retain(|t| t.name != name && t.alias.as_deref() != Some(name));
+        let targets = &self.monitored_processes;
+        self.current_processes
+            .retain(|p| targets.iter().any(|t| t.pid == Some(p.pid)));
+        before - self.monitored_processes.len()
     }
 
-    pub fn remove_process(&mut self, index: usize) {
-        if index < self.monitored_processes.len() {
-            self.monitored_processes.remove(index);
+    /// Marks the target watching `pid` as having no running process, so it
+    /// is bound again once a process of the same name shows up.
+    pub fn unbind(&mut self, pid: Pid) {
+        if let Some(target) = self.target_mut(pid) {
+            target.pid = None;
         }
     }
 
-    pub fn get_monitored_processes(&self) -> &[(String, Pid)] {
+    pub fn get_monitored_processes(&self) -> &[WatchTarget] {
         &self.monitored_processes
     }
 
+    pub fn target_mut(&mut self, pid: Pid) -> Option<&mut WatchTarget> {
+        self.monitored_processes
+            .iter_mut()
+            .find(|t| t.pid == Some(pid))
+    }
+
+    /// Binds targets without a PID to a running process of the same name,
+    /// on the target's host when processes come from several `hosts`.
+    pub fn resolve_targets(&mut self, running: &[(Pid, String)], hosts: &[String]) {
+        let mut taken: Vec<Pid> = self
+            .monitored_processes
+            .iter()
+            .filter_map(|t| t.pid)
+            .collect();
+        for target in self
+            .monitored_processes
+            .iter_mut()
+            .filter(|t| t.pid.is_none())
+        {
+            let found = running
+                .iter()
+                .filter(|(pid, name)| {
+                    *name == target.name
+                        && !taken.contains(pid)
+                        && (hosts.is_empty()
+                            || hosts.get(split_host_pid(*pid).0) == target.host.as_ref())
+                })
+                .map(|(pid, _)| *pid)
+                .min();
+            if let Some(pid) = found {
+                target.pid = Some(pid);
+                taken.push(pid);
+            }
+        }
+    }
+
     pub fn get_current_processes(&self) -> &[ProcessInfo] {
         &self.current_processes
     }
@@ -55,16 +298,63 @@ impl ProcessManager {
         self.current_processes = processes;
     }
 
-    pub fn find_matching_processes(pattern: &str, sys: &System) -> Vec<(Pid, String)> {
-        sys.processes()
+    pub fn find_matching_processes(pattern: &str, running: &[(Pid, String)]) -> Vec<(Pid, String)> {
+        running
             .iter()
-            .filter(|(_, process)| {
-                process
-                    .name()
-                    .to_lowercase()
-                    .contains(&pattern.to_lowercase())
-            })
-            .map(|(pid, process)| (*pid, process.name().to_string()))
+            .filter(|(_, name)| name.to_lowercase().contains(&pattern.to_lowercase()))
+            .cloned()
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(name: &str, host: &str, pid: u32, cpu_usage: f32) -> ProcessInfo {
+        ProcessInfo {
+            host: Some(host.to_string()),
+            ..ProcessInfo::new(name.to_string(), Pid::from_u32(pid), cpu_usage, 100.0)
+        }
+    }
+
+    #[test]
+    fn host_pids_split_back_into_host_and_pid() {
+        let pid = host_pid(3, 4242).unwrap();
+        assert_eq!(split_host_pid(pid), (3, 4242));
+        let last = host_pid(MAX_HOSTS - 1, (1 << HOST_PID_BITS) - 1).unwrap();
+        assert_eq!(
+            split_host_pid(last),
+            (MAX_HOSTS - 1, (1 << HOST_PID_BITS) - 1)
+        );
+
+        assert_eq!(host_pid(MAX_HOSTS, 1), None);
+        assert_eq!(host_pid(0, 1 << HOST_PID_BITS), None);
+        assert_eq!(host_pid(1, u32::MAX), None);
+    }
+
+    #[test]
+    fn totals_names_running_on_several_hosts() {
+        let processes = [
+            remote("nginx", "web1", 1, 10.0),
+            remote("nginx", "web1", 2, 5.0),
+            remote("nginx", "web2", 3, 20.0),
+            remote("redis", "web1", 4, 1.0),
+            // Local processes are never part of a total
+            ProcessInfo::new("nginx".to_string(), Pid::from_u32(5), 50.0, 100.0),
+            ProcessInfo {
+                alias: Some("cache".to_string()),
+                ..remote("redis", "web2", 6, 2.0)
+            },
+        ];
+        assert_eq!(
+            HostTotal::across_hosts(&processes),
+            [HostTotal {
+                name: "nginx".to_string(),
+                hosts: 2,
+                cpu_usage: 35.0,
+                memory_mb: 300.0,
+            }]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::domain::history::{Frame, History, Metric};
+use std::time::{Duration, Instant};
+use sysinfo::Pid;
+
+const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+const DEFAULT_SPEED_INDEX: usize = 2;
+
+/// Playback position within a loaded recording.
+#[derive(Debug)]
+pub struct ReplayState {
+    pub source: String,
+    history: History,
+    cursor: usize,
+    playing: bool,
+    speed_index: usize,
+    /// Recording time that has played past the current frame
+    carry: Duration,
+    last_advance: Instant,
+}
+
+impl ReplayState {
+    pub fn new(source: String, history: History) -> Self {
+        Self {
+            source,
+            history,
+            cursor: 0,
+            playing: true,
+            speed_index: DEFAULT_SPEED_INDEX,
+            carry: Duration::ZERO,
+            last_advance: Instant::now(),
+        }
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn current_frame(&self) -> Option<&Frame> {
+        self.history.frame(self.cursor)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn speed(&self) -> f64 {
+        SPEEDS[self.speed_index]
+    }
+
+    pub fn toggle_playing(&mut self) {
+        if !self.playing && self.cursor + 1 >= self.history.len() {
+            // Restart from the beginning once the end was reached
+            self.cursor = 0;
+        }
+        self.playing = !self.playing;
+        self.carry = Duration::ZERO;
+        self.last_advance = Instant::now();
+    }
+
+    pub fn faster(&mut self) {
+        self.speed_index = (self.speed_index + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed_index = self.speed_index.saturating_sub(1);
+    }
+
+    pub fn seek_by(&mut self, frames: isize) {
+        let target = self.cursor.saturating_add_signed(frames);
+        self.seek_to(target);
+    }
+
+    pub fn seek_to(&mut self, index: usize) {
+        self.cursor = index.min(self.history.len().saturating_sub(1));
+        self.carry = Duration::ZERO;
+    }
+
+    pub fn seek_to_end(&mut self) {
+        self.seek_to(usize::MAX);
+    }
+
+    /// Moves the cursor to the frame where the process peaked, returning whether it was found.
+    pub fn jump_to_peak(&mut self, pid: Pid, metric: Metric) -> bool {
+        match self.history.peak_index(pid, metric) {
+            Some(index) => {
+                self.seek_to(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Advances playback by the wall time since the last call, scaled by the
+    /// speed. Returns true when the cursor moved.
+    pub fn advance(&mut self) -> bool {
+        let now = Instant::now();
+        let wall = now.duration_since(self.last_advance);
+        self.last_advance = now;
+        if !self.playing {
+            return false;
+        }
+
+        self.carry += wall.mul_f64(self.speed());
+        let start = self.cursor;
+        while let (Some(current), Some(next)) = (
+            self.history.frame(self.cursor),
+            self.history.frame(self.cursor + 1),
+        ) {
+            let step = next.elapsed.saturating_sub(current.elapsed);
+            if self.carry < step {
+                break;
+            }
+            self.carry -= step;
+            self.cursor += 1;
+        }
+
+        if self.cursor + 1 >= self.history.len() {
+            self.playing = false;
+        }
+        self.cursor != start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::process::ProcessInfo;
+
+    /// Two nginx workers sampled every second, the second one peaking
+    /// at `peak`.
+    fn replay(frames: u64, peak: u64) -> ReplayState {
+        let mut history = History::new();
+        for i in 0..frames {
+            let high = if i == peak { 90.0 } else { 10.0 };
+            history.push(Frame {
+                elapsed: Duration::from_secs(i),
+                processes: vec![
+                    ProcessInfo::new(
+                        "nginx".to_string(),
+                        Pid::from_u32(1),
+                        50.0,
+                        500.0 - i as f64,
+                    ),
+                    ProcessInfo::new("nginx".to_string(), Pid::from_u32(2), high, 100.0),
+                ],
+            });
+        }
+        ReplayState::new("test".to_string(), history)
+    }
+
+    /// Advances as if `wall` had passed since the last call.
+    fn advance_by(replay: &mut ReplayState, wall: Duration) -> bool {
+        replay.last_advance = Instant::now() - wall;
+        replay.advance()
+    }
+
+    #[test]
+    fn advances_with_wall_time_scaled_by_speed() {
+        let mut replay = replay(10, 0);
+        assert!(!advance_by(&mut replay, Duration::from_millis(500)));
+        assert!(advance_by(&mut replay, Duration::from_millis(600)));
+        assert_eq!(replay.cursor(), 1);
+
+        replay.faster();
+        assert_eq!(replay.speed(), 2.0);
+        advance_by(&mut replay, Duration::from_millis(1500));
+        assert_eq!(replay.cursor(), 4);
+        assert_eq!(
+            replay.current_frame().unwrap().elapsed,
+            Duration::from_secs(4)
+        );
+    }
+
+    #[test]
+    fn stops_playing_at_the_end() {
+        let mut replay = replay(3, 0);
+        assert!(replay.is_playing());
+        advance_by(&mut replay, Duration::from_secs(60));
+        assert_eq!(replay.cursor(), 2);
+        assert!(!replay.is_playing());
+        assert!(!advance_by(&mut replay, Duration::from_secs(1)));
+
+        // Playing again restarts from the beginning
+        replay.toggle_playing();
+        assert!(replay.is_playing());
+        assert_eq!(replay.cursor(), 0);
+    }
+
+    #[test]
+    fn pausing_holds_the_cursor() {
+        let mut replay = replay(10, 0);
+        replay.seek_to(3);
+        replay.toggle_playing();
+        assert!(!replay.is_playing());
+        assert!(!advance_by(&mut replay, Duration::from_secs(5)));
+        replay.toggle_playing();
+        assert_eq!(replay.cursor(), 3);
+    }
+
+    #[test]
+    fn seeks_within_the_recording() {
+        let mut replay = replay(10, 0);
+        replay.seek_by(4);
+        assert_eq!(replay.cursor(), 4);
+        replay.seek_by(-10);
+        assert_eq!(replay.cursor(), 0);
+        replay.seek_by(100);
+        assert_eq!(replay.cursor(), 9);
+        replay.seek_to(2);
+        assert_eq!(replay.cursor(), 2);
+        replay.seek_to_end();
+        assert_eq!(replay.cursor(), 9);
+
+        let mut empty = ReplayState::new("empty".to_string(), History::new());
+        empty.seek_to_end();
+        assert_eq!(empty.cursor(), 0);
+        assert!(empty.current_frame().is_none());
+    }
+
+    #[test]
+    fn clamps_the_speed() {
+        let mut replay = replay(2, 0);
+        assert_eq!(replay.speed(), 1.0);
+        for _ in 0..10 {
+            replay.faster();
+        }
+        assert_eq!(replay.speed(), 16.0);
+        for _ in 0..10 {
+            replay.slower();
+        }
+        assert_eq!(replay.speed(), 0.25);
+    }
+
+    #[test]
+    fn jumps_to_the_peak_of_the_selected_pid() {
+        let mut replay = replay(10, 6);
+        assert!(replay.jump_to_peak(Pid::from_u32(2), Metric::Cpu));
+        assert_eq!(replay.cursor(), 6);
+        // The other worker's memory peaks in the first frame
+        assert!(replay.jump_to_peak(Pid::from_u32(1), Metric::Memory));
+        assert_eq!(replay.cursor(), 0);
+        assert!(!replay.jump_to_peak(Pid::from_u32(3), Metric::Cpu));
+        assert_eq!(replay.cursor(), 0);
+    }
+}
@@ -1,5 +1,25 @@
-use crate::domain::process::{ProcessInfo, ProcessManager};
-use std::time::{Duration, Instant};
+use crate::{
+    config::Config,
+    domain::{
+        column::{Column, SortOrder},
+        control::SchedulingInfo,
+        filter::Filter,
+        history::{Frame, History, Marker},
+        process::{ProcessInfo, ProcessManager, WatchTarget, split_host_pid},
+        replay::ReplayState,
+    },
+    recording::{
+        Recording,
+        sqlite::{Database, SessionWriter},
+    },
+    sampler::Overhead,
+};
+use color_eyre::{Result, eyre::bail};
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::Pid;
+
+/// Number of live frames kept for the charts.
+const HISTORY_CAPACITY: usize = 600;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AppMode {
@@ -8,6 +28,19 @@ pub enum AppMode {
     SelectProcess,
     Stats,
     SavePrompt,
+    Replay,
+    SignalMenu,
+    ConfirmSignal,
+    Detail,
+    ReniceInput,
+    AffinityInput,
+    AliasInput,
+    GroupInput,
+    IntervalInput,
+    MarkerInput,
+    /// Choosing, ordering and sorting table columns
+    Columns,
+    FilterInput,
 }
 
 #[derive(Debug)]
@@ -19,14 +52,40 @@ pub struct AppState {
     pub candidate_processes: Vec<String>,
     pub selected_process: usize,
     pub selected_monitored_process: usize,
+    pub selected_signal: usize,
+    /// Process acted on by the signal menu and detail view
+    pub target_process: Option<ProcessInfo>,
+    pub scheduling_info: Option<SchedulingInfo>,
     pub interval: Duration,
-    pub last_tick: Instant,
-    pub stats_data: Vec<(String, Vec<ProcessInfo>)>,
+    /// End of the running burst, if any
+    pub burst_until: Option<Instant>,
+    pub overhead: Option<Overhead>,
+    pub started_at: Instant,
+    pub stats_data: History,
+    pub history: History,
+    /// Mode to go back to once a marker is entered
+    pub marker_origin: AppMode,
+    /// Mode to go back to once a filter is entered
+    pub filter_origin: AppMode,
+    /// Cursor in the column picker, an index into [`AppState::column_choices`]
+    pub selected_column: usize,
+    pub replay: Option<ReplayState>,
+    pub status_message: Option<String>,
+    pub sort: SortOrder,
+    /// Highlights matching rows; `n` and `N` jump between them
+    pub filter: Option<Filter>,
+    /// Session saved on exit, if any
+    pub session_name: Option<String>,
+    /// Agents the processes are read from, when they aren't local
+    pub hosts: Vec<String>,
+    /// Database every sampled frame and marker is also written to
+    pub database: Option<SessionWriter>,
+    pub config: Config,
     process_manager: ProcessManager,
 }
 
 impl AppState {
-    pub fn new(interval: u64) -> Self {
+    pub fn new(config: Config) -> Self {
         Self {
             mode: AppMode::Normal,
             should_quit: false,
@@ -35,17 +94,69 @@ impl AppState {
             candidate_processes: Vec::new(),
             selected_process: 0,
             selected_monitored_process: 0,
-            interval: Duration::from_secs(interval),
-            last_tick: Instant::now(),
-            stats_data: Vec::new(),
+            selected_signal: 0,
+            target_process: None,
+            scheduling_info: None,
+            interval: config.update_interval,
+            burst_until: None,
+            overhead: None,
+            started_at: Instant::now(),
+            stats_data: History::new(),
+            history: History::with_capacity(HISTORY_CAPACITY),
+            marker_origin: AppMode::Normal,
+            filter_origin: AppMode::Normal,
+            selected_column: 0,
+            replay: None,
+            status_message: None,
+            sort: SortOrder::default(),
+            filter: None,
+            session_name: None,
+            hosts: Vec::new(),
+            database: None,
             process_manager: ProcessManager::new(),
+            config,
         }
     }
 
+    pub fn new_replay(config: Config, replay: ReplayState) -> Self {
+        let mut state = Self::new(config);
+        state.mode = AppMode::Replay;
+        state.replay = Some(replay);
+        state.sync_replay();
+        state
+    }
+
+    /// Starts a session in a database that frames and markers are written
+    /// to from now on.
+    pub fn store_in(&mut self, database: Database, source: &str) -> Result<()> {
+        let started = SystemTime::now() - self.started_at.elapsed();
+        self.database = Some(database.begin_session(source, started, &self.config.thresholds)?);
+        Ok(())
+    }
+
     pub fn process_manager(&mut self) -> &mut ProcessManager {
         &mut self.process_manager
     }
 
+    /// Binds targets without a running process, see
+    /// [`ProcessManager::resolve_targets`].
+    pub fn resolve_targets(&mut self, running: &[(Pid, String)]) {
+        self.process_manager.resolve_targets(running, &self.hosts);
+    }
+
+    /// Name of the host a PID from the source belongs to, when processes
+    /// aren't local.
+    pub fn host_of(&self, pid: Pid) -> Option<String> {
+        if self.hosts.is_empty() {
+            return None;
+        }
+        self.hosts.get(split_host_pid(pid).0).cloned()
+    }
+
+    pub fn watch_targets(&self) -> &[WatchTarget] {
+        self.process_manager.get_monitored_processes()
+    }
+
     pub fn processes(&self) -> &[ProcessInfo] {
         self.process_manager.get_current_processes()
     }
@@ -56,30 +167,342 @@ impl AppState {
         self.candidate_processes.clear();
     }
 
-    pub fn tick(&mut self) {
-        self.last_tick = Instant::now();
+    /// Starts a burst, or stops the one that is running.
+    pub fn toggle_burst(&mut self) {
+        self.burst_until = match self.burst_remaining() {
+            Some(_) => None,
+            None => Some(Instant::now() + self.config.burst.duration),
+        };
     }
 
-    pub fn add_stats(&mut self, process_info: ProcessInfo) {
-        if self.mode == AppMode::Stats {
-            if let Some(entry) = self
-                .stats_data
-                .iter_mut()
-                .find(|e| e.0 == process_info.name)
-            {
-                entry.1.push(process_info);
-            } else {
-                self.stats_data
-                    .push((process_info.name.clone(), vec![process_info]));
+    pub fn burst_remaining(&self) -> Option<Duration> {
+        self.burst_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Sets the sampling interval of the selected process; `None` goes back
+    /// to the default.
+    pub fn set_selected_interval(&mut self, interval: Option<Duration>) -> bool {
+        let Some(pid) = self.selected_process_info().map(|p| p.pid) else {
+            return false;
+        };
+        match self.process_manager.target_mut(pid) {
+            Some(target) => {
+                target.interval = interval;
+                true
             }
+            None => false,
+        }
+    }
+
+    /// Appends a frame sampled at `taken_at` to the live history, and to
+    /// the stats recording while in stats mode. Fails only when writing to
+    /// the database failed, see [`AppState::store_in`].
+    pub fn record_frame(&mut self, taken_at: Instant, processes: &[ProcessInfo]) -> Result<()> {
+        let frame = Frame {
+            elapsed: taken_at.saturating_duration_since(self.started_at),
+            processes: processes.to_vec(),
+        };
+        if self.collecting_stats() {
+            self.stats_data.push(frame.clone());
+        }
+        let stored = self.store(|database| database.write_frame(&frame));
+        self.history.push(frame);
+        stored
+    }
+
+    /// Drops a marker at the current time into the live history, and into
+    /// the stats recording while one is running. Fails like
+    /// [`AppState::record_frame`].
+    pub fn add_marker(&mut self, text: &str) -> Result<()> {
+        let marker = Marker {
+            elapsed: self.started_at.elapsed(),
+            text: text.to_string(),
+        };
+        if self.collecting_stats() {
+            self.stats_data.add_marker(marker.clone());
+        }
+        let stored = self.store(|database| database.write_marker(&marker));
+        self.history.add_marker(marker);
+        stored
+    }
+
+    /// Writes to the database, if any, and stops using it on failure so a
+    /// full disk doesn't repeat the error on every tick.
+    fn store(&mut self, write: impl FnOnce(&mut SessionWriter) -> Result<()>) -> Result<()> {
+        if let Some(database) = &mut self.database
+            && let Err(e) = write(database)
+        {
+            let path = database.path().display().to_string();
+            self.database = None;
+            bail!("Stopped writing to {path}: {e}");
+        }
+        Ok(())
+    }
+
+    /// Whether sampled frames go into the stats recording. Typing a marker
+    /// doesn't pause it.
+    fn collecting_stats(&self) -> bool {
+        match self.mode {
+            AppMode::Stats => true,
+            AppMode::MarkerInput => self.marker_origin == AppMode::Stats,
+            _ => false,
+        }
+    }
+
+    /// The history shown in charts, up to and including the replay cursor.
+    pub fn chart_history(&self) -> (&History, usize) {
+        match &self.replay {
+            Some(replay) => (replay.history(), replay.cursor() + 1),
+            None => (&self.history, self.history.len()),
+        }
+    }
+
+    pub fn selected_process_info(&self) -> Option<&ProcessInfo> {
+        self.processes().get(self.selected_monitored_process)
+    }
+
+    pub fn refresh_scheduling_info(&mut self) {
+        self.scheduling_info = self
+            .target_process
+            .as_ref()
+            .filter(|p| p.host.is_none())
+            .map(|p| SchedulingInfo::query(p.pid));
+    }
+
+    /// Shows the frame under the replay cursor in the process table.
+    pub fn sync_replay(&mut self) {
+        if let Some(frame) = self.replay.as_ref().and_then(|r| r.current_frame()) {
+            let processes = frame.processes.clone();
+            self.update_processes(processes);
         }
     }
 
-    pub fn update_processes(&mut self, processes: Vec<ProcessInfo>) {
+    /// Replaces the rows of the targets in `sampled_pids` with fresh samples,
+    /// keeping the latest rows of targets that weren't due. Targets that were
+    /// due but are missing from `samples` have exited; they lose their row
+    /// and wait to be bound to a new process. Returns whether any did.
+    pub fn apply_samples(&mut self, sampled_pids: &[Pid], mut samples: Vec<ProcessInfo>) -> bool {
+        let mut exited = false;
+        for &pid in sampled_pids {
+            if !samples.iter().any(|p| p.pid == pid) {
+                self.process_manager.unbind(pid);
+                exited = true;
+            }
+        }
+        let mut previous = self.processes().to_vec();
+        let processes = self
+            .watch_targets()
+            .iter()
+            .filter_map(|target| {
+                let pid = target.pid?;
+                if sampled_pids.contains(&pid) {
+                    // Labels may have changed while the sample was taken
+                    let i = samples.iter().position(|p| p.pid == pid)?;
+                    Some(samples.swap_remove(i).with_labels(target))
+                } else {
+                    let i = previous.iter().position(|p| p.pid == pid)?;
+                    Some(previous.swap_remove(i))
+                }
+            })
+            .collect();
+        self.update_processes(processes);
+        exited
+    }
+
+    /// Replaces the rows, keeping the selection on the same PID when rows
+    /// are reordered.
+    pub fn update_processes(&mut self, mut processes: Vec<ProcessInfo>) {
+        let selected = self.selected_process_info().map(|p| p.pid);
+        self.sort.sort(&mut processes);
         self.process_manager.update_processes(processes);
+        let processes = self.processes();
+        self.selected_monitored_process = selected
+            .and_then(|pid| processes.iter().position(|p| p.pid == pid))
+            .unwrap_or_else(|| {
+                self.selected_monitored_process
+                    .min(processes.len().saturating_sub(1))
+            });
+    }
+
+    /// Re-sorts the current rows, e.g. after the sort order changed.
+    pub fn resort(&mut self) {
+        let processes = self.processes().to_vec();
+        self.update_processes(processes);
+    }
+
+    /// Every column for the column picker: the visible ones in table order,
+    /// then the hidden ones.
+    pub fn column_choices(&self) -> Vec<(Column, bool)> {
+        let visible = &self.config.columns;
+        visible
+            .iter()
+            .map(|&column| (column, true))
+            .chain(
+                Column::ALL
+                    .into_iter()
+                    .filter(|column| !visible.contains(column))
+                    .map(|column| (column, false)),
+            )
+            .collect()
+    }
+
+    /// Shows or hides a column; the last visible one stays. Hiding the
+    /// sort column goes back to insertion order.
+    pub fn toggle_column(&mut self, column: Column) {
+        let columns = &mut self.config.columns;
+        match columns.iter().position(|&c| c == column) {
+            Some(_) if columns.len() == 1 => {
+                self.status_message = Some("At least one column must stay visible".to_string());
+            }
+            Some(index) => {
+                columns.remove(index);
+                if self.sort.column == Some(column) {
+                    self.sort.column = None;
+                    self.resort();
+                }
+            }
+            None => columns.push(column),
+        }
+        self.selected_column = self
+            .column_choices()
+            .iter()
+            .position(|&(c, _)| c == column)
+            .unwrap_or_default();
+    }
+
+    /// Moves a visible column one place left (`up`) or right in the table,
+    /// keeping the picker's cursor on it.
+    pub fn move_column(&mut self, column: Column, up: bool) {
+        let columns = &mut self.config.columns;
+        let Some(index) = columns.iter().position(|&c| c == column) else {
+            return;
+        };
+        let target = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1).filter(|&i| i < columns.len())
+        };
+        if let Some(target) = target {
+            columns.swap(index, target);
+            self.selected_column = target;
+        }
+    }
+
+    /// Sorts by a column, or reverses the order if already sorted by it.
+    pub fn sort_by(&mut self, column: Column) {
+        self.sort.toggle(column);
+        self.resort();
+    }
+
+    /// Sets the filter from typed input, clearing it if the input is empty,
+    /// and selects the first match from the current row on.
+    pub fn apply_filter(&mut self, input: &str) {
+        if input.trim().is_empty() {
+            self.filter = None;
+            return;
+        }
+        match Filter::parse(input) {
+            Ok(filter) => {
+                let matches = self
+                    .processes()
+                    .iter()
+                    .filter(|p| filter.matches(p))
+                    .count();
+                self.status_message = Some(format!(
+                    "{matches} of {} processes match /{}",
+                    self.processes().len(),
+                    filter.text
+                ));
+                self.filter = Some(filter);
+                if !self
+                    .selected_process_info()
+                    .is_some_and(|p| self.filter.as_ref().is_some_and(|f| f.matches(p)))
+                {
+                    self.jump_to_match(true);
+                }
+            }
+            Err(e) => self.status_message = Some(format!("Invalid filter: {e}")),
+        }
+    }
+
+    /// Selects the next (`forward`) or previous process matching the
+    /// filter, wrapping around the table.
+    pub fn jump_to_match(&mut self, forward: bool) {
+        let Some(filter) = &self.filter else {
+            self.status_message = Some("No filter set, press / to add one".to_string());
+            return;
+        };
+        let len = self.processes().len();
+        let next = (1..=len)
+            .map(|step| {
+                if forward {
+                    (self.selected_monitored_process + step) % len
+                } else {
+                    (self.selected_monitored_process + len - step % len) % len
+                }
+            })
+            .find(|&i| filter.matches(&self.processes()[i]));
+        match next {
+            Some(index) => self.selected_monitored_process = index,
+            None => self.status_message = Some(format!("No process matches /{}", filter.text)),
+        }
+    }
+
+    /// Sets the alias or group of the selected process; an empty value clears it.
+    pub fn label_selected(&mut self, alias: bool, value: &str) {
+        let Some(pid) = self.selected_process_info().map(|p| p.pid) else {
+            return;
+        };
+        let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        if let Some(target) = self.process_manager.target_mut(pid) {
+            if alias {
+                target.alias = value;
+            } else {
+                target.group = value;
+            }
+            let target = target.clone();
+            let processes = self
+                .processes()
+                .iter()
+                .cloned()
+                .map(|p| {
+                    if p.pid == pid {
+                        p.with_labels(&target)
+                    } else {
+                        p
+                    }
+                })
+                .collect();
+            self.update_processes(processes);
+        }
     }
 
     pub fn clear_stats(&mut self) {
         self.stats_data.clear();
     }
+
+    /// The stats recorded so far. Live frames are timed from `started_at`,
+    /// so the recording knows when it started; replayed ones don't.
+    pub fn stats_recording(&self) -> Recording {
+        let origin = self
+            .replay
+            .is_none()
+            .then(|| SystemTime::now() - self.started_at.elapsed());
+        Recording::from_history(&self.stats_data, origin, &self.config.thresholds)
+    }
+
+    /// Where the stats being recorded come from, shown in reports.
+    pub fn recording_source(&self) -> String {
+        let mut source = match &self.replay {
+            Some(replay) => format!("Replay of {}", replay.source),
+            None if self.hosts.is_empty() => "Live capture".to_string(),
+            None => format!("Live capture from {}", self.hosts.join(", ")),
+        };
+        if let Some(session) = &self.session_name {
+            source.push_str(&format!(" (session {session})"));
+        }
+        source
+    }
 }
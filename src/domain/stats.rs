@@ -0,0 +1,227 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Summary {
+    pub mean: f64,
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mean = mean(samples);
+        Some(Self {
+            mean,
+            stddev: variance(samples, mean).sqrt(),
+            min: samples.iter().copied().fold(f64::INFINITY, f64::min),
+            max: samples.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+/// Result of Welch's unequal-variances t-test between two sample sets.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Comparison {
+    /// Ratio of the mean of `b` to the mean of `a`
+    pub ratio: f64,
+    pub t: f64,
+    pub degrees_of_freedom: f64,
+    /// Two-tailed p-value
+    pub p_value: f64,
+    pub significant: bool,
+}
+
+pub const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> Option<Comparison> {
+    if a.len() < 2 || b.len() < 2 {
+        return None;
+    }
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let se_a = variance(a, mean_a) / a.len() as f64;
+    let se_b = variance(b, mean_b) / b.len() as f64;
+    let ratio = if mean_a == 0.0 {
+        f64::NAN
+    } else {
+        mean_b / mean_a
+    };
+
+    if se_a + se_b == 0.0 {
+        // Both sets are constant: any difference in means is exact
+        let differs = mean_a != mean_b;
+        return Some(Comparison {
+            ratio,
+            t: if differs { f64::INFINITY } else { 0.0 },
+            degrees_of_freedom: (a.len() + b.len() - 2) as f64,
+            p_value: if differs { 0.0 } else { 1.0 },
+            significant: differs,
+        });
+    }
+
+    let t = (mean_b - mean_a) / (se_a + se_b).sqrt();
+    let df = (se_a + se_b).powi(2)
+        / (se_a.powi(2) / (a.len() - 1) as f64 + se_b.powi(2) / (b.len() - 1) as f64);
+    let p_value = incomplete_beta(df / 2.0, 0.5, df / (df + t * t));
+
+    Some(Comparison {
+        ratio,
+        t,
+        degrees_of_freedom: df,
+        p_value,
+        significant: p_value < SIGNIFICANCE_LEVEL,
+    })
+}
+
+fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+/// Sample variance (Bessel-corrected); zero for a single sample.
+fn variance(samples: &[f64], mean: f64) -> f64 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (samples.len() - 1) as f64
+}
+
+/// Regularized incomplete beta function I_x(a, b).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges quickly only on this side of the mean
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 200;
+    const EPSILON: f64 = 1e-12;
+    const TINY: f64 = 1e-300;
+
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+
+        let aa = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 + aa * d;
+        d = if d.abs() < TINY { 1.0 / TINY } else { 1.0 / d };
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        h *= d * c;
+
+        let aa = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 + aa * d;
+        d = if d.abs() < TINY { 1.0 / TINY } else { 1.0 / d };
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+/// Lanczos approximation of ln(Γ(x)) for x > 0.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000_000_000_190_015, |acc, (i, c)| {
+            acc + c / (x + 1.0 + i as f64)
+        });
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    #[test]
+    fn summarizes_samples() {
+        let summary = Summary::from_samples(&[2.0, 4.0, 4.0, 6.0]).unwrap();
+        assert_eq!(summary.mean, 4.0);
+        assert!(close(summary.stddev, (8.0f64 / 3.0).sqrt(), 1e-12));
+        assert_eq!((summary.min, summary.max), (2.0, 6.0));
+        assert!(Summary::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn matches_reference_p_values() {
+        // t = 2 with 8 degrees of freedom, p = 0.0805 from t tables
+        let result = welch_t_test(&[1.0, 2.0, 3.0, 4.0, 5.0], &[3.0, 4.0, 5.0, 6.0, 7.0]).unwrap();
+        assert!(close(result.t, 2.0, 1e-12));
+        assert!(close(result.degrees_of_freedom, 8.0, 1e-12));
+        assert!(close(result.p_value, 0.0805, 1e-4), "{}", result.p_value);
+        assert!(!result.significant);
+        assert!(close(result.ratio, 5.0 / 3.0, 1e-12));
+    }
+
+    #[test]
+    fn finds_clear_differences() {
+        let a = [100.0, 101.0, 99.0, 100.5, 99.5];
+        let b = [120.0, 121.0, 119.0, 120.5, 119.5];
+        let result = welch_t_test(&a, &b).unwrap();
+        assert!(result.significant);
+        assert!(result.p_value < 1e-6);
+        // Swapping the sets only flips the sign
+        let swapped = welch_t_test(&b, &a).unwrap();
+        assert!(close(swapped.t, -result.t, 1e-9));
+        assert!(close(swapped.p_value, result.p_value, 1e-12));
+    }
+
+    #[test]
+    fn handles_constant_and_short_samples() {
+        let same = welch_t_test(&[3.0, 3.0], &[3.0, 3.0, 3.0]).unwrap();
+        assert_eq!((same.p_value, same.significant), (1.0, false));
+        let differ = welch_t_test(&[3.0, 3.0], &[4.0, 4.0]).unwrap();
+        assert_eq!((differ.p_value, differ.significant), (0.0, true));
+        assert!(
+            welch_t_test(&[0.0, 0.0], &[1.0, 2.0])
+                .unwrap()
+                .ratio
+                .is_nan()
+        );
+        assert!(welch_t_test(&[1.0], &[1.0, 2.0]).is_none());
+    }
+}
@@ -1,13 +1,99 @@
-use crate::domain::{
-    process::ProcessManager,
-    state::{AppMode, AppState},
+use crate::{
+    domain::{
+        control::{self, Signal},
+        history::Metric,
+        process::{ProcessInfo, ProcessManager, WatchTarget},
+        state::{AppMode, AppState},
+    },
+    recording::RecordingFormat,
+    report::{self, Report},
+    sampler::SamplerEvent,
+    session::Session,
+    source::MetricsSource,
+    utils,
 };
 use color_eyre::Result;
-use crossterm::event::KeyCode;
-use sysinfo::System;
+use crossterm::event::{
+    self as crossterm_event, Event, KeyCode, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::layout::Rect;
+use std::{sync::mpsc::Sender, thread};
+
+use crate::ui;
+
+/// Frames skipped by `[` and `]` during replay.
+const REPLAY_SEEK_STEP: isize = 10;
+
+/// Everything the main loop reacts to.
+#[derive(Debug)]
+pub enum AppEvent {
+    Key(KeyCode),
+    Mouse(MouseEvent),
+    Resize,
+    Sampler(SamplerEvent),
+    /// Marker text sent with `catz mark --tui`
+    Mark(String),
+}
+
+impl From<SamplerEvent> for AppEvent {
+    fn from(event: SamplerEvent) -> Self {
+        AppEvent::Sampler(event)
+    }
+}
+
+/// Forwards terminal input to the main loop until it stops listening.
+pub fn spawn_input_thread(events: Sender<AppEvent>) {
+    thread::spawn(move || {
+        loop {
+            let event = match crossterm_event::read() {
+                Ok(Event::Key(key)) => AppEvent::Key(key.code),
+                Ok(Event::Mouse(mouse))
+                    if mouse.kind == MouseEventKind::Down(MouseButton::Left) =>
+                {
+                    AppEvent::Mouse(mouse)
+                }
+                Ok(Event::Resize(..)) => AppEvent::Resize,
+                Ok(_) => continue,
+                Err(_) => return,
+            };
+            if events.send(event).is_err() {
+                return;
+            }
+        }
+    });
+}
 
-pub fn handle_key_events(key_event: KeyCode, state: &mut AppState, sys: &System) -> Result<()> {
+pub fn handle_key_events(
+    key_event: KeyCode,
+    state: &mut AppState,
+    source: &mut dyn MetricsSource,
+    page_rows: usize,
+) -> Result<()> {
+    state.status_message = None;
+    let key_event = match state.mode {
+        AppMode::Normal | AppMode::Stats => state.config.keybindings.translate(key_event),
+        _ => key_event,
+    };
     match (key_event, state.mode) {
+        // Free-text labels may contain any character, including 'q'
+        (KeyCode::Esc, AppMode::AliasInput | AppMode::GroupInput) => {
+            state.input_buffer.clear();
+            state.mode = AppMode::Normal;
+        }
+        (KeyCode::Esc, AppMode::FilterInput) => {
+            state.input_buffer.clear();
+            state.mode = state.filter_origin;
+        }
+        (KeyCode::Esc, AppMode::MarkerInput) => {
+            state.input_buffer.clear();
+            state.mode = state.marker_origin;
+        }
+        (
+            KeyCode::Char(c),
+            AppMode::AliasInput | AppMode::GroupInput | AppMode::MarkerInput | AppMode::FilterInput,
+        ) => {
+            state.input_buffer.push(c);
+        }
         (KeyCode::Char('q') | KeyCode::Esc, AppMode::SavePrompt) => {
             state.mode = AppMode::Stats;
             state.save_filename.clear();
@@ -15,6 +101,27 @@ pub fn handle_key_events(key_event: KeyCode, state: &mut AppState, sys: &System)
         (KeyCode::Char('q') | KeyCode::Esc, AppMode::InputPattern | AppMode::SelectProcess) => {
             state.cancel_input();
         }
+        (KeyCode::Char('q') | KeyCode::Esc, AppMode::SignalMenu | AppMode::ConfirmSignal)
+        | (KeyCode::Char('n'), AppMode::ConfirmSignal) => {
+            state.mode = AppMode::Normal;
+            state.target_process = None;
+        }
+        (KeyCode::Char('q') | KeyCode::Esc, AppMode::ReniceInput | AppMode::AffinityInput) => {
+            state.mode = AppMode::Detail;
+            state.input_buffer.clear();
+        }
+        (KeyCode::Char('q') | KeyCode::Esc, AppMode::IntervalInput) => {
+            state.mode = AppMode::Normal;
+            state.input_buffer.clear();
+        }
+        (KeyCode::Char('q') | KeyCode::Esc, AppMode::Detail) => {
+            state.mode = AppMode::Normal;
+            state.target_process = None;
+            state.scheduling_info = None;
+        }
+        (KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('c'), AppMode::Columns) => {
+            state.mode = AppMode::Normal;
+        }
         (KeyCode::Char('q') | KeyCode::Esc, _) => {
             state.should_quit = true;
         }
@@ -31,34 +138,275 @@ pub fn handle_key_events(key_event: KeyCode, state: &mut AppState, sys: &System)
             state.save_filename.clear();
         }
         (KeyCode::Char('d'), AppMode::Normal) => {
-            let selected = state.selected_monitored_process;
-            state.process_manager().remove_process(selected);
+            if let Some(pid) = state.selected_process_info().map(|p| p.pid) {
+                let manager = state.process_manager();
+                if let Some(index) = manager.target_index(pid) {
+                    manager.remove_target(index);
+                }
+                let len = state.processes().len();
+                if state.selected_monitored_process >= len {
+                    state.selected_monitored_process = len.saturating_sub(1);
+                }
+            }
+        }
+        (KeyCode::Char('r'), AppMode::Normal) => {
+            if let Some(process) = state.selected_process_info() {
+                state.input_buffer = process.alias.clone().unwrap_or_default();
+                state.mode = AppMode::AliasInput;
+            }
+        }
+        (KeyCode::Char('g'), AppMode::Normal) => {
+            if let Some(process) = state.selected_process_info() {
+                state.input_buffer = process.group.clone().unwrap_or_default();
+                state.mode = AppMode::GroupInput;
+            }
+        }
+        (KeyCode::Enter, AppMode::AliasInput | AppMode::GroupInput) => {
+            let value = std::mem::take(&mut state.input_buffer);
+            state.label_selected(state.mode == AppMode::AliasInput, &value);
+            state.mode = AppMode::Normal;
+        }
+        (KeyCode::Char('m'), AppMode::Normal | AppMode::Stats) => {
+            state.marker_origin = state.mode;
+            state.input_buffer.clear();
+            state.mode = AppMode::MarkerInput;
+        }
+        (KeyCode::Enter, AppMode::MarkerInput) => {
+            let text = std::mem::take(&mut state.input_buffer);
+            let text = text.trim();
+            if !text.is_empty() {
+                add_marker(state, text);
+            }
+            state.mode = state.marker_origin;
+        }
+        (KeyCode::Char('i'), AppMode::Normal) => {
+            if let Some(pid) = state.selected_process_info().map(|p| p.pid) {
+                state.input_buffer = state
+                    .watch_targets()
+                    .iter()
+                    .find(|t| t.pid == Some(pid))
+                    .and_then(|t| t.interval)
+                    .map(|interval| humantime::format_duration(interval).to_string())
+                    .unwrap_or_default();
+                state.mode = AppMode::IntervalInput;
+            }
+        }
+        (KeyCode::Enter, AppMode::IntervalInput) => {
+            let input = std::mem::take(&mut state.input_buffer);
+            let interval = Some(input.trim())
+                .filter(|i| !i.is_empty())
+                .map(utils::parse_interval)
+                .transpose();
+            state.status_message = Some(match interval {
+                Ok(interval) if state.set_selected_interval(interval) => match interval {
+                    Some(interval) => {
+                        format!("Sampling every {}", humantime::format_duration(interval))
+                    }
+                    None => format!(
+                        "Sampling at the default interval ({})",
+                        humantime::format_duration(state.interval)
+                    ),
+                },
+                Ok(_) => "No process selected".to_string(),
+                Err(e) => e,
+            });
+            state.mode = AppMode::Normal;
+        }
+        (KeyCode::Char('b'), AppMode::Normal | AppMode::Stats) => {
+            state.toggle_burst();
+            state.status_message = Some(match state.burst_remaining() {
+                Some(_) => format!(
+                    "Burst: sampling every {} for {}",
+                    humantime::format_duration(state.config.burst.interval),
+                    humantime::format_duration(state.config.burst.duration)
+                ),
+                None => "Burst stopped".to_string(),
+            });
+        }
+        (KeyCode::Char('o'), AppMode::Normal) => {
+            let columns = state.config.columns.clone();
+            state.sort.cycle(&columns);
+            state.resort();
+        }
+        (KeyCode::Char('O'), AppMode::Normal) => {
+            state.sort.descending = !state.sort.descending;
+            state.resort();
+        }
+        (KeyCode::Char('c'), AppMode::Normal) => {
+            state.selected_column = 0;
+            state.mode = AppMode::Columns;
+        }
+        (KeyCode::Up | KeyCode::Char('k'), AppMode::Columns) => {
+            state.selected_column = state.selected_column.saturating_sub(1);
+        }
+        (KeyCode::Down | KeyCode::Char('j'), AppMode::Columns) => {
+            let last = state.column_choices().len() - 1;
+            state.selected_column = (state.selected_column + 1).min(last);
+        }
+        (KeyCode::Char(' ' | 'K' | 'J') | KeyCode::Enter, AppMode::Columns) => {
+            let Some(&(column, _)) = state.column_choices().get(state.selected_column) else {
+                return Ok(());
+            };
+            match key_event {
+                KeyCode::Char(' ') => state.toggle_column(column),
+                KeyCode::Char('K') => state.move_column(column, true),
+                KeyCode::Char('J') => state.move_column(column, false),
+                _ => state.sort_by(column),
+            }
+        }
+        (KeyCode::Char('/'), AppMode::Normal | AppMode::Replay) => {
+            state.filter_origin = state.mode;
+            state.input_buffer = state
+                .filter
+                .as_ref()
+                .map(|f| f.text.clone())
+                .unwrap_or_default();
+            state.mode = AppMode::FilterInput;
+        }
+        (KeyCode::Enter, AppMode::FilterInput) => {
+            let input = std::mem::take(&mut state.input_buffer);
+            state.mode = state.filter_origin;
+            state.apply_filter(&input);
+        }
+        (KeyCode::Char('n'), AppMode::Normal | AppMode::Replay) => state.jump_to_match(true),
+        (KeyCode::Char('N'), AppMode::Normal | AppMode::Replay) => state.jump_to_match(false),
+        (KeyCode::Char('w'), AppMode::Normal) => {
+            state.status_message = Some(match state.session_name.clone() {
+                Some(name) => match Session::capture(state).save(&name) {
+                    Ok(path) => format!("Saved session {name} to {}", path.display()),
+                    Err(e) => format!("Failed to save session {name}: {e}"),
+                },
+                None => "Sessions are disabled (--no-session)".to_string(),
+            });
+        }
+        (KeyCode::Char('k'), AppMode::Normal)
+            if state
+                .selected_process_info()
+                .is_some_and(|p| !controllable(p, source)) =>
+        {
+            state.status_message =
+                Some("Signals and scheduling only work on local processes".to_string());
+        }
+        (KeyCode::Char('n' | 'c' | 'k'), AppMode::Detail)
+            if state
+                .target_process
+                .as_ref()
+                .is_some_and(|p| !controllable(p, source)) =>
+        {
+            state.status_message =
+                Some("Signals and scheduling only work on local processes".to_string());
+        }
+        (KeyCode::Char('k'), AppMode::Normal) => {
+            if let Some(process) = state.selected_process_info().cloned() {
+                state.target_process = Some(process);
+                state.selected_signal = 0;
+                state.mode = AppMode::SignalMenu;
+            }
+        }
+        (KeyCode::Enter, AppMode::Normal) => {
+            if let Some(process) = state.selected_process_info().cloned() {
+                let local = controllable(&process, source);
+                state.target_process = Some(process);
+                if local {
+                    state.refresh_scheduling_info();
+                } else {
+                    state.scheduling_info = None;
+                }
+                state.mode = AppMode::Detail;
+            }
+        }
+        (KeyCode::Char('n'), AppMode::Detail) => {
+            state.mode = AppMode::ReniceInput;
+            state.input_buffer.clear();
+        }
+        (KeyCode::Char('c'), AppMode::Detail) => {
+            state.mode = AppMode::AffinityInput;
+            state.input_buffer.clear();
+        }
+        (KeyCode::Char('k'), AppMode::Detail) => {
+            state.selected_signal = 0;
+            state.mode = AppMode::SignalMenu;
+        }
+        (KeyCode::Enter, AppMode::ReniceInput | AppMode::AffinityInput) => {
+            apply_scheduling_change(state);
+        }
+        (KeyCode::Enter, AppMode::SignalMenu) => {
+            state.mode = AppMode::ConfirmSignal;
+        }
+        (KeyCode::Char('y') | KeyCode::Enter, AppMode::ConfirmSignal) => {
+            if let Some(process) = state.target_process.take() {
+                let signal = Signal::ALL[state.selected_signal];
+                state.status_message = Some(match control::send_signal(process.pid, signal) {
+                    Ok(()) => format!(
+                        "Sent {} to {} ({})",
+                        signal.name(),
+                        process.name,
+                        process.pid
+                    ),
+                    Err(e) => format!(
+                        "Failed to send {} to {} ({}): {}",
+                        signal.name(),
+                        process.name,
+                        process.pid,
+                        control::describe_error(&e)
+                    ),
+                });
+            }
+            state.mode = AppMode::Normal;
         }
         (KeyCode::Char(c), AppMode::SavePrompt) => {
             state.save_filename.push(c);
         }
-        (KeyCode::Char(c), AppMode::InputPattern) => {
+        (
+            KeyCode::Char(c),
+            AppMode::InputPattern
+            | AppMode::ReniceInput
+            | AppMode::AffinityInput
+            | AppMode::IntervalInput,
+        ) => {
             state.input_buffer.push(c);
         }
         (KeyCode::Backspace, AppMode::SavePrompt) => {
             state.save_filename.pop();
         }
-        (KeyCode::Backspace, AppMode::InputPattern) => {
+        (
+            KeyCode::Backspace,
+            AppMode::InputPattern
+            | AppMode::ReniceInput
+            | AppMode::AffinityInput
+            | AppMode::AliasInput
+            | AppMode::GroupInput
+            | AppMode::MarkerInput
+            | AppMode::FilterInput
+            | AppMode::IntervalInput,
+        ) => {
             state.input_buffer.pop();
         }
         (KeyCode::Enter, AppMode::SavePrompt) => {
-            if state.save_filename.ends_with(".csv") {
-                if !state.save_filename.is_empty() {
-                    let _ = crate::ui::components::save_dialog::save_stats_to_csv(
-                        &state.stats_data,
-                        &state.save_filename,
-                    );
+            let path = state.config.export.resolve(&state.save_filename);
+            let is_report = report::is_report_path(&path);
+            if is_report || RecordingFormat::from_path(&path).is_some() {
+                let recording = state.stats_recording();
+                let saved = if is_report {
+                    Report::new(recording, state.recording_source())
+                        .and_then(|report| report.save(&path))
+                } else {
+                    recording.save(&path)
+                };
+                match saved {
+                    Ok(()) => {
+                        state.status_message = Some(format!("Saved stats to {}", path.display()));
+                        state.mode = AppMode::Normal;
+                        state.save_filename.clear();
+                        state.clear_stats();
+                    }
+                    // The stats and file name are kept, to retry with another path
+                    Err(e) => state.status_message = Some(format!("Failed to save stats: {e}")),
                 }
-                state.mode = AppMode::Normal;
-                state.save_filename.clear();
-                state.clear_stats();
             } else {
-                state.save_filename.push_str(" (must end with .csv)");
+                state
+                    .save_filename
+                    .push_str(" (must end with .csv, .json, .ndjson, .db or .html)");
             }
         }
         (KeyCode::Enter, AppMode::SelectProcess) => {
@@ -67,51 +415,214 @@ pub fn handle_key_events(key_event: KeyCode, state: &mut AppState, sys: &System)
                 .get(state.selected_process)
                 .cloned()
             {
+                // The first matching process, on every host it runs on
                 let pattern = state.input_buffer.clone();
-                if let Some((pid, _)) = ProcessManager::find_matching_processes(&pattern, sys)
-                    .into_iter()
-                    .find(|(_, name)| name == &selected_process)
+                let mut hosts = Vec::new();
+                for (pid, name) in
+                    ProcessManager::find_matching_processes(&pattern, &source.processes())
                 {
-                    state.process_manager().add_process(selected_process, pid);
+                    let host = state.host_of(pid);
+                    if name != selected_process || hosts.contains(&host) {
+                        continue;
+                    }
+                    hosts.push(host.clone());
+                    state.process_manager().add_target(WatchTarget {
+                        host,
+                        ..WatchTarget::new(name, pid)
+                    });
                 }
             }
             state.mode = AppMode::Normal;
             state.input_buffer.clear();
             state.candidate_processes.clear();
         }
-        (KeyCode::Enter, AppMode::InputPattern) => {
-            if !state.input_buffer.is_empty() {
-                let candidates = ProcessManager::find_matching_processes(&state.input_buffer, sys);
-                state.candidate_processes = candidates.into_iter().map(|(_, name)| name).collect();
-                if !state.candidate_processes.is_empty() {
-                    state.mode = AppMode::SelectProcess;
-                    state.selected_process = 0;
-                }
+        (KeyCode::Enter, AppMode::InputPattern) if !state.input_buffer.is_empty() => {
+            let candidates =
+                ProcessManager::find_matching_processes(&state.input_buffer, &source.processes());
+            state.candidate_processes = candidates.into_iter().map(|(_, name)| name).collect();
+            if !state.candidate_processes.is_empty() {
+                state.mode = AppMode::SelectProcess;
+                state.selected_process = 0;
             }
         }
-        (KeyCode::Up, AppMode::SelectProcess) => {
-            if state.selected_process > 0 {
-                state.selected_process -= 1;
-            }
+        (KeyCode::Up, AppMode::SignalMenu) if state.selected_signal > 0 => {
+            state.selected_signal -= 1;
         }
-        (KeyCode::Up, AppMode::Normal) => {
-            if !state.processes().is_empty() && state.selected_monitored_process > 0 {
-                state.selected_monitored_process -= 1;
-            }
+        (KeyCode::Down, AppMode::SignalMenu) if state.selected_signal + 1 < Signal::ALL.len() => {
+            state.selected_signal += 1;
         }
-        (KeyCode::Down, AppMode::SelectProcess) => {
-            if state.selected_process + 1 < state.candidate_processes.len() {
-                state.selected_process += 1;
-            }
+        (
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End,
+            AppMode::SelectProcess,
+        ) => {
+            let len = state.candidate_processes.len();
+            state.selected_process = navigate(key_event, state.selected_process, len, page_rows);
         }
-        (KeyCode::Down, AppMode::Normal) => {
-            if !state.processes().is_empty()
-                && state.selected_monitored_process + 1 < state.processes().len()
-            {
-                state.selected_monitored_process += 1;
-            }
+        (
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End,
+            AppMode::Normal | AppMode::Replay,
+        ) => {
+            let len = state.processes().len();
+            state.selected_monitored_process =
+                navigate(key_event, state.selected_monitored_process, len, page_rows);
         }
+        (_, AppMode::Replay) => handle_replay_keys(key_event, state),
         _ => {}
     }
     Ok(())
 }
+
+/// Adds a marker typed in or sent with `catz mark --tui`.
+pub fn add_marker(state: &mut AppState, text: &str) {
+    state.status_message = Some(match state.add_marker(text) {
+        Ok(()) => format!("Added marker \"{text}\""),
+        Err(e) => e.to_string(),
+    });
+}
+
+/// Sorts by the column whose header was clicked, or reverses the order on a
+/// second click. Returns whether anything changed.
+pub fn handle_mouse_event(mouse: MouseEvent, state: &mut AppState, size: Rect) -> bool {
+    match ui::header_column_at(state, size, mouse.column, mouse.row) {
+        Some(column) => {
+            state.sort_by(column);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Moves a selection among `len` rows by one row, a page of `page_rows`, or
+/// to either end, stopping at the first and last rows.
+fn navigate(key_event: KeyCode, selected: usize, len: usize, page_rows: usize) -> usize {
+    let last = len.saturating_sub(1);
+    let page_rows = page_rows.max(1);
+    match key_event {
+        KeyCode::Up => selected.saturating_sub(1),
+        KeyCode::Down => selected + 1,
+        KeyCode::PageUp => selected.saturating_sub(page_rows),
+        KeyCode::PageDown => selected + page_rows,
+        KeyCode::Home => 0,
+        KeyCode::End => last,
+        _ => selected,
+    }
+    .min(last)
+}
+
+/// Whether signals and scheduling changes reach the process itself, and
+/// not whichever local process happens to have its PID.
+fn controllable(process: &ProcessInfo, source: &dyn MetricsSource) -> bool {
+    process.host.is_none() && source.is_local()
+}
+
+fn apply_scheduling_change(state: &mut AppState) {
+    let Some(process) = state.target_process.clone() else {
+        state.mode = AppMode::Normal;
+        return;
+    };
+    let input = state.input_buffer.trim().to_string();
+    let result = match state.mode {
+        AppMode::ReniceInput => match input.parse::<i32>() {
+            Ok(nice) => control::set_priority(process.pid, nice)
+                .map(|()| {
+                    format!(
+                        "Set nice value of {} ({}) to {}",
+                        process.name, process.pid, nice
+                    )
+                })
+                .map_err(|e| control::describe_error(&e)),
+            Err(_) => Err(format!("invalid nice value {input:?}")),
+        },
+        _ => match control::parse_cpu_list(&input) {
+            Ok(cpus) => control::set_cpu_affinity(process.pid, &cpus)
+                .map(|()| {
+                    format!(
+                        "Pinned {} ({}) to CPUs {}",
+                        process.name,
+                        process.pid,
+                        control::format_cpu_list(&cpus)
+                    )
+                })
+                .map_err(|e| control::describe_error(&e)),
+            Err(e) => Err(e),
+        },
+    };
+
+    state.status_message = Some(match result {
+        Ok(message) => message,
+        Err(e) => format!("Failed to update {} ({}): {}", process.name, process.pid, e),
+    });
+    state.input_buffer.clear();
+    state.refresh_scheduling_info();
+    state.mode = AppMode::Detail;
+}
+
+fn handle_replay_keys(key_event: KeyCode, state: &mut AppState) {
+    let selected = state.selected_process_info().cloned();
+    let Some(replay) = &mut state.replay else {
+        return;
+    };
+    match key_event {
+        KeyCode::Char(' ') => replay.toggle_playing(),
+        KeyCode::Char('+') | KeyCode::Char('=') => replay.faster(),
+        KeyCode::Char('-') => replay.slower(),
+        KeyCode::Left => replay.seek_by(-1),
+        KeyCode::Right => replay.seek_by(1),
+        KeyCode::Char('[') => replay.seek_by(-REPLAY_SEEK_STEP),
+        KeyCode::Char(']') => replay.seek_by(REPLAY_SEEK_STEP),
+        KeyCode::Char('g') => replay.seek_to(0),
+        KeyCode::Char('G') => replay.seek_to_end(),
+        KeyCode::Char('p') | KeyCode::Char('P') => {
+            let metric = if key_event == KeyCode::Char('p') {
+                Metric::Cpu
+            } else {
+                Metric::Memory
+            };
+            let Some(process) = selected else {
+                return;
+            };
+            if !replay.jump_to_peak(process.pid, metric) {
+                state.status_message = Some(format!("No samples for {}", process.name));
+                return;
+            }
+        }
+        _ => return,
+    }
+    state.sync_replay();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigate_stops_at_both_ends() {
+        assert_eq!(navigate(KeyCode::Up, 0, 10, 4), 0);
+        assert_eq!(navigate(KeyCode::Down, 3, 10, 4), 4);
+        assert_eq!(navigate(KeyCode::Down, 9, 10, 4), 9);
+        assert_eq!(navigate(KeyCode::PageDown, 3, 10, 4), 7);
+        assert_eq!(navigate(KeyCode::PageDown, 7, 10, 4), 9);
+        assert_eq!(navigate(KeyCode::PageUp, 7, 10, 4), 3);
+        assert_eq!(navigate(KeyCode::PageUp, 2, 10, 4), 0);
+        assert_eq!(navigate(KeyCode::Home, 5, 10, 4), 0);
+        assert_eq!(navigate(KeyCode::End, 5, 10, 4), 9);
+    }
+
+    #[test]
+    fn navigate_handles_empty_lists_and_pages() {
+        assert_eq!(navigate(KeyCode::End, 0, 0, 4), 0);
+        assert_eq!(navigate(KeyCode::Down, 0, 0, 4), 0);
+        // A page is at least one row, even before the first render
+        assert_eq!(navigate(KeyCode::PageDown, 0, 10, 0), 1);
+    }
+}
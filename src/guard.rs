@@ -0,0 +1,436 @@
+//! Resource budgets for a process, checked while it runs.
+//!
+//! ```no_run
+//! use catz::guard::{ByteSize, ResourceGuard};
+//!
+//! let child = std::process::Command::new("./my-service").spawn().unwrap();
+//! let guard = ResourceGuard::watch(child.id())
+//!     .max_rss(500.mib())
+//!     .max_avg_cpu(50.0);
+//! // ... exercise the service ...
+//! let report = guard.finish().expect("service stayed within budget");
+//! println!("peak RSS: {:.1} MB", report.peak_rss_mb);
+//! ```
+
+use serde::Serialize;
+use std::{
+    fmt,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+use sysinfo::Pid;
+
+use crate::{
+    domain::{process::WatchTarget, stats::Summary},
+    sampler::{SamplerEvent, SamplerHandle, Schedule},
+    source::SourceKind,
+};
+
+/// Sampling interval used unless [`ResourceGuard::interval`] says otherwise.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A memory size in bytes, e.g. `500.mib()`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Bytes(pub u64);
+
+impl Bytes {
+    pub fn as_mb(self) -> f64 {
+        self.0 as f64 / 1024.0 / 1024.0
+    }
+}
+
+pub trait ByteSize {
+    fn kib(self) -> Bytes;
+    fn mib(self) -> Bytes;
+    fn gib(self) -> Bytes;
+}
+
+/// Sizes too large for a `u64` saturate at `u64::MAX` bytes.
+impl ByteSize for u64 {
+    fn kib(self) -> Bytes {
+        Bytes(self.saturating_mul(1 << 10))
+    }
+
+    fn mib(self) -> Bytes {
+        Bytes(self.saturating_mul(1 << 20))
+    }
+
+    fn gib(self) -> Bytes {
+        Bytes(self.saturating_mul(1 << 30))
+    }
+}
+
+/// Limits a process must stay within while it is watched.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Budget {
+    pub max_rss_mb: Option<f64>,
+    pub max_avg_cpu: Option<f64>,
+    pub max_cpu: Option<f64>,
+}
+
+/// A limit that was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "metric", rename_all = "snake_case")]
+pub enum Violation {
+    Rss { peak_mb: f64, limit_mb: f64 },
+    AvgCpu { average: f64, limit: f64 },
+    Cpu { peak: f64, limit: f64 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Rss { peak_mb, limit_mb } => {
+                write!(f, "peak RSS {peak_mb:.1} MB exceeds {limit_mb:.1} MB")
+            }
+            Violation::AvgCpu { average, limit } => {
+                write!(f, "average CPU {average:.1}% exceeds {limit:.1}%")
+            }
+            Violation::Cpu { peak, limit } => {
+                write!(f, "peak CPU {peak:.1}% exceeds {limit:.1}%")
+            }
+        }
+    }
+}
+
+/// What was measured while a process was watched.
+#[derive(Debug, Clone, Serialize)]
+pub struct GuardReport {
+    pub pid: u32,
+    pub name: String,
+    pub samples: usize,
+    pub duration_secs: f64,
+    pub cpu_usage: Summary,
+    pub peak_rss_mb: f64,
+    pub budget: Budget,
+    pub violations: Vec<Violation>,
+    /// Whether the process exited while it was watched
+    pub exited: bool,
+}
+
+impl GuardReport {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub enum GuardError {
+    /// The process was never sampled, e.g. because it wasn't running
+    NoSamples {
+        pid: u32,
+    },
+    Exceeded(Box<GuardReport>),
+}
+
+impl fmt::Display for GuardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuardError::NoSamples { pid } => write!(f, "process {pid} was never sampled"),
+            GuardError::Exceeded(report) => {
+                write!(f, "{} ({}) exceeded its budget: ", report.name, report.pid)?;
+                for (i, violation) in report.violations.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{violation}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for GuardError {}
+
+/// Samples a process in the background and checks it against a budget
+/// when finished. A guard dropped without calling [`finish`](Self::finish)
+/// panics if the budget was exceeded, so it can be used as a test assertion.
+pub struct ResourceGuard {
+    pid: Pid,
+    name: String,
+    budget: Budget,
+    interval: Duration,
+    started: Instant,
+    sampler: Option<SamplerHandle>,
+    events: Receiver<SamplerEvent>,
+    cpu: Vec<f64>,
+    memory: Vec<f64>,
+    exited: bool,
+}
+
+impl ResourceGuard {
+    /// Starts watching a process through sysinfo.
+    pub fn watch(pid: u32) -> Self {
+        Self::watch_with(pid, &SourceKind::Sysinfo)
+    }
+
+    /// Starts watching a process read from the given source.
+    pub fn watch_with(pid: u32, source: &SourceKind) -> Self {
+        let pid = Pid::from_u32(pid);
+        let name = source
+            .open()
+            .processes()
+            .into_iter()
+            .find(|(p, _)| *p == pid)
+            .map(|(_, name)| name)
+            .unwrap_or_default();
+
+        let (sender, events) = mpsc::channel();
+        let sampler = SamplerHandle::spawn(source.open(), sender);
+        let guard = Self {
+            pid,
+            name,
+            budget: Budget::default(),
+            interval: DEFAULT_INTERVAL,
+            started: Instant::now(),
+            sampler: Some(sampler),
+            events,
+            cpu: Vec::new(),
+            memory: Vec::new(),
+            exited: false,
+        };
+        guard.reschedule();
+        guard
+    }
+
+    pub fn max_rss(mut self, limit: Bytes) -> Self {
+        self.budget.max_rss_mb = Some(limit.as_mb());
+        self
+    }
+
+    /// Limits the CPU usage averaged over all samples, in percent of one CPU.
+    /// The first sample reads 0% on most platforms, since there is nothing
+    /// to measure it against yet, so it is left out of the CPU figures.
+    pub fn max_avg_cpu(mut self, limit: f64) -> Self {
+        self.budget.max_avg_cpu = Some(limit);
+        self
+    }
+
+    /// Limits the CPU usage of any single sample, in percent of one CPU.
+    pub fn max_cpu(mut self, limit: f64) -> Self {
+        self.budget.max_cpu = Some(limit);
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self.reschedule();
+        self
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid.as_u32()
+    }
+
+    /// Whether the process has exited, after taking in the latest samples.
+    pub fn has_exited(&mut self) -> bool {
+        self.collect();
+        self.exited
+    }
+
+    /// Blocks until the process exits or `timeout` passes, whichever is
+    /// first; a timeout too long to represent waits for the exit. Returns
+    /// whether it exited.
+    pub fn wait(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now().checked_add(timeout);
+        while !self.has_exited() {
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => self.interval,
+            };
+            if remaining.is_zero() {
+                break;
+            }
+            thread::sleep(self.interval.min(remaining));
+        }
+        self.exited
+    }
+
+    /// Stops sampling and checks the budget.
+    pub fn finish(mut self) -> Result<GuardReport, GuardError> {
+        self.stop()
+    }
+
+    fn reschedule(&self) {
+        if let Some(sampler) = &self.sampler {
+            sampler.update(Schedule::new(
+                vec![WatchTarget::new(self.name.clone(), self.pid)],
+                self.interval,
+            ));
+        }
+    }
+
+    fn collect(&mut self) {
+        for event in self.events.try_iter() {
+            let SamplerEvent::Sample(sample) = event else {
+                continue;
+            };
+            match sample.processes.iter().find(|p| p.pid == self.pid) {
+                Some(process) => {
+                    self.cpu.push(process.cpu_usage as f64);
+                    self.memory.push(process.memory_mb);
+                }
+                None if sample.sampled_pids.contains(&self.pid) => self.exited = true,
+                None => {}
+            }
+        }
+    }
+
+    fn stop(&mut self) -> Result<GuardReport, GuardError> {
+        self.collect();
+        // Dropping the handle ends the sampler thread
+        self.sampler = None;
+
+        // See `max_avg_cpu` for why the first CPU sample is skipped
+        let cpu_samples = match self.cpu.as_slice() {
+            [_, rest @ ..] if !rest.is_empty() => rest,
+            all => all,
+        };
+        let (Some(cpu), Some(memory)) = (
+            Summary::from_samples(cpu_samples),
+            Summary::from_samples(&self.memory),
+        ) else {
+            return Err(GuardError::NoSamples {
+                pid: self.pid.as_u32(),
+            });
+        };
+
+        let budget = self.budget;
+        let mut violations = Vec::new();
+        if let Some(limit_mb) = budget.max_rss_mb.filter(|limit| memory.max > *limit) {
+            violations.push(Violation::Rss {
+                peak_mb: memory.max,
+                limit_mb,
+            });
+        }
+        if let Some(limit) = budget.max_avg_cpu.filter(|limit| cpu.mean > *limit) {
+            violations.push(Violation::AvgCpu {
+                average: cpu.mean,
+                limit,
+            });
+        }
+        if let Some(limit) = budget.max_cpu.filter(|limit| cpu.max > *limit) {
+            violations.push(Violation::Cpu {
+                peak: cpu.max,
+                limit,
+            });
+        }
+
+        let report = GuardReport {
+            pid: self.pid.as_u32(),
+            name: self.name.clone(),
+            samples: self.cpu.len(),
+            duration_secs: self.started.elapsed().as_secs_f64(),
+            cpu_usage: cpu,
+            peak_rss_mb: memory.max,
+            budget,
+            violations,
+            exited: self.exited,
+        };
+        if report.passed() {
+            Ok(report)
+        } else {
+            Err(GuardError::Exceeded(Box::new(report)))
+        }
+    }
+}
+
+impl Drop for ResourceGuard {
+    fn drop(&mut self) {
+        if self.sampler.is_none() || thread::panicking() {
+            return;
+        }
+        if let Err(e @ GuardError::Exceeded(_)) = self.stop() {
+            panic!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::FakeSource;
+
+    /// A guard sampling the fixture process with PID 100 every millisecond.
+    fn guard(fixture: &str) -> ResourceGuard {
+        let source = SourceKind::Fake(FakeSource::parse(fixture).unwrap());
+        ResourceGuard::watch_with(100, &source).interval(Duration::from_millis(1))
+    }
+
+    const SERVICE: &str = r#"
+        [[processes]]
+        pid = 100
+        name = "service"
+        cpu = [0.0, 40.0, 60.0]
+        memory_mb = [100.0, 300.0]
+        exit = 3
+    "#;
+
+    #[test]
+    fn reports_what_was_measured() {
+        let mut guard = guard(SERVICE);
+        assert!(guard.wait(Duration::MAX));
+        let report = guard.finish().unwrap();
+        assert_eq!(report.name, "service");
+        assert_eq!(report.samples, 3);
+        assert!(report.exited);
+        assert_eq!(report.peak_rss_mb, 300.0);
+        // The first sample's 0% is left out
+        assert_eq!(report.cpu_usage.mean, 50.0);
+        assert_eq!(report.cpu_usage.min, 40.0);
+    }
+
+    #[test]
+    fn reports_every_exceeded_limit() {
+        let mut guard = guard(SERVICE)
+            .max_rss(200.mib())
+            .max_avg_cpu(45.0)
+            .max_cpu(100.0);
+        assert!(guard.wait(Duration::from_secs(10)));
+        let Err(GuardError::Exceeded(report)) = guard.finish() else {
+            panic!("expected the budget to be exceeded");
+        };
+        assert_eq!(
+            report.violations,
+            [
+                Violation::Rss {
+                    peak_mb: 300.0,
+                    limit_mb: 200.0
+                },
+                Violation::AvgCpu {
+                    average: 50.0,
+                    limit: 45.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn fails_for_processes_that_are_not_running() {
+        let mut guard = guard(SERVICE.replace("exit = 3", "start = 99").as_str());
+        // Gone as soon as it is first sampled
+        assert!(guard.wait(Duration::from_secs(10)));
+        assert!(matches!(
+            guard.finish(),
+            Err(GuardError::NoSamples { pid: 100 })
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeded its budget")]
+    fn panics_when_dropped_over_budget() {
+        let mut guard = guard(SERVICE).max_cpu(50.0);
+        guard.wait(Duration::from_secs(10));
+    }
+
+    #[test]
+    fn byte_sizes_saturate() {
+        assert_eq!(2.kib(), Bytes(2048));
+        assert_eq!(500.mib().as_mb(), 500.0);
+        assert_eq!(1.gib(), Bytes(1 << 30));
+        assert_eq!(u64::MAX.kib(), Bytes(u64::MAX));
+        assert_eq!((u64::MAX / 2).gib(), Bytes(u64::MAX));
+    }
+}
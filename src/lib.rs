@@ -0,0 +1,47 @@
+//! CatZ's process sampling, usable outside the TUI.
+//!
+//! - [`domain::process`] resolves watch targets to running processes
+//! - [`source`] reads process metrics from sysinfo, procfs or a fixture
+//! - [`sampler`] samples targets on a background thread
+//! - [`domain::history`] keeps sampled frames for charts and exports
+//! - [`guard`] checks a process against resource budgets in tests
+//! - [`recording`] loads and saves recordings as CSV, JSON, NDJSON or SQLite
+//!
+//! [`cli`] is the `catz` binary itself; the TUI, agent and reports behind it
+//! aren't part of the API.
+//!
+//! ```no_run
+//! use catz::{
+//!     domain::process::{ProcessManager, WatchTarget},
+//!     source::{MetricsSource, SysinfoSource},
+//! };
+//!
+//! let mut source = SysinfoSource::new();
+//! let running = source.processes();
+//! let (pid, name) = ProcessManager::find_matching_processes("postgres", &running)
+//!     .into_iter()
+//!     .next()
+//!     .expect("postgres is running");
+//! let target = WatchTarget::new(name, pid);
+//! for sample in source.sample(&[pid]) {
+//!     assert!(sample.memory_mb < 500.0, "{} uses too much memory", target.name);
+//! }
+//! ```
+
+mod agent;
+mod args;
+pub mod cli;
+mod commands;
+mod config;
+pub mod domain;
+mod event;
+pub mod guard;
+pub mod recording;
+mod report;
+pub mod sampler;
+mod session;
+pub mod source;
+mod terminal;
+mod ui;
+mod utils;
+mod web;
@@ -0,0 +1,990 @@
+pub mod sqlite;
+
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail, eyre},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+use sysinfo::Pid;
+
+use crate::domain::{
+    history::{Frame, History, Marker},
+    process::{MAX_HOSTS, ProcessInfo, host_pid},
+};
+
+use self::sqlite::Database;
+pub use crate::config::Thresholds;
+
+/// Header written by the CSV exporter.
+pub const CSV_HEADER: &str =
+    "Process Name,PID,CPU %,Memory (MB),Threads,Read (KB/s),Write (KB/s),Elapsed (ms)";
+/// Header of recordings made before threads and disk I/O were sampled.
+const BASIC_CSV_HEADER: &str = "Process Name,PID,CPU %,Memory (MB),Elapsed (ms)";
+/// Columns put in front of the header, in this order, when samples come
+/// from several hosts or carry a group. Neither can contain commas, so
+/// they are split off the left of a row.
+const HOST_CSV_COLUMN: &str = "Host,";
+const GROUP_CSV_COLUMN: &str = "Group,";
+/// Column appended to either header when the recording has markers.
+/// Marker rows leave the sample columns empty.
+const MARKER_CSV_COLUMN: &str = ",Marker";
+/// Comment lines before the header: when the recording started, in RFC
+/// 3339, and the thresholds in effect while recording, as JSON.
+const STARTED_CSV_COMMENT: &str = "# started: ";
+const THRESHOLDS_CSV_COMMENT: &str = "# thresholds: ";
+/// Header of recordings made before samples carried a timestamp.
+const LEGACY_CSV_HEADER: &str = "Process Name,PID,CPU %,Memory (MB)";
+/// Sample spacing assumed for legacy recordings, which don't store one.
+const LEGACY_SAMPLE_SPACING_MS: u64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Csv,
+    Json,
+    Ndjson,
+    /// A SQLite database of capture sessions, see [`sqlite`]
+    Sqlite,
+}
+
+impl RecordingFormat {
+    pub const EXTENSIONS: [&'static str; 4] = ["csv", "json", "ndjson", "db"];
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_extension(path.extension()?.to_str()?)
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            "db" | "sqlite" | "sqlite3" => Some(Self::Sqlite),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+            Self::Sqlite => "db",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedSample {
+    pub name: String,
+    pub pid: u32,
+    pub cpu_usage: f32,
+    pub memory_mb: f64,
+    /// This and the disk I/O are 0 in recordings made before they were
+    /// sampled
+    #[serde(default)]
+    pub threads: u32,
+    /// Disk reads in KB per second
+    #[serde(default)]
+    pub read_kb_s: f64,
+    /// Disk writes in KB per second
+    #[serde(default)]
+    pub write_kb_s: f64,
+    /// Milliseconds since the start of the recording
+    pub elapsed_ms: u64,
+    /// Host the process ran on, when it wasn't local
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Group the process was labelled with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+/// A marker such as "deployed v2" dropped during the recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMarker {
+    /// Milliseconds since the start of the recording
+    pub elapsed_ms: u64,
+    #[serde(rename = "marker")]
+    pub text: String,
+}
+
+/// What a recording stores besides its samples and markers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Metadata {
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    started: Option<SystemTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thresholds: Option<Thresholds>,
+}
+
+/// One NDJSON line; markers and the metadata are mixed in with the
+/// samples.
+enum NdjsonLine {
+    Sample(RecordedSample),
+    Marker(RecordedMarker),
+    Metadata(Metadata),
+}
+
+impl NdjsonLine {
+    /// Tells lines apart by a field only their kind has, so that errors
+    /// say what is wrong with the line rather than that no kind matched.
+    fn parse(line: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(line)?;
+        let Some(object) = value.as_object() else {
+            bail!("expected an object, got {line:?}");
+        };
+        Ok(if object.contains_key("name") {
+            NdjsonLine::Sample(serde_json::from_value(value)?)
+        } else if object.contains_key("marker") {
+            NdjsonLine::Marker(serde_json::from_value(value)?)
+        } else {
+            NdjsonLine::Metadata(serde_json::from_value(value)?)
+        })
+    }
+}
+
+/// JSON recordings are an array of samples, or this object when they have
+/// markers or metadata.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonRecording {
+    samples: Vec<RecordedSample>,
+    #[serde(default)]
+    markers: Vec<RecordedMarker>,
+    #[serde(default, with = "humantime_serde")]
+    started: Option<SystemTime>,
+    #[serde(default)]
+    thresholds: Option<Thresholds>,
+}
+
+/// Borrowed [`JsonRecording`] for saving.
+#[derive(Serialize)]
+struct JsonRecordingRef<'a> {
+    samples: &'a [RecordedSample],
+    markers: &'a [RecordedMarker],
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    started: Option<SystemTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thresholds: Option<&'a Thresholds>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub samples: Vec<RecordedSample>,
+    pub markers: Vec<RecordedMarker>,
+    /// Wall-clock time that `elapsed_ms` counts from; `None` in recordings
+    /// that don't store it
+    pub started: Option<SystemTime>,
+    /// Thresholds in effect while recording, which its alerts are based
+    /// on; `None` in recordings that don't store them
+    pub thresholds: Option<Thresholds>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let format = RecordingFormat::from_path(path).ok_or_else(|| {
+            eyre!(
+                "unsupported recording {}: expected one of .{}",
+                path.display(),
+                RecordingFormat::EXTENSIONS.join(", .")
+            )
+        })?;
+        if format == RecordingFormat::Sqlite {
+            // The latest session; `catz query` lists the others
+            return Database::open_read_only(path)?.load(None);
+        }
+        let content = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read recording {}", path.display()))?;
+        let recording = match format {
+            RecordingFormat::Csv => Self::parse_csv(&content),
+            RecordingFormat::Json => Self::parse_json(&content),
+            RecordingFormat::Ndjson => Self::parse_ndjson(&content),
+            RecordingFormat::Sqlite => unreachable!("loaded above"),
+        };
+        recording.wrap_err_with(|| format!("invalid recording {}", path.display()))
+    }
+
+    pub fn parse_csv(content: &str) -> Result<Self> {
+        let mut lines = content.lines().enumerate();
+        let mut metadata = Metadata::default();
+        let header = loop {
+            let Some((index, line)) = lines.next() else {
+                bail!("recording is empty");
+            };
+            let Some(comment) = line.strip_prefix('#') else {
+                break line;
+            };
+            if let Some(time) = line.strip_prefix(STARTED_CSV_COMMENT) {
+                metadata.started = Some(
+                    humantime::parse_rfc3339_weak(time.trim())
+                        .wrap_err_with(|| format!("line {}: invalid start time", index + 1))?,
+                );
+            } else if let Some(json) = line.strip_prefix(THRESHOLDS_CSV_COMMENT) {
+                metadata.thresholds = Some(
+                    serde_json::from_str(json)
+                        .wrap_err_with(|| format!("line {}: invalid thresholds", index + 1))?,
+                );
+            } else if !comment.trim().is_empty() {
+                bail!("line {}: unexpected comment {line:?}", index + 1);
+            }
+        };
+        let (header, with_markers) = match header.trim().strip_suffix(MARKER_CSV_COLUMN) {
+            Some(header) => (header, true),
+            None => (header.trim(), false),
+        };
+        let (header, with_host) = match header.strip_prefix(HOST_CSV_COLUMN) {
+            Some(header) => (header, true),
+            None => (header, false),
+        };
+        let (header, with_group) = match header.strip_prefix(GROUP_CSV_COLUMN) {
+            Some(header) => (header, true),
+            None => (header, false),
+        };
+        let columns = CsvColumns {
+            host: with_host,
+            group: with_group,
+        };
+        // Only basic recordings lack the thread and disk I/O columns
+        let (legacy, basic) = match header {
+            CSV_HEADER => (false, false),
+            BASIC_CSV_HEADER => (false, true),
+            LEGACY_CSV_HEADER if !with_markers && !with_host && !with_group => (true, true),
+            _ => bail!("unexpected CSV header: {header:?}"),
+        };
+
+        // Legacy rows are grouped by process, so the n-th row of a process is its n-th tick
+        let mut ticks: HashMap<String, u64> = HashMap::new();
+        let mut samples = Vec::new();
+        let mut markers = Vec::new();
+        for (index, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line = if with_markers {
+                match parse_marker_row(line, columns, basic) {
+                    Some(marker) => {
+                        markers.push(marker.wrap_err_with(|| format!("line {}", index + 1))?);
+                        continue;
+                    }
+                    // Sample rows end with an empty marker column
+                    None => line.strip_suffix(',').unwrap_or(line),
+                }
+            } else {
+                line
+            };
+            let row = if legacy {
+                parse_legacy_csv_row(line).map(|(name, pid, cpu_usage, memory_mb)| {
+                    let tick = ticks.entry(name.clone()).or_default();
+                    let elapsed_ms = *tick * LEGACY_SAMPLE_SPACING_MS;
+                    *tick += 1;
+                    RecordedSample {
+                        name,
+                        pid,
+                        cpu_usage,
+                        memory_mb,
+                        threads: 0,
+                        read_kb_s: 0.0,
+                        write_kb_s: 0.0,
+                        elapsed_ms,
+                        host: None,
+                        group: None,
+                    }
+                })
+            } else {
+                columns.split(line).and_then(|(host, group, rest)| {
+                    parse_csv_row(rest, basic).map(|sample| RecordedSample {
+                        host,
+                        group,
+                        ..sample
+                    })
+                })
+            };
+            samples.push(row.wrap_err_with(|| format!("line {}", index + 1))?);
+        }
+        Ok(Self {
+            samples,
+            markers,
+            started: metadata.started,
+            thresholds: metadata.thresholds,
+        })
+    }
+
+    fn parse_json(content: &str) -> Result<Self> {
+        // Told apart by their type rather than with an untagged enum,
+        // whose errors don't say what is wrong
+        if content.trim_start().starts_with('[') {
+            return Ok(Self {
+                samples: serde_json::from_str(content)?,
+                ..Self::default()
+            });
+        }
+        let recording: JsonRecording = serde_json::from_str(content)?;
+        Ok(Self {
+            samples: recording.samples,
+            markers: recording.markers,
+            started: recording.started,
+            thresholds: recording.thresholds,
+        })
+    }
+
+    fn parse_ndjson(content: &str) -> Result<Self> {
+        let mut recording = Self::default();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match NdjsonLine::parse(line).map_err(|e| eyre!("line {}: {e}", index + 1))? {
+                NdjsonLine::Sample(sample) => recording.samples.push(sample),
+                NdjsonLine::Marker(marker) => recording.markers.push(marker),
+                NdjsonLine::Metadata(metadata) => {
+                    recording.started = metadata.started.or(recording.started);
+                    recording.thresholds = metadata.thresholds.or(recording.thresholds);
+                }
+            }
+        }
+        Ok(recording)
+    }
+
+    /// Flattens a history into samples and markers timed relative to its
+    /// first frame, keeping the thresholds it was recorded with. `origin` is
+    /// the wall-clock time the history's frame times count from.
+    pub fn from_history(
+        history: &History,
+        origin: Option<SystemTime>,
+        thresholds: &Thresholds,
+    ) -> Self {
+        let start = history
+            .time_range()
+            .map(|(start, _)| start)
+            .unwrap_or_default();
+        let samples = history
+            .frames()
+            .flat_map(|frame| {
+                let elapsed_ms = frame.elapsed.saturating_sub(start).as_millis() as u64;
+                frame.processes.iter().map(move |p| RecordedSample {
+                    name: p.name.clone(),
+                    pid: p.local_pid(),
+                    cpu_usage: p.cpu_usage,
+                    memory_mb: p.memory_mb,
+                    threads: p.threads,
+                    read_kb_s: p.read_kb_s,
+                    write_kb_s: p.write_kb_s,
+                    elapsed_ms,
+                    host: p.host.clone(),
+                    group: p.group.clone(),
+                })
+            })
+            .collect();
+        let markers = history
+            .markers()
+            .iter()
+            .map(|marker| RecordedMarker {
+                elapsed_ms: marker.elapsed.saturating_sub(start).as_millis() as u64,
+                text: marker.text.clone(),
+            })
+            .collect();
+        Self {
+            samples,
+            markers,
+            started: origin.and_then(|origin| origin.checked_add(start)),
+            thresholds: Some(thresholds.clone()),
+        }
+    }
+
+    /// Groups samples sharing a timestamp into frames. PIDs of samples from
+    /// several hosts are made unique with [`host_pid`], numbering hosts in
+    /// order of appearance. Fails when they don't fit, i.e. for more than
+    /// [`MAX_HOSTS`] hosts or out of range PIDs.
+    pub fn into_history(self) -> Result<History> {
+        let mut hosts: Vec<String> = Vec::new();
+        let mut frames: BTreeMap<u64, Vec<ProcessInfo>> = BTreeMap::new();
+        for sample in self.samples {
+            let pid = match &sample.host {
+                Some(host) => {
+                    let index = match hosts.iter().position(|h| h == host) {
+                        Some(index) => index,
+                        None if hosts.len() == MAX_HOSTS => {
+                            bail!("samples come from more than {MAX_HOSTS} hosts")
+                        }
+                        None => {
+                            hosts.push(host.clone());
+                            hosts.len() - 1
+                        }
+                    };
+                    host_pid(index, sample.pid)
+                        .ok_or_else(|| eyre!("PID {} of host {host} is out of range", sample.pid))?
+                }
+                None => Pid::from_u32(sample.pid),
+            };
+            frames
+                .entry(sample.elapsed_ms)
+                .or_default()
+                .push(ProcessInfo {
+                    threads: sample.threads,
+                    read_kb_s: sample.read_kb_s,
+                    write_kb_s: sample.write_kb_s,
+                    host: sample.host,
+                    group: sample.group,
+                    ..ProcessInfo::new(sample.name, pid, sample.cpu_usage, sample.memory_mb)
+                });
+        }
+
+        let mut history = History::new();
+        for (elapsed_ms, processes) in frames {
+            history.push(Frame {
+                elapsed: Duration::from_millis(elapsed_ms),
+                processes,
+            });
+        }
+        for marker in self.markers {
+            history.add_marker(Marker {
+                elapsed: Duration::from_millis(marker.elapsed_ms),
+                text: marker.text,
+            });
+        }
+        Ok(history)
+    }
+
+    /// Writes the recording to a file, or adds it to a database as a new
+    /// session.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let format = RecordingFormat::from_path(path).ok_or_else(|| {
+            eyre!(
+                "file name must end with .{}",
+                RecordingFormat::EXTENSIONS.join(", .")
+            )
+        })?;
+        if format == RecordingFormat::Sqlite {
+            Database::open(path)?.insert_recording(self, "catz recording")?;
+            return Ok(());
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("failed to create {}", parent.display()))?;
+        }
+        let mut file = BufWriter::new(
+            File::create(path).wrap_err_with(|| format!("failed to create {}", path.display()))?,
+        );
+        match format {
+            RecordingFormat::Csv => {
+                let columns = CsvColumns {
+                    host: self.samples.iter().any(|s| s.host.is_some()),
+                    group: self.samples.iter().any(|s| s.group.is_some()),
+                };
+                let with_markers = !self.markers.is_empty();
+                let marker_column = if with_markers { MARKER_CSV_COLUMN } else { "" };
+                if let Some(started) = self.started {
+                    writeln!(
+                        file,
+                        "{STARTED_CSV_COMMENT}{}",
+                        humantime::format_rfc3339_millis(started)
+                    )?;
+                }
+                // Even empty ones, so that the recording keeps saying none were in effect
+                if let Some(thresholds) = &self.thresholds {
+                    writeln!(
+                        file,
+                        "{THRESHOLDS_CSV_COMMENT}{}",
+                        serde_json::to_string(thresholds)?
+                    )?;
+                }
+                writeln!(file, "{}{CSV_HEADER}{marker_column}", columns.header())?;
+                for s in &self.samples {
+                    if columns.host {
+                        write!(file, "{},", s.host.as_deref().unwrap_or_default())?;
+                    }
+                    if columns.group {
+                        // Groups are free text, so commas are swapped out
+                        let group = s.group.as_deref().unwrap_or_default();
+                        write!(file, "{},", group.replace(',', ";"))?;
+                    }
+                    write!(
+                        file,
+                        "{},{},{:.2},{:.2},{},{:.2},{:.2},{}",
+                        s.name,
+                        s.pid,
+                        s.cpu_usage,
+                        s.memory_mb,
+                        s.threads,
+                        s.read_kb_s,
+                        s.write_kb_s,
+                        s.elapsed_ms
+                    )?;
+                    writeln!(file, "{}", if with_markers { "," } else { "" })?;
+                }
+                for marker in &self.markers {
+                    write!(file, "{}", ",".repeat(columns.count()))?;
+                    // Markers are single-line and the last column, so commas are fine
+                    writeln!(
+                        file,
+                        ",,,,,,,{},{}",
+                        marker.elapsed_ms,
+                        marker.text.replace(['\n', '\r'], " ")
+                    )?;
+                }
+            }
+            RecordingFormat::Json
+                if self.markers.is_empty()
+                    && self.started.is_none()
+                    && self.thresholds.is_none() =>
+            {
+                serde_json::to_writer_pretty(&mut file, &self.samples)?
+            }
+            RecordingFormat::Json => serde_json::to_writer_pretty(
+                &mut file,
+                &JsonRecordingRef {
+                    samples: &self.samples,
+                    markers: &self.markers,
+                    started: self.started,
+                    thresholds: self.thresholds.as_ref(),
+                },
+            )?,
+            RecordingFormat::Ndjson => {
+                if self.started.is_some() || self.thresholds.is_some() {
+                    let metadata = Metadata {
+                        started: self.started,
+                        thresholds: self.thresholds.clone(),
+                    };
+                    serde_json::to_writer(&mut file, &metadata)?;
+                    writeln!(file)?;
+                }
+                for s in &self.samples {
+                    serde_json::to_writer(&mut file, s)?;
+                    writeln!(file)?;
+                }
+                for marker in &self.markers {
+                    serde_json::to_writer(&mut file, marker)?;
+                    writeln!(file)?;
+                }
+            }
+            RecordingFormat::Sqlite => unreachable!("saved above"),
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Process names in order of first appearance.
+    pub fn process_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for sample in &self.samples {
+            if !names.contains(&sample.name.as_str()) {
+                names.push(&sample.name);
+            }
+        }
+        names
+    }
+
+    pub fn samples_for<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a RecordedSample> {
+        self.samples.iter().filter(move |s| s.name == name)
+    }
+}
+
+/// Host and group columns in front of the sample columns of a CSV row.
+#[derive(Debug, Clone, Copy)]
+struct CsvColumns {
+    host: bool,
+    group: bool,
+}
+
+impl CsvColumns {
+    fn count(&self) -> usize {
+        usize::from(self.host) + usize::from(self.group)
+    }
+
+    fn header(&self) -> String {
+        let host = if self.host { HOST_CSV_COLUMN } else { "" };
+        let group = if self.group { GROUP_CSV_COLUMN } else { "" };
+        format!("{host}{group}")
+    }
+
+    /// Splits the host and group off the left of a row, empty ones being
+    /// `None`, and returns the rest of it.
+    fn split<'a>(&self, line: &'a str) -> Result<(Option<String>, Option<String>, &'a str)> {
+        let mut rest = line;
+        let mut label = |present: bool, name: &str| -> Result<Option<String>> {
+            if !present {
+                return Ok(None);
+            }
+            let (value, tail) = rest
+                .split_once(',')
+                .ok_or_else(|| eyre!("expected a {name}, got {line:?}"))?;
+            rest = tail;
+            Ok(Some(value.to_string()).filter(|v| !v.is_empty()))
+        };
+        let host = label(self.host, "host")?;
+        let group = label(self.group, "group")?;
+        Ok((host, group, rest))
+    }
+}
+
+/// Parses `[host,][group,],,,,[,,,]elapsed,text` rows; `None` for sample
+/// rows.
+fn parse_marker_row(
+    line: &str,
+    columns: CsvColumns,
+    basic: bool,
+) -> Option<Result<RecordedMarker>> {
+    let line = columns.split(line).ok()?.2;
+    // Process names are never empty, so only marker rows start with a comma
+    let empty_columns = if basic { ",,,," } else { ",,,,,,," };
+    let rest = line.strip_prefix(empty_columns)?;
+    Some(
+        rest.split_once(',')
+            .ok_or_else(|| eyre!("expected a marker, got {line:?}"))
+            .and_then(|(elapsed, text)| {
+                Ok(RecordedMarker {
+                    elapsed_ms: elapsed
+                        .trim()
+                        .parse()
+                        .map_err(|_| eyre!("invalid elapsed time {elapsed:?}"))?,
+                    text: text.to_string(),
+                })
+            }),
+    )
+}
+
+fn parse_csv_row(line: &str, basic: bool) -> Result<RecordedSample> {
+    let fields = if basic { 5 } else { 8 };
+    let (rest, elapsed) = line
+        .rsplit_once(',')
+        .ok_or_else(|| eyre!("expected {fields} fields, got {line:?}"))?;
+    let (rest, threads, read_kb_s, write_kb_s) = if basic {
+        (rest, 0, 0.0, 0.0)
+    } else {
+        let mut extra = rest.rsplitn(4, ',');
+        let (Some(write), Some(read), Some(threads), Some(rest)) =
+            (extra.next(), extra.next(), extra.next(), extra.next())
+        else {
+            bail!("expected {fields} fields, got {line:?}");
+        };
+        let rate = |value: &str| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| eyre!("invalid I/O value {value:?}"))
+        };
+        let threads = threads
+            .trim()
+            .parse()
+            .map_err(|_| eyre!("invalid thread count {threads:?}"))?;
+        (rest, threads, rate(read)?, rate(write)?)
+    };
+    let (name, pid, cpu_usage, memory_mb) = parse_legacy_csv_row(rest)?;
+    Ok(RecordedSample {
+        name,
+        pid,
+        cpu_usage,
+        memory_mb,
+        threads,
+        read_kb_s,
+        write_kb_s,
+        elapsed_ms: elapsed
+            .trim()
+            .parse()
+            .map_err(|_| eyre!("invalid elapsed time {elapsed:?}"))?,
+        host: None,
+        group: None,
+    })
+}
+
+fn parse_legacy_csv_row(line: &str) -> Result<(String, u32, f32, f64)> {
+    // Process names are written unquoted, so split numeric fields off the right
+    let mut fields = line.rsplitn(4, ',');
+    let memory = fields.next();
+    let cpu = fields.next();
+    let pid = fields.next();
+    let name = fields.next();
+    let (Some(name), Some(pid), Some(cpu), Some(memory)) = (name, pid, cpu, memory) else {
+        bail!("expected 4 fields, got {line:?}");
+    };
+
+    Ok((
+        name.to_string(),
+        pid.trim()
+            .parse()
+            .map_err(|_| eyre!("invalid PID {pid:?}"))?,
+        cpu.trim()
+            .parse()
+            .map_err(|_| eyre!("invalid CPU value {cpu:?}"))?,
+        memory
+            .trim()
+            .parse()
+            .map_err(|_| eyre!("invalid memory value {memory:?}"))?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn sample(name: &str, elapsed_ms: u64, host: Option<&str>) -> RecordedSample {
+        RecordedSample {
+            name: name.to_string(),
+            pid: 42,
+            cpu_usage: 12.5,
+            memory_mb: 100.25,
+            threads: 7,
+            read_kb_s: 4.5,
+            write_kb_s: 0.75,
+            elapsed_ms,
+            host: host.map(String::from),
+            group: None,
+        }
+    }
+
+    fn recording(host: Option<&str>) -> Recording {
+        Recording {
+            samples: vec![sample("nginx", 0, host), sample("nginx", 500, host)],
+            markers: vec![RecordedMarker {
+                elapsed_ms: 250,
+                text: "deployed v2, finally".to_string(),
+            }],
+            started: Some(SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123)),
+            thresholds: Some(Thresholds {
+                cpu: Some(80.0),
+                memory_mb: None,
+            }),
+        }
+    }
+
+    fn save_and_load(recording: &Recording, extension: &str) -> Recording {
+        let path = env::temp_dir().join(format!(
+            "catz-recording-{}-{extension}.{extension}",
+            process::id()
+        ));
+        recording.save(&path).unwrap();
+        let loaded = Recording::load(&path);
+        let _ = fs::remove_file(&path);
+        if extension == "db" {
+            for suffix in ["-wal", "-shm"] {
+                let mut side = path.clone().into_os_string();
+                side.push(suffix);
+                let _ = fs::remove_file(side);
+            }
+        }
+        loaded.unwrap()
+    }
+
+    fn assert_same(loaded: &Recording, recording: &Recording) {
+        assert_eq!(loaded.samples.len(), recording.samples.len());
+        for (loaded, sample) in loaded.samples.iter().zip(&recording.samples) {
+            assert_eq!(loaded.name, sample.name);
+            assert_eq!(loaded.pid, sample.pid);
+            assert_eq!(loaded.cpu_usage, sample.cpu_usage);
+            assert_eq!(loaded.memory_mb, sample.memory_mb);
+            assert_eq!(loaded.threads, sample.threads);
+            assert_eq!(loaded.read_kb_s, sample.read_kb_s);
+            assert_eq!(loaded.write_kb_s, sample.write_kb_s);
+            assert_eq!(loaded.elapsed_ms, sample.elapsed_ms);
+            assert_eq!(loaded.host, sample.host);
+            assert_eq!(loaded.group, sample.group);
+        }
+        assert_eq!(loaded.markers.len(), recording.markers.len());
+        for (loaded, marker) in loaded.markers.iter().zip(&recording.markers) {
+            assert_eq!(loaded.elapsed_ms, marker.elapsed_ms);
+            assert_eq!(loaded.text, marker.text);
+        }
+        assert_eq!(loaded.started, recording.started);
+        assert_eq!(loaded.thresholds, recording.thresholds);
+    }
+
+    #[test]
+    fn recordings_round_trip() {
+        for host in [None, Some("web1")] {
+            let recording = recording(host);
+            for extension in RecordingFormat::EXTENSIONS {
+                assert_same(&save_and_load(&recording, extension), &recording);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_thresholds_round_trip() {
+        let mut recording = recording(None);
+        recording.thresholds = Some(Thresholds::default());
+        for extension in RecordingFormat::EXTENSIONS {
+            assert_same(&save_and_load(&recording, extension), &recording);
+        }
+        recording.thresholds = None;
+        for extension in RecordingFormat::EXTENSIONS {
+            assert_same(&save_and_load(&recording, extension), &recording);
+        }
+    }
+
+    #[test]
+    fn groups_round_trip() {
+        for host in [None, Some("web1")] {
+            let mut recording = recording(host);
+            for sample in &mut recording.samples {
+                sample.group = Some("web".to_string());
+            }
+            recording.samples.push(sample("redis", 500, host));
+            for extension in RecordingFormat::EXTENSIONS {
+                assert_same(&save_and_load(&recording, extension), &recording);
+            }
+        }
+    }
+
+    #[test]
+    fn numbers_hosts_in_order_of_appearance() {
+        let mut recording = recording(Some("web1"));
+        recording.samples.push(sample("nginx", 500, Some("web2")));
+        let history = recording.into_history().unwrap();
+        let pids: Vec<Pid> = history
+            .frames()
+            .flat_map(|frame| frame.processes.iter().map(|p| p.pid))
+            .collect();
+        let web1 = host_pid(0, 42).unwrap();
+        assert_eq!(pids, [web1, web1, host_pid(1, 42).unwrap()]);
+    }
+
+    #[test]
+    fn refuses_hosts_and_pids_that_do_not_fit() {
+        let mut many_hosts = recording(None);
+        many_hosts.samples = (0..=MAX_HOSTS)
+            .map(|i| sample("nginx", 0, Some(&format!("host{i}"))))
+            .collect();
+        let error = many_hosts.into_history().unwrap_err();
+        assert!(
+            error.to_string().contains("more than 1024 hosts"),
+            "{error}"
+        );
+
+        let mut large_pid = recording(Some("web1"));
+        large_pid.samples[1].pid = u32::MAX;
+        let error = large_pid.into_history().unwrap_err();
+        assert!(error.to_string().contains("out of range"), "{error}");
+
+        // Local PIDs are used as they are
+        let mut local = recording(None);
+        local.samples[1].pid = u32::MAX;
+        assert!(local.into_history().is_ok());
+    }
+
+    #[test]
+    fn parses_csv_without_threads_and_io() {
+        let recording = Recording::parse_csv(
+            "Process Name,PID,CPU %,Memory (MB),Elapsed (ms),Marker\n\
+             nginx,42,1.50,10.00,0,\n\
+             ,,,,500,go\n",
+        )
+        .unwrap();
+        assert_eq!(recording.samples.len(), 1);
+        assert_eq!(recording.samples[0].cpu_usage, 1.5);
+        assert_eq!(recording.samples[0].threads, 0);
+        assert_eq!(recording.samples[0].read_kb_s, 0.0);
+        assert_eq!(recording.markers[0].text, "go");
+        assert_eq!(recording.thresholds, None);
+    }
+
+    #[test]
+    fn parses_legacy_csv() {
+        let recording = Recording::parse_csv(
+            "Process Name,PID,CPU %,Memory (MB)\n\
+             web, server,42,1.50,10.00\n\
+             web, server,42,2.50,11.00\n",
+        )
+        .unwrap();
+        let elapsed: Vec<u64> = recording.samples.iter().map(|s| s.elapsed_ms).collect();
+        assert_eq!(elapsed, [0, LEGACY_SAMPLE_SPACING_MS]);
+        assert_eq!(recording.samples[0].name, "web, server");
+    }
+
+    #[test]
+    fn rejects_invalid_csv() {
+        for content in [
+            "",
+            "Name,PID\n",
+            "# notes\nProcess Name,PID,CPU %,Memory (MB)\n",
+            "# thresholds: {\"gpu\":1}\nProcess Name,PID,CPU %,Memory (MB)\n",
+            "Process Name,PID,CPU %,Memory (MB),Elapsed (ms)\nnginx,x,1,1,0\n",
+            "Process Name,PID,CPU %,Memory (MB),Elapsed (ms)\nnginx,1,1,1\n",
+        ] {
+            assert!(Recording::parse_csv(content).is_err(), "{content:?}");
+        }
+    }
+
+    #[test]
+    fn parses_csv_markers_with_hosts() {
+        let recording = Recording::parse_csv(&format!(
+            "# started: 2023-11-14T22:13:20.000Z\n\
+             {HOST_CSV_COLUMN}{CSV_HEADER}{MARKER_CSV_COLUMN}\n\
+             web1,nginx,42,1.50,10.00,4,0.00,8.00,0,\n\
+             ,,,,,,,,250,deployed, v2\n"
+        ))
+        .unwrap();
+        assert_eq!(recording.samples[0].host.as_deref(), Some("web1"));
+        assert_eq!(recording.samples[0].threads, 4);
+        assert_eq!(recording.samples[0].write_kb_s, 8.0);
+        assert_eq!(recording.markers[0].elapsed_ms, 250);
+        assert_eq!(recording.markers[0].text, "deployed, v2");
+        assert_eq!(
+            recording.started,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+    }
+
+    #[test]
+    fn parses_ndjson_lines_by_their_fields() {
+        let recording = Recording::parse_ndjson(
+            r#"{"name":"nginx","pid":42,"cpu_usage":1.5,"memory_mb":10.0,"elapsed_ms":0}
+               {"elapsed_ms":250,"marker":"go"}
+
+               {"thresholds":{"cpu":50.0}}"#,
+        )
+        .unwrap();
+        assert_eq!(recording.samples[0].name, "nginx");
+        assert_eq!(recording.markers[0].text, "go");
+        assert_eq!(recording.thresholds.and_then(|t| t.cpu), Some(50.0));
+    }
+
+    #[test]
+    fn names_what_is_wrong_with_ndjson_lines() {
+        for (content, error) in [
+            (
+                r#"{"name":"nginx","cpu_usage":1.5,"memory_mb":10.0,"elapsed_ms":0}"#,
+                "line 1: missing field `pid`",
+            ),
+            (
+                r#"{"elapsed_ms":"soon","marker":"go"}"#,
+                "line 1: invalid type",
+            ),
+            (r#"{"thresholds":{"gpu":1}}"#, "line 1: unknown field `gpu`"),
+            ("{}\n[1]", "line 2: expected an object"),
+            ("{", "line 1: EOF"),
+        ] {
+            let message = Recording::parse_ndjson(content).unwrap_err().to_string();
+            assert!(message.starts_with(error), "{message:?} for {content:?}");
+        }
+    }
+
+    #[test]
+    fn parses_json_arrays_and_objects() {
+        let sample = r#"{"name":"nginx","pid":42,"cpu_usage":1.5,"memory_mb":10.0,"elapsed_ms":0}"#;
+        let recording = Recording::parse_json(&format!("[{sample}]")).unwrap();
+        assert_eq!(recording.samples.len(), 1);
+        let recording =
+            Recording::parse_json(&format!(r#"{{"samples":[{sample}],"markers":[]}}"#)).unwrap();
+        assert_eq!(recording.samples.len(), 1);
+
+        let error = Recording::parse_json(r#"{"samples":[],"notes":1}"#).unwrap_err();
+        assert!(
+            error.to_string().contains("unknown field `notes`"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,626 @@
+//! SQLite storage for long captures. A database holds any number of
+//! sessions: each `--db` run and each recording saved to it adds one, so
+//! captures can be queried across days with `catz query` and still be
+//! replayed like any other recording.
+
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail, eyre},
+};
+use rusqlite::{Connection, OpenFlags, Transaction, params, types::ValueRef};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    config::Thresholds,
+    domain::history::{Frame, Marker},
+};
+
+use super::{RecordedMarker, RecordedSample, Recording};
+
+/// Local processes are stored with an empty host, since `NULL`s never
+/// compare equal in the `processes` uniqueness constraint.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    -- Unix time in milliseconds that elapsed_ms counts from
+    started_ms INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    -- Thresholds in effect while recording, as JSON
+    thresholds TEXT
+);
+CREATE TABLE IF NOT EXISTS processes (
+    id INTEGER PRIMARY KEY,
+    session_id INTEGER NOT NULL REFERENCES sessions (id),
+    host TEXT NOT NULL DEFAULT '',
+    name TEXT NOT NULL,
+    pid INTEGER NOT NULL,
+    group_name TEXT,
+    UNIQUE (session_id, host, name, pid)
+);
+CREATE TABLE IF NOT EXISTS samples (
+    process_id INTEGER NOT NULL REFERENCES processes (id),
+    elapsed_ms INTEGER NOT NULL,
+    cpu_usage REAL NOT NULL,
+    memory_mb REAL NOT NULL,
+    threads INTEGER NOT NULL DEFAULT 0,
+    read_kb_s REAL NOT NULL DEFAULT 0,
+    write_kb_s REAL NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS samples_by_process ON samples (process_id, elapsed_ms);
+CREATE TABLE IF NOT EXISTS markers (
+    session_id INTEGER NOT NULL REFERENCES sessions (id),
+    elapsed_ms INTEGER NOT NULL,
+    text TEXT NOT NULL
+);
+";
+
+/// Columns added to the schema later, with their definitions. Databases
+/// opened for writing get them added; read-only ones are read without.
+const ADDED_COLUMNS: [(&str, &str, &str); 5] = [
+    ("sessions", "thresholds", "TEXT"),
+    ("processes", "group_name", "TEXT"),
+    ("samples", "threads", "INTEGER NOT NULL DEFAULT 0"),
+    ("samples", "read_kb_s", "REAL NOT NULL DEFAULT 0"),
+    ("samples", "write_kb_s", "REAL NOT NULL DEFAULT 0"),
+];
+
+/// Column names and rows of a query, with SQLite values mapped to JSON.
+#[derive(Debug, Clone, Default)]
+pub struct QueryRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Process row IDs of a session by host, name and PID.
+type ProcessIds = HashMap<(String, String, u32), i64>;
+
+pub struct Database {
+    connection: Connection,
+    path: PathBuf,
+}
+
+impl Database {
+    /// Opens a database for writing, creating it and its tables if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("failed to create {}", parent.display()))?;
+        }
+        let connection = Connection::open(path)
+            .wrap_err_with(|| format!("failed to open database {}", path.display()))?;
+        // WAL lets `catz query` read while a capture is writing. With it,
+        // NORMAL only syncs at checkpoints rather than on every frame's
+        // commit; a power loss may lose the last frames but can't corrupt
+        // the database
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .and_then(|()| connection.pragma_update(None, "synchronous", "NORMAL"))
+            .and_then(|()| connection.execute_batch(SCHEMA))
+            .wrap_err_with(|| format!("failed to set up database {}", path.display()))?;
+        let database = Self {
+            connection,
+            path: path.to_path_buf(),
+        };
+        for (table, column, definition) in ADDED_COLUMNS {
+            if !database.has_column(table, column)? {
+                database
+                    .connection
+                    .execute_batch(&format!(
+                        "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+                    ))
+                    .wrap_err_with(|| format!("failed to upgrade database {}", path.display()))?;
+            }
+        }
+        Ok(database)
+    }
+
+    fn has_column(&self, table: &str, column: &str) -> Result<bool> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT name FROM pragma_table_info('{table}')"))?;
+        let mut names = statement.query_map([], |row| row.get::<_, String>(0))?;
+        Ok(names.any(|name| name.is_ok_and(|name| name == column)))
+    }
+
+    /// Opens an existing database without changing it.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            bail!("database {} does not exist", path.display());
+        }
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .wrap_err_with(|| format!("failed to open database {}", path.display()))?;
+        Ok(Self {
+            connection,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads one session, or the latest one, as a recording.
+    pub fn load(&self, session: Option<i64>) -> Result<Recording> {
+        let session = match session {
+            Some(session) => self
+                .connection
+                .query_row("SELECT id FROM sessions WHERE id = ?1", [session], |row| {
+                    row.get(0)
+                })
+                .map_err(|_| eyre!("{} has no session {session}", self.path.display()))?,
+            None => self
+                .connection
+                .query_row("SELECT MAX(id) FROM sessions", [], |row| {
+                    row.get::<_, Option<i64>>(0)
+                })?
+                .ok_or_else(|| eyre!("{} has no sessions", self.path.display()))?,
+        };
+
+        let threads = if self.has_column("samples", "threads")? {
+            "s.threads"
+        } else {
+            "0"
+        };
+        let io = if self.has_column("samples", "read_kb_s")? {
+            "s.read_kb_s, s.write_kb_s"
+        } else {
+            "0.0, 0.0"
+        };
+        let group = if self.has_column("processes", "group_name")? {
+            "p.group_name"
+        } else {
+            "NULL"
+        };
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT p.host, p.name, p.pid, s.cpu_usage, s.memory_mb, s.elapsed_ms, {threads}, {io},
+                 {group}
+             FROM samples s JOIN processes p ON p.id = s.process_id
+             WHERE p.session_id = ?1
+             ORDER BY s.elapsed_ms, p.id"
+        ))?;
+        let samples = statement
+            .query_map([session], |row| {
+                let host: String = row.get(0)?;
+                Ok(RecordedSample {
+                    host: Some(host).filter(|h| !h.is_empty()),
+                    name: row.get(1)?,
+                    pid: row.get(2)?,
+                    cpu_usage: row.get(3)?,
+                    memory_mb: row.get(4)?,
+                    elapsed_ms: row.get(5)?,
+                    threads: row.get(6)?,
+                    read_kb_s: row.get(7)?,
+                    write_kb_s: row.get(8)?,
+                    group: row.get(9)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT elapsed_ms, text FROM markers WHERE session_id = ?1 ORDER BY elapsed_ms",
+        )?;
+        let markers = statement
+            .query_map([session], |row| {
+                Ok(RecordedMarker {
+                    elapsed_ms: row.get(0)?,
+                    text: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let started_ms: i64 = self.connection.query_row(
+            "SELECT started_ms FROM sessions WHERE id = ?1",
+            [session],
+            |row| row.get(0),
+        )?;
+        let thresholds = if self.has_column("sessions", "thresholds")? {
+            self.connection
+                .query_row(
+                    "SELECT thresholds FROM sessions WHERE id = ?1",
+                    [session],
+                    |row| row.get::<_, Option<String>>(0),
+                )?
+                .map(|json| serde_json::from_str(&json))
+                .transpose()
+                .wrap_err_with(|| format!("session {session} has invalid thresholds"))?
+        } else {
+            None
+        };
+        Ok(Recording {
+            samples,
+            markers,
+            started: Some(UNIX_EPOCH + Duration::from_millis(started_ms.max(0) as u64)),
+            thresholds,
+        })
+    }
+
+    /// Stores a recording as a new session and returns its ID.
+    pub fn insert_recording(&mut self, recording: &Recording, source: &str) -> Result<i64> {
+        // Recordings that don't know when they started are assumed to
+        // have just ended
+        let started = recording.started.unwrap_or_else(|| {
+            let duration = recording
+                .samples
+                .iter()
+                .map(|s| s.elapsed_ms)
+                .chain(recording.markers.iter().map(|m| m.elapsed_ms))
+                .max()
+                .unwrap_or(0);
+            SystemTime::now() - Duration::from_millis(duration)
+        });
+
+        let transaction = self.connection.transaction()?;
+        let session = insert_session(&transaction, source, started, recording.thresholds.as_ref())?;
+        let mut processes = ProcessIds::new();
+        for sample in &recording.samples {
+            let process = process_id(
+                &transaction,
+                &mut processes,
+                session,
+                sample.host.as_deref().unwrap_or_default(),
+                &sample.name,
+                sample.pid,
+                sample.group.as_deref(),
+            )?;
+            insert_sample(
+                &transaction,
+                process,
+                sample.elapsed_ms,
+                &SampleValues {
+                    cpu_usage: sample.cpu_usage,
+                    memory_mb: sample.memory_mb,
+                    threads: sample.threads,
+                    read_kb_s: sample.read_kb_s,
+                    write_kb_s: sample.write_kb_s,
+                },
+            )?;
+        }
+        for marker in &recording.markers {
+            transaction.execute(
+                "INSERT INTO markers (session_id, elapsed_ms, text) VALUES (?1, ?2, ?3)",
+                params![session, marker.elapsed_ms, marker.text],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(session)
+    }
+
+    /// Starts a session that frames are written to as they are sampled.
+    /// Frame times count from `started`.
+    pub fn begin_session(
+        mut self,
+        source: &str,
+        started: SystemTime,
+        thresholds: &Thresholds,
+    ) -> Result<SessionWriter> {
+        let transaction = self.connection.transaction()?;
+        let session = insert_session(&transaction, source, started, Some(thresholds))?;
+        transaction.commit()?;
+        Ok(SessionWriter {
+            database: self,
+            session,
+            processes: ProcessIds::new(),
+        })
+    }
+
+    /// Runs a single SQL statement and collects its rows.
+    pub fn query(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<QueryRows> {
+        let mut statement = self.connection.prepare(sql)?;
+        let columns: Vec<String> = statement
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let count = columns.len();
+        let rows = statement
+            .query_map(params, |row| {
+                (0..count)
+                    .map(|i| row.get_ref(i).map(json_value))
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(QueryRows { columns, rows })
+    }
+}
+
+/// A session being captured, one frame at a time.
+pub struct SessionWriter {
+    database: Database,
+    session: i64,
+    processes: ProcessIds,
+}
+
+impl std::fmt::Debug for SessionWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionWriter")
+            .field("path", &self.database.path)
+            .field("session", &self.session)
+            .finish()
+    }
+}
+
+impl SessionWriter {
+    pub fn path(&self) -> &Path {
+        self.database.path()
+    }
+
+    pub fn session(&self) -> i64 {
+        self.session
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        let transaction = self.database.connection.transaction()?;
+        for process in &frame.processes {
+            let id = process_id(
+                &transaction,
+                &mut self.processes,
+                self.session,
+                process.host.as_deref().unwrap_or_default(),
+                &process.name,
+                process.local_pid(),
+                process.group.as_deref(),
+            )?;
+            insert_sample(
+                &transaction,
+                id,
+                frame.elapsed.as_millis() as u64,
+                &SampleValues {
+                    cpu_usage: process.cpu_usage,
+                    memory_mb: process.memory_mb,
+                    threads: process.threads,
+                    read_kb_s: process.read_kb_s,
+                    write_kb_s: process.write_kb_s,
+                },
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn write_marker(&mut self, marker: &Marker) -> Result<()> {
+        self.database.connection.execute(
+            "INSERT INTO markers (session_id, elapsed_ms, text) VALUES (?1, ?2, ?3)",
+            params![self.session, marker.elapsed.as_millis() as u64, marker.text],
+        )?;
+        Ok(())
+    }
+}
+
+fn insert_session(
+    transaction: &Transaction,
+    source: &str,
+    started: SystemTime,
+    thresholds: Option<&Thresholds>,
+) -> Result<i64> {
+    let started_ms = started
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64;
+    let thresholds = thresholds.map(serde_json::to_string).transpose()?;
+    transaction.execute(
+        "INSERT INTO sessions (started_ms, source, thresholds) VALUES (?1, ?2, ?3)",
+        params![started_ms, source, thresholds],
+    )?;
+    Ok(transaction.last_insert_rowid())
+}
+
+/// Row ID of a process, inserting it with its group on first sight.
+fn process_id(
+    transaction: &Transaction,
+    processes: &mut ProcessIds,
+    session: i64,
+    host: &str,
+    name: &str,
+    pid: u32,
+    group: Option<&str>,
+) -> Result<i64> {
+    let key = (host.to_string(), name.to_string(), pid);
+    if let Some(id) = processes.get(&key) {
+        return Ok(*id);
+    }
+    transaction.execute(
+        "INSERT OR IGNORE INTO processes (session_id, host, name, pid, group_name)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![session, host, name, pid, group],
+    )?;
+    let id = transaction.query_row(
+        "SELECT id FROM processes WHERE session_id = ?1 AND host = ?2 AND name = ?3 AND pid = ?4",
+        params![session, host, name, pid],
+        |row| row.get(0),
+    )?;
+    processes.insert(key, id);
+    Ok(id)
+}
+
+/// The measured columns of a `samples` row.
+struct SampleValues {
+    cpu_usage: f32,
+    memory_mb: f64,
+    threads: u32,
+    read_kb_s: f64,
+    write_kb_s: f64,
+}
+
+fn insert_sample(
+    transaction: &Transaction,
+    process: i64,
+    elapsed_ms: u64,
+    values: &SampleValues,
+) -> Result<()> {
+    transaction.execute(
+        "INSERT INTO samples (process_id, elapsed_ms, cpu_usage, memory_mb, threads, read_kb_s, write_kb_s)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            process,
+            elapsed_ms,
+            values.cpu_usage,
+            values.memory_mb,
+            values.threads,
+            values.read_kb_s,
+            values.write_kb_s
+        ],
+    )?;
+    Ok(())
+}
+
+fn json_value(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned().into(),
+        ValueRef::Blob(blob) => format!("<{} bytes>", blob.len()).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+    use crate::domain::process::ProcessInfo;
+
+    /// A database in a fresh temporary directory, removed when dropped.
+    struct TempDatabase(PathBuf);
+
+    impl TempDatabase {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("catz-sqlite-{}-{name}", process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            Self(dir.join("catz.db"))
+        }
+    }
+
+    impl Drop for TempDatabase {
+        fn drop(&mut self) {
+            if let Some(dir) = self.0.parent() {
+                let _ = fs::remove_dir_all(dir);
+            }
+        }
+    }
+
+    fn frame(elapsed_ms: u64, cpu_usage: f32) -> Frame {
+        let mut process = ProcessInfo::new("nginx".to_string(), 42.into(), cpu_usage, 64.0);
+        process.threads = 3;
+        process.write_kb_s = 2.5;
+        Frame {
+            elapsed: Duration::from_millis(elapsed_ms),
+            processes: vec![process],
+        }
+    }
+
+    #[test]
+    fn sessions_are_written_frame_by_frame() {
+        let path = TempDatabase::new("frames");
+        let started = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let thresholds = Thresholds {
+            cpu: Some(50.0),
+            memory_mb: None,
+        };
+        let mut writer = Database::open(&path.0)
+            .unwrap()
+            .begin_session("test", started, &thresholds)
+            .unwrap();
+        writer.write_frame(&frame(0, 10.0)).unwrap();
+        writer
+            .write_marker(&Marker {
+                elapsed: Duration::from_millis(500),
+                text: "go".to_string(),
+            })
+            .unwrap();
+        writer.write_frame(&frame(1000, 60.0)).unwrap();
+        drop(writer);
+
+        let recording = Database::open_read_only(&path.0)
+            .unwrap()
+            .load(None)
+            .unwrap();
+        assert_eq!(recording.started, Some(started));
+        assert_eq!(recording.thresholds, Some(thresholds));
+        assert_eq!(recording.samples.len(), 2);
+        let sample = &recording.samples[1];
+        assert_eq!((sample.name.as_str(), sample.pid), ("nginx", 42));
+        assert_eq!(sample.cpu_usage, 60.0);
+        assert_eq!(sample.threads, 3);
+        assert_eq!(sample.write_kb_s, 2.5);
+        assert_eq!(sample.elapsed_ms, 1000);
+        assert_eq!(recording.markers[0].text, "go");
+    }
+
+    #[test]
+    fn loads_the_requested_session() {
+        let path = TempDatabase::new("sessions");
+        let mut database = Database::open(&path.0).unwrap();
+        let mut first = Recording::default();
+        first.samples.push(RecordedSample {
+            host: Some("web1".to_string()),
+            name: "first".to_string(),
+            pid: 1,
+            cpu_usage: 1.0,
+            memory_mb: 1.0,
+            threads: 0,
+            read_kb_s: 0.0,
+            write_kb_s: 0.0,
+            elapsed_ms: 0,
+            group: None,
+        });
+        let mut second = first.clone();
+        second.samples[0].name = "second".to_string();
+        second.samples[0].host = None;
+        let first_id = database.insert_recording(&first, "first").unwrap();
+        database.insert_recording(&second, "second").unwrap();
+
+        let loaded = database.load(Some(first_id)).unwrap();
+        assert_eq!(loaded.samples[0].name, "first");
+        assert_eq!(loaded.samples[0].host.as_deref(), Some("web1"));
+        let latest = database.load(None).unwrap();
+        assert_eq!(latest.samples[0].name, "second");
+        assert_eq!(latest.samples[0].host, None);
+        // Recordings without a start time are assumed to have just ended
+        assert!(latest.started.is_some());
+        assert!(database.load(Some(99)).is_err());
+    }
+
+    #[test]
+    fn upgrades_databases_without_the_added_columns() {
+        let path = TempDatabase::new("upgrade");
+        fs::create_dir_all(path.0.parent().unwrap()).unwrap();
+        let connection = Connection::open(&path.0).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE sessions (id INTEGER PRIMARY KEY, started_ms INTEGER NOT NULL,
+                                        source TEXT NOT NULL);
+                 CREATE TABLE processes (id INTEGER PRIMARY KEY, session_id INTEGER NOT NULL,
+                                         host TEXT NOT NULL DEFAULT '', name TEXT NOT NULL,
+                                         pid INTEGER NOT NULL,
+                                         UNIQUE (session_id, host, name, pid));
+                 CREATE TABLE samples (process_id INTEGER NOT NULL, elapsed_ms INTEGER NOT NULL,
+                                       cpu_usage REAL NOT NULL, memory_mb REAL NOT NULL);
+                 CREATE TABLE markers (session_id INTEGER NOT NULL, elapsed_ms INTEGER NOT NULL,
+                                       text TEXT NOT NULL);
+                 INSERT INTO sessions VALUES (1, 0, 'old');
+                 INSERT INTO processes VALUES (1, 1, '', 'nginx', 42);
+                 INSERT INTO samples VALUES (1, 0, 5.0, 10.0);",
+            )
+            .unwrap();
+        drop(connection);
+
+        let old = Database::open_read_only(&path.0)
+            .unwrap()
+            .load(None)
+            .unwrap();
+        assert_eq!(old.samples[0].cpu_usage, 5.0);
+        assert_eq!(old.samples[0].threads, 0);
+        assert_eq!(old.thresholds, None);
+
+        let mut database = Database::open(&path.0).unwrap();
+        let id = database.insert_recording(&old, "new").unwrap();
+        assert_eq!(database.load(Some(id)).unwrap().samples[0].memory_mb, 10.0);
+    }
+}
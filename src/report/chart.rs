@@ -0,0 +1,468 @@
+//! Time series line charts, drawn onto an SVG or PNG [`Canvas`].
+
+use color_eyre::{Result, eyre::WrapErr};
+use std::{fs, path::Path};
+
+use super::{png::PngCanvas, svg::SvgCanvas};
+
+/// Colors of successive series.
+const PALETTE: [Rgb; 8] = [
+    Rgb(0x00, 0x97, 0xa7),
+    Rgb(0xc2, 0x18, 0x5b),
+    Rgb(0x7c, 0xb3, 0x42),
+    Rgb(0xf5, 0x7c, 0x00),
+    Rgb(0x5e, 0x35, 0xb1),
+    Rgb(0x1e, 0x88, 0xe5),
+    Rgb(0x6d, 0x4c, 0x41),
+    Rgb(0x00, 0x89, 0x7b),
+];
+const TEXT: Rgb = Rgb(0x22, 0x22, 0x22);
+const AXIS_TEXT: Rgb = Rgb(0x55, 0x55, 0x55);
+const GRID: Rgb = Rgb(0xe6, 0xe6, 0xe6);
+const FRAME: Rgb = Rgb(0x99, 0x99, 0x99);
+const MARKER: Rgb = Rgb(0x75, 0x75, 0x75);
+
+const MARGIN_LEFT: f64 = 64.0;
+const MARGIN_RIGHT: f64 = 24.0;
+const MARGIN_TOP: f64 = 36.0;
+const MARGIN_BOTTOM: f64 = 44.0;
+const FONT_SIZE: f64 = 11.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const WHITE: Rgb = Rgb(0xff, 0xff, 0xff);
+
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// Which point of the text its position refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextStyle {
+    pub size: f64,
+    pub color: Rgb,
+    pub anchor: Anchor,
+    pub bold: bool,
+    /// Runs upwards, as on a y axis
+    pub vertical: bool,
+}
+
+impl TextStyle {
+    fn new(size: f64, color: Rgb) -> Self {
+        Self {
+            size,
+            color,
+            anchor: Anchor::Start,
+            bold: false,
+            vertical: false,
+        }
+    }
+
+    fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+}
+
+/// Drawing primitives charts are made of. Coordinates are in pixels from
+/// the top left corner, text positions are on its baseline.
+pub trait Canvas {
+    fn polyline(&mut self, points: &[(f64, f64)], color: Rgb, width: f64, dashed: bool);
+
+    fn rect(
+        &mut self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        fill: Option<Rgb>,
+        stroke: Option<Rgb>,
+    );
+
+    fn text(&mut self, x: f64, y: f64, text: &str, style: TextStyle);
+}
+
+/// Image formats a chart can be saved as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "svg" => Some(Self::Svg),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+}
+
+/// One line of a chart: `(seconds, value)` points.
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+/// A time series chart with one or more lines and optional markers.
+#[derive(Debug, Clone)]
+pub struct LineChart {
+    pub title: String,
+    /// Label of the y axis, including its unit
+    pub y_label: String,
+    pub series: Vec<Series>,
+    /// `(seconds, text)` of events drawn as vertical lines
+    pub markers: Vec<(f64, String)>,
+    pub log_scale: bool,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl LineChart {
+    pub fn new(title: impl Into<String>, y_label: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            y_label: y_label.into(),
+            series: Vec::new(),
+            markers: Vec::new(),
+            log_scale: false,
+            width: 640,
+            height: 280,
+        }
+    }
+
+    pub fn series(mut self, label: impl Into<String>, points: Vec<(f64, f64)>) -> Self {
+        self.series.push(Series {
+            label: label.into(),
+            points,
+        });
+        self
+    }
+
+    pub fn markers(mut self, markers: Vec<(f64, String)>) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    /// Uses a logarithmic y axis; values of zero or less sit on its floor.
+    pub fn log_scale(mut self, log_scale: bool) -> Self {
+        self.log_scale = log_scale;
+        self
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut canvas = SvgCanvas::new(self.width, self.height);
+        self.draw(&mut canvas);
+        canvas.finish()
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut canvas = PngCanvas::new(self.width, self.height);
+        self.draw(&mut canvas);
+        canvas.finish()
+    }
+
+    pub fn save(&self, path: &Path, format: ImageFormat) -> Result<()> {
+        let content = match format {
+            ImageFormat::Svg => self.to_svg().into_bytes(),
+            ImageFormat::Png => self.to_png()?,
+        };
+        fs::write(path, content).wrap_err_with(|| format!("failed to write {}", path.display()))
+    }
+
+    pub fn draw(&self, canvas: &mut dyn Canvas) {
+        let (width, height) = (self.width as f64, self.height as f64);
+        // Several lines get a legend column on the right
+        let legend_width = if self.series.len() > 1 {
+            let longest = self.series.iter().map(|s| s.label.chars().count()).max();
+            24.0 + longest.unwrap_or(0) as f64 * FONT_SIZE * 0.6
+        } else {
+            0.0
+        };
+        let plot_left = MARGIN_LEFT;
+        let plot_top = MARGIN_TOP;
+        let plot_width = (width - MARGIN_LEFT - MARGIN_RIGHT - legend_width).max(1.0);
+        let plot_height = (height - MARGIN_TOP - MARGIN_BOTTOM).max(1.0);
+
+        let points = self.series.iter().flat_map(|s| &s.points);
+        let (x_min, x_max) = points
+            .clone()
+            .map(|(x, _)| *x)
+            .chain(self.markers.iter().map(|(x, _)| *x))
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| {
+                (lo.min(x), hi.max(x))
+            });
+        let (x_min, x_max) = if x_min.is_finite() {
+            (x_min, x_max.max(x_min + 1.0))
+        } else {
+            (0.0, 1.0)
+        };
+        let y_axis = YAxis::new(points.map(|(_, y)| *y), self.log_scale);
+        let x_ticks = nice_ticks(x_min, x_max, 6);
+
+        let x = |value: f64| plot_left + (value - x_min) / (x_max - x_min) * plot_width;
+        let y = |value: f64| plot_top + plot_height - y_axis.fraction(value) * plot_height;
+
+        canvas.rect(0.0, 0.0, width, height, Some(Rgb::WHITE), None);
+        canvas.text(
+            plot_left,
+            20.0,
+            &self.title,
+            TextStyle {
+                bold: true,
+                ..TextStyle::new(13.0, TEXT)
+            },
+        );
+
+        // Grid and axis labels
+        for tick in &y_axis.ticks {
+            canvas.polyline(
+                &[(plot_left, y(*tick)), (plot_left + plot_width, y(*tick))],
+                GRID,
+                1.0,
+                false,
+            );
+            canvas.text(
+                plot_left - 6.0,
+                y(*tick) + 4.0,
+                &format_value(*tick),
+                TextStyle::new(FONT_SIZE, AXIS_TEXT).anchor(Anchor::End),
+            );
+        }
+        for tick in x_ticks.iter().filter(|t| (x_min..=x_max).contains(*t)) {
+            canvas.polyline(
+                &[(x(*tick), plot_top), (x(*tick), plot_top + plot_height)],
+                GRID,
+                1.0,
+                false,
+            );
+            canvas.text(
+                x(*tick),
+                plot_top + plot_height + 16.0,
+                &format_seconds(*tick),
+                TextStyle::new(FONT_SIZE, AXIS_TEXT).anchor(Anchor::Middle),
+            );
+        }
+        canvas.rect(
+            plot_left,
+            plot_top,
+            plot_width,
+            plot_height,
+            None,
+            Some(FRAME),
+        );
+        canvas.text(
+            plot_left + plot_width / 2.0,
+            height - 8.0,
+            "Time",
+            TextStyle::new(FONT_SIZE, AXIS_TEXT).anchor(Anchor::Middle),
+        );
+        canvas.text(
+            18.0,
+            plot_top + plot_height / 2.0,
+            &self.y_label,
+            TextStyle {
+                vertical: true,
+                ..TextStyle::new(FONT_SIZE, AXIS_TEXT).anchor(Anchor::Middle)
+            },
+        );
+
+        // Labels of nearby markers would overlap, so they are staggered
+        for (i, (time, text)) in self.markers.iter().enumerate() {
+            canvas.polyline(
+                &[(x(*time), plot_top), (x(*time), plot_top + plot_height)],
+                MARKER,
+                1.0,
+                true,
+            );
+            canvas.text(
+                x(*time) + 3.0,
+                plot_top + 11.0 + (i % 3) as f64 * 12.0,
+                text,
+                TextStyle::new(10.0, MARKER),
+            );
+        }
+
+        for (i, series) in self.series.iter().enumerate() {
+            let points: Vec<(f64, f64)> = series
+                .points
+                .iter()
+                .map(|(px, py)| (x(*px), y(*py)))
+                .collect();
+            canvas.polyline(&points, PALETTE[i % PALETTE.len()], 1.5, false);
+        }
+
+        if legend_width > 0.0 {
+            let left = plot_left + plot_width + 12.0;
+            for (i, series) in self.series.iter().enumerate() {
+                let baseline = plot_top + 10.0 + i as f64 * 16.0;
+                canvas.rect(
+                    left,
+                    baseline - 9.0,
+                    10.0,
+                    10.0,
+                    Some(PALETTE[i % PALETTE.len()]),
+                    None,
+                );
+                canvas.text(
+                    left + 14.0,
+                    baseline,
+                    &series.label,
+                    TextStyle::new(FONT_SIZE, TEXT),
+                );
+            }
+        }
+    }
+}
+
+/// Ticks and scaling of the value axis.
+struct YAxis {
+    ticks: Vec<f64>,
+    /// Bounds, as logarithms on a log scale
+    low: f64,
+    high: f64,
+    log_scale: bool,
+}
+
+impl YAxis {
+    fn new(values: impl Iterator<Item = f64> + Clone, log_scale: bool) -> Self {
+        if log_scale {
+            let positive = values.filter(|v| *v > 0.0);
+            let min = positive.clone().fold(f64::INFINITY, f64::min);
+            let max = positive.fold(0.0, f64::max);
+            let (low, high) = if min.is_finite() {
+                let low = min.log10().floor();
+                (low, max.log10().ceil().max(low + 1.0))
+            } else {
+                (0.0, 1.0)
+            };
+            let ticks = (low as i32..=high as i32)
+                .map(|exponent| 10f64.powi(exponent))
+                .collect();
+            return Self {
+                ticks,
+                low,
+                high,
+                log_scale,
+            };
+        }
+        let max = values.fold(0.0, f64::max);
+        let ticks = nice_ticks(0.0, if max > 0.0 { max * 1.05 } else { 1.0 }, 5);
+        let high = ticks.last().copied().unwrap_or(1.0);
+        Self {
+            ticks,
+            low: 0.0,
+            high,
+            log_scale,
+        }
+    }
+
+    /// Position of a value between the bottom (0) and top (1) of the axis.
+    fn fraction(&self, value: f64) -> f64 {
+        let value = if self.log_scale {
+            if value > 0.0 { value.log10() } else { self.low }
+        } else {
+            value
+        };
+        ((value - self.low) / (self.high - self.low)).clamp(0.0, 1.0)
+    }
+}
+
+/// Round tick values covering `min..=max`, spaced 1, 2 or 5 times a power
+/// of ten apart. An empty range has a single tick.
+fn nice_ticks(min: f64, max: f64, count: usize) -> Vec<f64> {
+    if max <= min {
+        return vec![min];
+    }
+    let range = max - min;
+    let rough = range / count.max(1) as f64;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * magnitude);
+    let first = (min / step).floor() * step;
+    (0..)
+        .map(|i| first + i as f64 * step)
+        .take_while(|tick| *tick < max + step * 0.999)
+        .collect()
+}
+
+fn format_value(value: f64) -> String {
+    if value.abs() >= 100.0 || value.fract() == 0.0 {
+        format!("{value:.0}")
+    } else if value.abs() >= 0.1 {
+        format!("{value:.1}")
+    } else {
+        // Rounded to hide floating point noise such as 0.060000000000000005
+        let value = format!("{value:.6}");
+        value
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+/// `0.5s`, `42s`, `5m`, `1h30m`
+pub fn format_seconds(seconds: f64) -> String {
+    if seconds < 10.0 && (seconds * 10.0).round() % 10.0 != 0.0 {
+        return format!("{seconds:.1}s");
+    }
+    let total = seconds.round() as u64;
+    match (total / 3600, total / 60 % 60, total % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, 0) => format!("{m}m"),
+        (0, m, s) => format!("{m}m{s}s"),
+        (h, 0, _) => format!("{h}h"),
+        (h, m, _) => format!("{h}h{m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_are_round_and_cover_the_range() {
+        assert_eq!(
+            nice_ticks(0.0, 100.0, 5),
+            [0.0, 20.0, 40.0, 60.0, 80.0, 100.0]
+        );
+        assert_eq!(nice_ticks(3.0, 17.0, 3), [0.0, 5.0, 10.0, 15.0, 20.0]);
+        let ticks = nice_ticks(0.0, 0.07, 4);
+        assert_eq!(ticks.len(), 5);
+        assert!((ticks[4] - 0.08).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ticks_of_an_empty_range() {
+        assert_eq!(nice_ticks(5.0, 5.0, 5), [5.0]);
+        assert_eq!(nice_ticks(0.0, 10.0, 0), [0.0, 10.0]);
+    }
+
+    #[test]
+    fn formats_tick_values() {
+        assert_eq!(format_value(250.0), "250");
+        assert_eq!(format_value(2.5), "2.5");
+        assert_eq!(format_value(0.1 * 0.6), "0.06");
+    }
+}
@@ -0,0 +1,476 @@
+//! Self-contained HTML reports of recordings, with SVG charts of CPU,
+//! memory and disk I/O, summary statistics, markers and threshold alerts,
+//! and standalone SVG or PNG charts.
+
+pub mod chart;
+pub mod png;
+pub mod svg;
+pub mod trace;
+
+use color_eyre::{Result, eyre::WrapErr};
+use std::{fmt::Write, fs, path::Path, time::SystemTime};
+
+use crate::{
+    config::Thresholds,
+    domain::{
+        history::{History, Metric},
+        process::ProcessInfo,
+        stats::Summary,
+    },
+    recording::Recording,
+};
+
+use self::{
+    chart::{LineChart, format_seconds},
+    svg::escape,
+};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2rem auto; max-width: 1340px; color: #222; }
+h1 { font-size: 1.5rem; margin-bottom: 0.25rem; }
+h2 { font-size: 1.15rem; margin-top: 2rem; border-bottom: 1px solid #ddd; padding-bottom: 0.25rem; }
+h3 { font-size: 1rem; margin-bottom: 0.25rem; }
+table { border-collapse: collapse; font-variant-numeric: tabular-nums; }
+th, td { padding: 0.3rem 0.8rem; border-bottom: 1px solid #eee; text-align: left; }
+th { background: #f5f5f5; }
+td.num { text-align: right; }
+dl { display: grid; grid-template-columns: max-content auto; gap: 0.2rem 1rem; }
+dt { color: #666; }
+dd { margin: 0; }
+.charts { display: flex; flex-wrap: wrap; gap: 1rem; }
+.muted { color: #777; }
+";
+
+/// Whether a file name asks for a report rather than a recording.
+pub fn is_report_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("html"))
+}
+
+/// A period during which a process stayed above a configured threshold.
+#[derive(Debug, Clone)]
+struct Alert {
+    process: String,
+    metric: Metric,
+    limit: f64,
+    /// Seconds of the first and last sample above the limit
+    from: f64,
+    to: f64,
+    peak: f64,
+}
+
+/// Statistics of one program over the whole recording.
+struct ProgramSummary {
+    program: ProcessInfo,
+    /// Distinct PIDs seen, more than one means the program restarted
+    instances: usize,
+    samples: usize,
+    cpu: Summary,
+    memory: Summary,
+    read: Summary,
+    write: Summary,
+}
+
+/// An HTML report of a recording.
+pub struct Report {
+    /// Where the recording came from, e.g. its file name
+    source: String,
+    history: History,
+    started: Option<SystemTime>,
+    thresholds: Thresholds,
+    /// Whether `thresholds` are the recording's own rather than the
+    /// current config's
+    recorded_thresholds: bool,
+}
+
+impl Report {
+    pub fn new(mut recording: Recording, source: impl Into<String>) -> Result<Self> {
+        let thresholds = recording.thresholds.take();
+        Ok(Self {
+            source: source.into(),
+            recorded_thresholds: thresholds.is_some(),
+            thresholds: thresholds.unwrap_or_default(),
+            started: recording.started,
+            history: recording.into_history()?,
+        })
+    }
+
+    /// Limits whose crossings are listed as alerts, for recordings that
+    /// don't store the ones they were made with.
+    pub fn thresholds(mut self, thresholds: Thresholds) -> Self {
+        if !self.recorded_thresholds {
+            self.thresholds = thresholds;
+        }
+        self
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, self.to_html())
+            .wrap_err_with(|| format!("failed to write {}", path.display()))
+    }
+
+    pub fn to_html(&self) -> String {
+        let programs = self.history.programs();
+        let summaries: Vec<ProgramSummary> =
+            programs.iter().filter_map(|p| self.summarize(p)).collect();
+        let alerts: Vec<Alert> = programs
+            .iter()
+            .flat_map(|p| alerts(&self.history, p, &self.thresholds))
+            .collect();
+
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>CatZ report - {title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>CatZ report</h1>\n<p class=\"muted\">{title}</p>\n",
+            title = escape(&self.source)
+        );
+        self.write_metadata(&mut html, programs.len());
+        write_summary(&mut html, &summaries);
+        self.write_markers(&mut html);
+        self.write_alerts(&mut html, &alerts);
+        self.write_charts(&mut html, &programs);
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn series(&self, program: &ProcessInfo, metric: Metric) -> Vec<(f64, f64)> {
+        self.history.program_series(program, metric)
+    }
+
+    fn summarize(&self, program: &ProcessInfo) -> Option<ProgramSummary> {
+        let samples: Vec<&ProcessInfo> = self
+            .history
+            .frames()
+            .filter_map(|frame| frame.processes.iter().find(|p| p.same_program(program)))
+            .collect();
+        let mut pids: Vec<u32> = samples.iter().map(|p| p.local_pid()).collect();
+        pids.sort_unstable();
+        pids.dedup();
+        let values = |metric: Metric| samples.iter().map(|p| metric.value(p)).collect::<Vec<_>>();
+        Some(ProgramSummary {
+            program: program.clone(),
+            instances: pids.len(),
+            samples: samples.len(),
+            cpu: Summary::from_samples(&values(Metric::Cpu))?,
+            memory: Summary::from_samples(&values(Metric::Memory))?,
+            read: Summary::from_samples(&values(Metric::DiskRead))?,
+            write: Summary::from_samples(&values(Metric::DiskWrite))?,
+        })
+    }
+
+    fn write_metadata(&self, html: &mut String, programs: usize) {
+        let (start, end) = self.history.time_range().unwrap_or_default();
+        let duration = end.saturating_sub(start);
+        let mut hosts: Vec<&str> = self
+            .history
+            .frames()
+            .flat_map(|frame| frame.processes.iter().filter_map(|p| p.host.as_deref()))
+            .collect();
+        hosts.sort_unstable();
+        hosts.dedup();
+
+        let mut rows = vec![
+            ("Recording", escape(&self.source)),
+            (
+                "Generated",
+                humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            ),
+        ];
+        if let Some(started) = self.started {
+            rows.push((
+                "Started",
+                humantime::format_rfc3339_seconds(started).to_string(),
+            ));
+        }
+        rows.extend([
+            ("Duration", format_seconds(duration.as_secs_f64())),
+            ("Frames", self.history.len().to_string()),
+        ]);
+        if self.history.len() > 1 {
+            let interval = duration / (self.history.len() as u32 - 1);
+            rows.push(("Average interval", format!("{} ms", interval.as_millis())));
+        }
+        rows.push(("Processes", programs.to_string()));
+        if !hosts.is_empty() {
+            rows.push(("Hosts", escape(&hosts.join(", "))));
+        }
+        rows.push(("Markers", self.history.markers().len().to_string()));
+        if self.thresholds.is_set() {
+            let mut limits = Vec::new();
+            if let Some(cpu) = self.thresholds.cpu {
+                limits.push(format!("CPU {cpu:.1}%"));
+            }
+            if let Some(memory_mb) = self.thresholds.memory_mb {
+                limits.push(format!("memory {memory_mb:.1} MB"));
+            }
+            if !self.recorded_thresholds {
+                limits.push("from the current config".to_string());
+            }
+            rows.push(("Thresholds", limits.join(", ")));
+        }
+
+        html.push_str("<h2>Recording</h2>\n<dl>\n");
+        for (name, value) in rows {
+            let _ = writeln!(html, "<dt>{name}</dt><dd>{value}</dd>");
+        }
+        html.push_str("</dl>\n");
+    }
+
+    fn write_markers(&self, html: &mut String) {
+        html.push_str("<h2>Markers</h2>\n");
+        if self.history.markers().is_empty() {
+            html.push_str("<p class=\"muted\">No markers were added.</p>\n");
+            return;
+        }
+        html.push_str("<table>\n<tr><th>Time</th><th>Marker</th></tr>\n");
+        for marker in self.history.markers() {
+            let _ = writeln!(
+                html,
+                "<tr><td class=\"num\">{}</td><td>{}</td></tr>",
+                format_seconds(marker.elapsed.as_secs_f64()),
+                escape(&marker.text)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn write_alerts(&self, html: &mut String, alerts: &[Alert]) {
+        html.push_str("<h2>Alerts</h2>\n");
+        if !self.thresholds.is_set() {
+            html.push_str(if self.recorded_thresholds {
+                "<p class=\"muted\">No thresholds were set while recording.</p>\n"
+            } else {
+                "<p class=\"muted\">No thresholds are configured; set <code>[thresholds]</code> in the config file.</p>\n"
+            });
+            return;
+        }
+        if !self.recorded_thresholds {
+            html.push_str(
+                "<p class=\"muted\">The recording doesn't store the thresholds it was made with, so these alerts use the current config's.</p>\n",
+            );
+        }
+        if alerts.is_empty() {
+            html.push_str("<p class=\"muted\">No process exceeded a threshold.</p>\n");
+            return;
+        }
+        html.push_str(
+            "<table>\n<tr><th>Process</th><th>Metric</th><th>Threshold</th><th>From</th><th>To</th><th>Peak</th></tr>\n",
+        );
+        for alert in alerts {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td class=\"num\">{:.1}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.1}</td></tr>",
+                escape(&alert.process),
+                metric_label(alert.metric),
+                alert.limit,
+                format_seconds(alert.from),
+                format_seconds(alert.to),
+                alert.peak
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn write_charts(&self, html: &mut String, programs: &[ProcessInfo]) {
+        let markers: Vec<(f64, String)> = self
+            .history
+            .markers()
+            .iter()
+            .map(|marker| (marker.elapsed.as_secs_f64(), marker.text.clone()))
+            .collect();
+        html.push_str("<h2>Charts</h2>\n");
+        // Recordings made before disk I/O was sampled only have zeros
+        let with_io = self.history.frames().any(|frame| {
+            frame
+                .processes
+                .iter()
+                .any(|p| p.read_kb_s > 0.0 || p.write_kb_s > 0.0)
+        });
+        if !with_io {
+            html.push_str(
+                "<p class=\"muted\">No disk I/O was recorded, so I/O charts are left out. Recordings made before I/O was sampled don't have it, and it can only be read for processes of the recording user.</p>\n",
+            );
+        }
+        for program in programs {
+            let name = label(program);
+            let _ = writeln!(html, "<h3>{}</h3>\n<div class=\"charts\">", escape(&name));
+            for metric in [Metric::Cpu, Metric::Memory] {
+                let chart = LineChart::new(
+                    format!("{} - {}", metric_label(metric), name),
+                    metric_label(metric),
+                )
+                .series(name.clone(), self.series(program, metric))
+                .markers(markers.clone());
+                html.push_str(&chart.to_svg());
+                html.push('\n');
+            }
+            if with_io {
+                let chart = LineChart::new(format!("Disk I/O (KB/s) - {name}"), "Disk I/O (KB/s)")
+                    .series("Read", self.series(program, Metric::DiskRead))
+                    .series("Write", self.series(program, Metric::DiskWrite))
+                    .markers(markers.clone());
+                html.push_str(&chart.to_svg());
+                html.push('\n');
+            }
+            html.push_str("</div>\n");
+        }
+    }
+}
+
+/// Periods during which a program stayed above a threshold.
+fn alerts(history: &History, program: &ProcessInfo, thresholds: &Thresholds) -> Vec<Alert> {
+    let limits = [
+        (Metric::Cpu, thresholds.cpu.map(f64::from)),
+        (Metric::Memory, thresholds.memory_mb),
+    ];
+    let mut alerts = Vec::new();
+    for (metric, limit) in limits {
+        let Some(limit) = limit else {
+            continue;
+        };
+        let mut current: Option<Alert> = None;
+        for (time, value) in history.program_series(program, metric) {
+            match &mut current {
+                Some(alert) if value > limit => {
+                    alert.to = time;
+                    alert.peak = alert.peak.max(value);
+                }
+                None if value > limit => {
+                    current = Some(Alert {
+                        process: label(program),
+                        metric,
+                        limit,
+                        from: time,
+                        to: time,
+                        peak: value,
+                    });
+                }
+                _ => alerts.extend(current.take()),
+            }
+        }
+        alerts.extend(current);
+    }
+    alerts
+}
+
+fn write_summary(html: &mut String, summaries: &[ProgramSummary]) {
+    html.push_str("<h2>Summary</h2>\n");
+    if summaries.is_empty() {
+        html.push_str("<p class=\"muted\">The recording has no samples.</p>\n");
+        return;
+    }
+    html.push_str(
+        "<table>\n<tr><th>Process</th><th>PIDs</th><th>Samples</th><th>CPU % mean</th><th>CPU % stddev</th><th>CPU % max</th><th>Memory MB mean</th><th>Memory MB min</th><th>Memory MB max</th><th>Read KB/s mean</th><th>Write KB/s mean</th></tr>\n",
+    );
+    for summary in summaries {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.1}</td><td class=\"num\">{:.1}</td><td class=\"num\">{:.1}</td><td class=\"num\">{:.1}</td><td class=\"num\">{:.1}</td><td class=\"num\">{:.1}</td><td class=\"num\">{:.1}</td><td class=\"num\">{:.1}</td></tr>",
+            escape(&label(&summary.program)),
+            summary.instances,
+            summary.samples,
+            summary.cpu.mean,
+            summary.cpu.stddev,
+            summary.cpu.max,
+            summary.memory.mean,
+            summary.memory.min,
+            summary.memory.max,
+            summary.read.mean,
+            summary.write.mean
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+/// `nginx`, or `nginx @ web1` for a remote process.
+pub fn label(program: &ProcessInfo) -> String {
+    match &program.host {
+        Some(host) => format!("{} @ {host}", program.name),
+        None => program.name.clone(),
+    }
+}
+
+pub fn metric_label(metric: Metric) -> &'static str {
+    match metric {
+        Metric::Cpu => "CPU %",
+        Metric::Memory => "Memory (MB)",
+        Metric::Threads => "Threads",
+        Metric::DiskRead => "Disk read (KB/s)",
+        Metric::DiskWrite => "Disk write (KB/s)",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::RecordedSample;
+
+    fn recording(cpu: &[f32], thresholds: Option<Thresholds>) -> Recording {
+        Recording {
+            samples: cpu
+                .iter()
+                .enumerate()
+                .map(|(i, cpu_usage)| RecordedSample {
+                    name: "nginx".to_string(),
+                    pid: 42,
+                    cpu_usage: *cpu_usage,
+                    memory_mb: 100.0,
+                    threads: 4,
+                    read_kb_s: i as f64,
+                    write_kb_s: 0.0,
+                    elapsed_ms: i as u64 * 1000,
+                    host: None,
+                    group: None,
+                })
+                .collect(),
+            markers: Vec::new(),
+            started: None,
+            thresholds,
+        }
+    }
+
+    fn cpu_limit(cpu: f32) -> Thresholds {
+        Thresholds {
+            cpu: Some(cpu),
+            memory_mb: None,
+        }
+    }
+
+    #[test]
+    fn alerts_use_the_recorded_thresholds() {
+        let html = Report::new(recording(&[10.0, 90.0, 10.0], Some(cpu_limit(50.0))), "run")
+            .unwrap()
+            .thresholds(cpu_limit(95.0))
+            .to_html();
+        assert!(html.contains("<td>CPU %</td><td class=\"num\">50.0</td>"));
+        assert!(!html.contains("current config"));
+    }
+
+    #[test]
+    fn alerts_fall_back_to_the_config() {
+        let html = Report::new(recording(&[10.0, 90.0, 10.0], None), "run")
+            .unwrap()
+            .thresholds(cpu_limit(95.0))
+            .to_html();
+        assert!(html.contains("No process exceeded a threshold."));
+        assert!(html.contains("current config"));
+    }
+
+    #[test]
+    fn charts_disk_io() {
+        let html = Report::new(recording(&[1.0, 2.0], None), "run")
+            .unwrap()
+            .to_html();
+        assert!(html.contains("Disk I/O (KB/s) - nginx"));
+
+        let mut recording = recording(&[1.0, 2.0], None);
+        recording.samples.iter_mut().for_each(|s| s.read_kb_s = 0.0);
+        let html = Report::new(recording, "run").unwrap().to_html();
+        assert!(!html.contains("Disk I/O (KB/s) - nginx"));
+        assert!(html.contains("No disk I/O was recorded"));
+    }
+}
@@ -0,0 +1,383 @@
+//! Renders charts as PNG without system libraries. Shapes are rasterized at
+//! several times the size and averaged down for smooth edges; text uses a
+//! built-in 5x7 bitmap font.
+
+use color_eyre::{Result, eyre::bail};
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+use super::chart::{Anchor, Canvas, Rgb, TextStyle};
+
+/// Samples per pixel along each axis.
+const SUPERSAMPLE: usize = 4;
+/// Dash and gap length of dashed lines, in pixels.
+const DASH: (f64, f64) = (4.0, 3.0);
+
+/// Glyphs of ASCII 32 to 126: one byte per column, top row in the lowest bit.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // '#'
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x55, 0x22, 0x50], // '&'
+    [0x00, 0x05, 0x03, 0x00, 0x00], // "'"
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // ')'
+    [0x14, 0x08, 0x3e, 0x08, 0x14], // '*'
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // '+'
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // '0'
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // '1'
+    [0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // '3'
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // '6'
+    [0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // '9'
+    [0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+    [0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // '@'
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // 'A'
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // 'D'
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // 'F'
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // 'G'
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // 'H'
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // 'J'
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // 'M'
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // 'N'
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // 'O'
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // 'Q'
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // 'T'
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // 'U'
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // 'V'
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x07, 0x08, 0x70, 0x08, 0x07], // 'Y'
+    [0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\\'
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // 'f'
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // 'g'
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // 'i'
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // 'j'
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // 'l'
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // 'm'
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // 'p'
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // 'q'
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x20], // 's'
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // 't'
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // 'u'
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // 'v'
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // 'y'
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x08, 0x04, 0x08, 0x10, 0x08], // '~'
+];
+
+pub struct PngCanvas {
+    width: usize,
+    height: usize,
+    /// Supersampled pixels, row by row
+    pixels: Vec<Rgb>,
+}
+
+impl PngCanvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        Self {
+            width,
+            height,
+            pixels: vec![Rgb::WHITE; width * height * SUPERSAMPLE * SUPERSAMPLE],
+        }
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let stride = self.width * SUPERSAMPLE;
+        let samples = (SUPERSAMPLE * SUPERSAMPLE) as u32;
+        let mut rgb = Vec::with_capacity(self.width * self.height * 3);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut sum = [0u32; 3];
+                for sy in 0..SUPERSAMPLE {
+                    let row = (y * SUPERSAMPLE + sy) * stride + x * SUPERSAMPLE;
+                    for pixel in &self.pixels[row..row + SUPERSAMPLE] {
+                        sum[0] += pixel.0 as u32;
+                        sum[1] += pixel.1 as u32;
+                        sum[2] += pixel.2 as u32;
+                    }
+                }
+                rgb.extend(sum.map(|channel| (channel / samples) as u8));
+            }
+        }
+        encode(self.width as u32, self.height as u32, &rgb)
+    }
+
+    /// Fills the samples whose centers lie in the rectangle, given in pixels.
+    fn fill(&mut self, (x0, y0): (f64, f64), (x1, y1): (f64, f64), color: Rgb) {
+        let scale = SUPERSAMPLE as f64;
+        let columns = sample_range(
+            x0.min(x1) * scale,
+            x0.max(x1) * scale,
+            self.width * SUPERSAMPLE,
+        );
+        let rows = sample_range(
+            y0.min(y1) * scale,
+            y0.max(y1) * scale,
+            self.height * SUPERSAMPLE,
+        );
+        let stride = self.width * SUPERSAMPLE;
+        for row in rows {
+            for column in columns.clone() {
+                self.pixels[row * stride + column] = color;
+            }
+        }
+    }
+
+    fn segment(
+        &mut self,
+        from: (f64, f64),
+        to: (f64, f64),
+        color: Rgb,
+        width: f64,
+        dash_offset: Option<f64>,
+    ) {
+        let scale = SUPERSAMPLE as f64;
+        let (ax, ay) = (from.0 * scale, from.1 * scale);
+        let (dx, dy) = ((to.0 - from.0) * scale, (to.1 - from.1) * scale);
+        let length_squared = dx * dx + dy * dy;
+        let half = width * scale / 2.0;
+        let stride = self.width * SUPERSAMPLE;
+        let columns = sample_range(ax.min(ax + dx) - half, ax.max(ax + dx) + half, stride);
+        let rows = sample_range(
+            ay.min(ay + dy) - half,
+            ay.max(ay + dy) + half,
+            self.height * SUPERSAMPLE,
+        );
+        for row in rows {
+            for column in columns.clone() {
+                let (px, py) = (column as f64 + 0.5 - ax, row as f64 + 0.5 - ay);
+                let t = if length_squared > 0.0 {
+                    ((px * dx + py * dy) / length_squared).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let (ex, ey) = (px - t * dx, py - t * dy);
+                if ex * ex + ey * ey > half * half {
+                    continue;
+                }
+                if let Some(offset) = dash_offset {
+                    let along = offset + t * length_squared.sqrt() / scale;
+                    if along % (DASH.0 + DASH.1) >= DASH.0 {
+                        continue;
+                    }
+                }
+                self.pixels[row * stride + column] = color;
+            }
+        }
+    }
+}
+
+impl Canvas for PngCanvas {
+    fn polyline(&mut self, points: &[(f64, f64)], color: Rgb, width: f64, dashed: bool) {
+        let mut travelled = 0.0;
+        for pair in points.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            self.segment(from, to, color, width, dashed.then_some(travelled));
+            travelled += ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+        }
+    }
+
+    fn rect(
+        &mut self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        fill: Option<Rgb>,
+        stroke: Option<Rgb>,
+    ) {
+        if let Some(fill) = fill {
+            self.fill((x, y), (x + width, y + height), fill);
+        }
+        if let Some(stroke) = stroke {
+            let corners = [
+                (x, y),
+                (x + width, y),
+                (x + width, y + height),
+                (x, y + height),
+                (x, y),
+            ];
+            self.polyline(&corners, stroke, 1.0, false);
+        }
+    }
+
+    fn text(&mut self, x: f64, y: f64, text: &str, style: TextStyle) {
+        // Glyphs sit in 6x8 cells, including the spacing
+        let pixel = style.size / 8.0;
+        let glyphs: Vec<&[u8; 5]> = text
+            .chars()
+            .map(|c| match c {
+                ' '..='~' => &FONT[c as usize - 32],
+                _ => &FONT['?' as usize - 32],
+            })
+            .collect();
+        let width = (glyphs.len() as f64 * 6.0 - 1.0).max(0.0) * pixel;
+        let start = match style.anchor {
+            Anchor::Start => 0.0,
+            Anchor::Middle => -width / 2.0,
+            Anchor::End => -width,
+        };
+        let dot_width = if style.bold { pixel * 1.6 } else { pixel };
+        for (i, glyph) in glyphs.iter().enumerate() {
+            for (column, bits) in glyph.iter().enumerate() {
+                for row in (0..7).filter(|row| bits >> row & 1 == 1) {
+                    // Offsets along and across the text direction
+                    let along = start + (i * 6 + column) as f64 * pixel;
+                    let across = (row as f64 - 7.0) * pixel;
+                    if style.vertical {
+                        self.fill(
+                            (x + across, y - along - dot_width),
+                            (x + across + pixel, y - along),
+                            style.color,
+                        );
+                    } else {
+                        self.fill(
+                            (x + along, y + across),
+                            (x + along + dot_width, y + across + pixel),
+                            style.color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Indices of the samples whose centers lie between two supersampled
+/// coordinates, clipped to the image.
+fn sample_range(from: f64, to: f64, limit: usize) -> std::ops::Range<usize> {
+    let first = (from - 0.5).ceil().max(0.0) as usize;
+    let end = ((to - 0.5).floor() + 1.0).clamp(0.0, limit as f64) as usize;
+    first.min(end)..end
+}
+
+/// Encodes 8-bit RGB pixels as a PNG file, which can't be empty.
+fn encode(width: u32, height: u32, rgb: &[u8]) -> Result<Vec<u8>> {
+    if width == 0 || height == 0 {
+        bail!("cannot encode a {width}x{height} PNG, both sides must be at least 1 pixel");
+    }
+    if rgb.len() != width as usize * height as usize * 3 {
+        bail!("{} bytes are not {width}x{height} RGB pixels", rgb.len());
+    }
+    // Every row starts with its filter type, 0 for none
+    let mut raw = Vec::with_capacity(rgb.len() + height as usize);
+    for row in rgb.chunks(width as usize * 3) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend(width.to_be_bytes());
+    header.extend(height.to_be_bytes());
+    // 8 bits per channel, RGB, default compression, filtering and no interlacing
+    header.extend([8, 2, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &compress_to_vec_zlib(&raw, 6));
+    write_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+    png.extend(crc32(kind.iter().chain(data)).to_be_bytes());
+}
+
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789".iter()), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND".iter()), 0xae42_6082);
+    }
+
+    #[test]
+    fn encodes_a_valid_png() {
+        let rgb = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+        let png = encode(2, 2, &rgb).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 2]);
+        assert!(png.ends_with(&[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap();
+        let length = u32::from_be_bytes(png[idat - 4..idat].try_into().unwrap()) as usize;
+        let raw = miniz_oxide::inflate::decompress_to_vec_zlib(&png[idat + 4..idat + 4 + length])
+            .unwrap();
+        assert_eq!(raw, [0, 255, 0, 0, 0, 255, 0, 0, 0, 0, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn rejects_empty_images() {
+        assert!(encode(0, 10, &[]).is_err());
+        assert!(encode(10, 0, &[]).is_err());
+        assert!(PngCanvas::new(0, 0).finish().is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Renders charts as standalone SVG.
+
+use std::fmt::Write;
+
+use super::chart::{Anchor, Canvas, Rgb, TextStyle};
+
+pub struct SvgCanvas {
+    svg: String,
+}
+
+impl SvgCanvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            svg: format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif">"#
+            ),
+        }
+    }
+
+    pub fn finish(mut self) -> String {
+        self.svg.push_str("</svg>");
+        self.svg
+    }
+}
+
+impl Canvas for SvgCanvas {
+    fn polyline(&mut self, points: &[(f64, f64)], color: Rgb, width: f64, dashed: bool) {
+        if points.is_empty() {
+            return;
+        }
+        let mut path = String::new();
+        for (i, (x, y)) in points.iter().enumerate() {
+            let _ = write!(path, "{}{x:.1},{y:.1}", if i == 0 { "M" } else { " L" });
+        }
+        let _ = write!(
+            self.svg,
+            r#"<path d="{path}" fill="none" stroke="{}" stroke-width="{width}"{}/>"#,
+            color.hex(),
+            if dashed {
+                r#" stroke-dasharray="4 3""#
+            } else {
+                ""
+            }
+        );
+    }
+
+    fn rect(
+        &mut self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        fill: Option<Rgb>,
+        stroke: Option<Rgb>,
+    ) {
+        let _ = write!(
+            self.svg,
+            r#"<rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" fill="{}" stroke="{}"/>"#,
+            fill.map_or("none".to_string(), |c| c.hex()),
+            stroke.map_or("none".to_string(), |c| c.hex())
+        );
+    }
+
+    fn text(&mut self, x: f64, y: f64, text: &str, style: TextStyle) {
+        let anchor = match style.anchor {
+            Anchor::Start => "start",
+            Anchor::Middle => "middle",
+            Anchor::End => "end",
+        };
+        let position = if style.vertical {
+            format!(r#"transform="translate({x:.1} {y:.1}) rotate(-90)""#)
+        } else {
+            format!(r#"x="{x:.1}" y="{y:.1}""#)
+        };
+        let _ = write!(
+            self.svg,
+            r#"<text {position} font-size="{}" fill="{}" text-anchor="{anchor}"{}>{}</text>"#,
+            style.size,
+            style.color.hex(),
+            if style.bold {
+                r#" font-weight="bold""#
+            } else {
+                ""
+            },
+            escape(text)
+        );
+    }
+}
+
+/// Escapes text for use in XML or HTML.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
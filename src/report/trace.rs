@@ -0,0 +1,280 @@
+//! Chrome Trace Event export, for opening recordings in Perfetto or
+//! chrome://tracing next to application traces.
+//!
+//! Every program becomes a trace process with counter tracks for CPU,
+//! memory, threads and disk I/O; the last two only when they were sampled.
+//! Markers are global instant events; restarts and threshold alerts are
+//! instant events on the program's process.
+//!
+//! Timestamps are wall-clock microseconds since the Unix epoch when the
+//! recording knows when it started, so that the trace lines up with
+//! application traces, and count from the start of the recording otherwise.
+
+use color_eyre::{Result, eyre::WrapErr};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    config::Thresholds,
+    domain::history::{History, Metric},
+    recording::Recording,
+};
+
+use super::{alerts, label, metric_label};
+
+/// Trace process holding the markers, which belong to no program.
+const MARKER_PID: u32 = 0;
+
+#[derive(Serialize)]
+struct TraceFile {
+    #[serde(rename = "traceEvents")]
+    trace_events: Vec<TraceEvent>,
+    #[serde(rename = "displayTimeUnit")]
+    display_time_unit: &'static str,
+}
+
+#[derive(Serialize)]
+struct TraceEvent {
+    name: String,
+    /// Phase: `C` counter, `i` instant, `M` metadata
+    ph: &'static str,
+    /// Microseconds, see the module docs
+    ts: u64,
+    pid: u32,
+    tid: u32,
+    /// Scope of instant events: `g` global or `p` process
+    #[serde(skip_serializing_if = "Option::is_none")]
+    s: Option<&'static str>,
+    args: BTreeMap<&'static str, Value>,
+}
+
+impl TraceEvent {
+    fn new(name: impl Into<String>, ph: &'static str, ts: u64, pid: u32) -> Self {
+        Self {
+            name: name.into(),
+            ph,
+            ts,
+            pid,
+            tid: 0,
+            s: None,
+            args: BTreeMap::new(),
+        }
+    }
+
+    fn arg(mut self, name: &'static str, value: impl Into<Value>) -> Self {
+        self.args.insert(name, value.into());
+        self
+    }
+}
+
+/// A recording as Chrome Trace Event JSON.
+pub struct Trace {
+    history: History,
+    started: Option<SystemTime>,
+    thresholds: Thresholds,
+    /// Whether `thresholds` are the recording's own
+    recorded_thresholds: bool,
+}
+
+impl Trace {
+    pub fn new(mut recording: Recording) -> Result<Self> {
+        let thresholds = recording.thresholds.take();
+        Ok(Self {
+            recorded_thresholds: thresholds.is_some(),
+            thresholds: thresholds.unwrap_or_default(),
+            started: recording.started,
+            history: recording.into_history()?,
+        })
+    }
+
+    /// Limits whose crossings are added as alert events, for recordings
+    /// that don't store the ones they were made with.
+    pub fn thresholds(mut self, thresholds: Thresholds) -> Self {
+        if !self.recorded_thresholds {
+            self.thresholds = thresholds;
+        }
+        self
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("failed to create {}", parent.display()))?;
+        }
+        let mut file = BufWriter::new(
+            File::create(path).wrap_err_with(|| format!("failed to create {}", path.display()))?,
+        );
+        serde_json::to_writer(&mut file, &self.to_trace())?;
+        file.flush()
+            .wrap_err_with(|| format!("failed to write {}", path.display()))
+    }
+
+    fn to_trace(&self) -> TraceFile {
+        let origin = self
+            .started
+            .and_then(|started| started.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_micros() as u64);
+        let ts = |seconds: f64| origin + (seconds * 1_000_000.0).round() as u64;
+        let mut events = Vec::new();
+        if !self.history.markers().is_empty() {
+            events.push(process_name(MARKER_PID, "Markers"));
+        }
+        for marker in self.history.markers() {
+            let mut event = TraceEvent::new(
+                &marker.text,
+                "i",
+                ts(marker.elapsed.as_secs_f64()),
+                MARKER_PID,
+            );
+            event.s = Some("g");
+            events.push(event);
+        }
+
+        for (i, program) in self.history.programs().iter().enumerate() {
+            // Programs are numbered rather than keyed by PID, which changes
+            // on restarts and may repeat across hosts
+            let pid = i as u32 + 1;
+            events.push(process_name(pid, &label(program)));
+
+            // Recordings made before threads and disk I/O were sampled hold
+            // zeros, which are left out rather than drawn as flat tracks
+            let samples = || {
+                self.history
+                    .frames()
+                    .flat_map(|frame| frame.processes.iter())
+                    .filter(|p| p.same_program(program))
+            };
+            let with_threads = samples().any(|p| p.threads > 0);
+            let with_io = samples().any(|p| p.read_kb_s > 0.0 || p.write_kb_s > 0.0);
+
+            let mut last_pid = None;
+            for frame in self.history.frames() {
+                let Some(process) = frame.processes.iter().find(|p| p.same_program(program)) else {
+                    continue;
+                };
+                let ts = ts(frame.elapsed.as_secs_f64());
+                let local_pid = process.local_pid();
+                if last_pid.is_some_and(|last| last != local_pid) {
+                    let mut restart = TraceEvent::new("Restart", "i", ts, pid)
+                        .arg("previous_pid", last_pid)
+                        .arg("pid", local_pid);
+                    restart.s = Some("p");
+                    events.push(restart);
+                }
+                last_pid = Some(local_pid);
+                events.push(
+                    TraceEvent::new(metric_label(Metric::Cpu), "C", ts, pid)
+                        .arg("cpu", Metric::Cpu.value(process)),
+                );
+                events.push(
+                    TraceEvent::new(metric_label(Metric::Memory), "C", ts, pid)
+                        .arg("rss", Metric::Memory.value(process)),
+                );
+                if with_threads {
+                    events.push(
+                        TraceEvent::new(metric_label(Metric::Threads), "C", ts, pid)
+                            .arg("threads", process.threads),
+                    );
+                }
+                if with_io {
+                    events.push(
+                        TraceEvent::new("Disk I/O (KB/s)", "C", ts, pid)
+                            .arg("read", Metric::DiskRead.value(process))
+                            .arg("write", Metric::DiskWrite.value(process)),
+                    );
+                }
+            }
+
+            for alert in alerts(&self.history, program, &self.thresholds) {
+                let name = format!("{} above {}", metric_label(alert.metric), alert.limit);
+                let mut event = TraceEvent::new(name, "i", ts(alert.from), pid)
+                    .arg("threshold", alert.limit)
+                    .arg("peak", alert.peak)
+                    .arg("until_s", alert.to);
+                event.s = Some("p");
+                events.push(event);
+            }
+        }
+
+        TraceFile {
+            trace_events: events,
+            display_time_unit: "ms",
+        }
+    }
+}
+
+fn process_name(pid: u32, name: &str) -> TraceEvent {
+    TraceEvent::new("process_name", "M", 0, pid).arg("name", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::RecordedSample;
+    use std::time::Duration;
+
+    fn sample(elapsed_ms: u64, threads: u32, read_kb_s: f64) -> RecordedSample {
+        RecordedSample {
+            host: None,
+            name: "svc".to_string(),
+            pid: 42,
+            cpu_usage: 10.0,
+            memory_mb: 64.0,
+            threads,
+            read_kb_s,
+            write_kb_s: 0.0,
+            elapsed_ms,
+            group: None,
+        }
+    }
+
+    fn counters<'a>(trace: &'a TraceFile, name: &str) -> Vec<&'a TraceEvent> {
+        trace
+            .trace_events
+            .iter()
+            .filter(|e| e.ph == "C" && e.name == name)
+            .collect()
+    }
+
+    #[test]
+    fn timestamps_are_wall_clock_when_the_start_is_known() {
+        let recording = Recording {
+            samples: vec![sample(0, 4, 1.5), sample(1500, 5, 0.0)],
+            started: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            ..Recording::default()
+        };
+        let trace = Trace::new(recording).unwrap().to_trace();
+
+        let cpu = counters(&trace, "CPU %");
+        assert_eq!(cpu[0].ts, 1_700_000_000_000_000);
+        assert_eq!(cpu[1].ts, 1_700_000_001_500_000);
+
+        let threads = counters(&trace, "Threads");
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[1].args["threads"], 5);
+        let io = counters(&trace, "Disk I/O (KB/s)");
+        assert_eq!(io.len(), 2);
+        assert_eq!(io[0].args["read"], 1.5);
+        assert_eq!(io[0].args["write"], 0.0);
+    }
+
+    #[test]
+    fn leaves_out_tracks_that_were_not_sampled() {
+        let recording = Recording {
+            samples: vec![sample(0, 0, 0.0), sample(1500, 0, 0.0)],
+            ..Recording::default()
+        };
+        let trace = Trace::new(recording).unwrap().to_trace();
+
+        assert_eq!(counters(&trace, "CPU %")[1].ts, 1_500_000);
+        assert!(counters(&trace, "Threads").is_empty());
+        assert!(counters(&trace, "Disk I/O (KB/s)").is_empty());
+    }
+}
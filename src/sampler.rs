@@ -0,0 +1,506 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+use sysinfo::Pid;
+
+use crate::{
+    domain::{
+        process::{ProcessInfo, WatchTarget},
+        state::AppState,
+    },
+    source::MetricsSource,
+    utils::MAX_INTERVAL,
+};
+
+/// How often the process list is rescanned while some targets have no
+/// running process.
+pub const RESOLVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// CatZ's own resource usage.
+#[derive(Debug, Clone, Copy)]
+pub struct Overhead {
+    pub cpu_usage: f32,
+    pub memory_mb: f64,
+}
+/// How often CatZ measures its own CPU and memory usage.
+const OVERHEAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What the sampler thread should watch and how often.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub targets: Vec<WatchTarget>,
+    pub interval: Duration,
+    /// Per-process-name overrides from the config file
+    pub intervals: BTreeMap<String, Duration>,
+    /// End and interval of the running burst, if any
+    pub burst: Option<(Instant, Duration)>,
+}
+
+impl Schedule {
+    /// Samples every target at the same interval.
+    pub fn new(targets: Vec<WatchTarget>, interval: Duration) -> Self {
+        Self {
+            targets,
+            interval,
+            intervals: BTreeMap::new(),
+            burst: None,
+        }
+    }
+
+    pub(crate) fn from_state(state: &AppState) -> Self {
+        Self {
+            targets: state.watch_targets().to_vec(),
+            interval: state.interval,
+            intervals: state.config.intervals.clone(),
+            burst: state
+                .burst_until
+                .map(|until| (until, state.config.burst.interval)),
+        }
+    }
+
+    /// Sampling interval of a target: the burst interval while a burst is
+    /// running, otherwise the target's own or configured override. Intervals
+    /// from sessions and agent requests aren't checked when parsed, so they
+    /// are capped here to keep `Instant` arithmetic from overflowing.
+    fn interval_for(&self, target: &WatchTarget, now: Instant) -> Duration {
+        let interval = match self.burst {
+            Some((until, interval)) if now < until => interval,
+            _ => target
+                .interval
+                .or_else(|| self.intervals.get(&target.name).copied())
+                .unwrap_or(self.interval),
+        };
+        interval.min(MAX_INTERVAL)
+    }
+}
+
+/// Readings for the targets that were due at `taken_at`. Targets in
+/// `sampled_pids` without a reading have exited.
+#[derive(Debug)]
+pub struct Sample {
+    pub taken_at: Instant,
+    pub sampled_pids: Vec<Pid>,
+    pub processes: Vec<ProcessInfo>,
+}
+
+/// What the sampler thread sends back.
+#[derive(Debug)]
+pub enum SamplerEvent {
+    Sample(Sample),
+    /// CatZ's own usage, or `None` if the source can't see this process
+    Overhead(Option<Overhead>),
+}
+
+/// Handle to the sampler thread. The thread stops once the handle and the
+/// event receiver are dropped.
+pub struct SamplerHandle {
+    schedules: Sender<Schedule>,
+}
+
+impl SamplerHandle {
+    /// Starts sampling on a new thread, sending results into `events`.
+    pub fn spawn<E>(source: Box<dyn MetricsSource>, events: Sender<E>) -> Self
+    where
+        E: From<SamplerEvent> + Send + 'static,
+    {
+        let (schedules, receiver) = mpsc::channel();
+        thread::spawn(move || Sampler::new(source).run(&receiver, &events));
+        Self { schedules }
+    }
+
+    pub fn update(&self, schedule: Schedule) {
+        // The thread only stops when the app is shutting down
+        let _ = self.schedules.send(schedule);
+    }
+}
+
+/// Samples the watched processes, each on its own interval.
+///
+/// Only the PIDs that are due are read, so this never scans the full process
+/// list.
+struct Sampler {
+    source: Box<dyn MetricsSource>,
+    own_pid: Option<Pid>,
+    schedule: Option<Schedule>,
+    /// When each PID was last sampled
+    last_samples: HashMap<Pid, Instant>,
+    last_overhead: Option<Instant>,
+}
+
+impl Sampler {
+    fn new(source: Box<dyn MetricsSource>) -> Self {
+        Self {
+            own_pid: source.own_pid(),
+            source,
+            schedule: None,
+            last_samples: HashMap::new(),
+            last_overhead: None,
+        }
+    }
+
+    fn run<E: From<SamplerEvent>>(mut self, schedules: &Receiver<Schedule>, events: &Sender<E>) {
+        loop {
+            let timeout = self.next_due().saturating_duration_since(Instant::now());
+            match schedules.recv_timeout(timeout) {
+                Ok(schedule) => self.schedule = Some(schedule),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            for event in self.sample_due() {
+                if events.send(event.into()).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// When the next target or overhead measurement is due.
+    fn next_due(&self) -> Instant {
+        let now = Instant::now();
+        let overhead = self.last_overhead.map_or(now, |at| at + OVERHEAD_INTERVAL);
+        let Some(schedule) = &self.schedule else {
+            return overhead;
+        };
+        schedule
+            .targets
+            .iter()
+            .filter_map(|target| {
+                let pid = target.pid?;
+                Some(match self.last_samples.get(&pid) {
+                    Some(at) => *at + schedule.interval_for(target, now),
+                    None => now,
+                })
+            })
+            .fold(overhead, Instant::min)
+    }
+
+    /// Samples every target whose interval has elapsed.
+    fn sample_due(&mut self) -> Vec<SamplerEvent> {
+        let now = Instant::now();
+        let (targets, due): (Vec<WatchTarget>, Vec<WatchTarget>) = match &self.schedule {
+            Some(schedule) => (
+                schedule.targets.clone(),
+                schedule
+                    .targets
+                    .iter()
+                    .filter(|target| {
+                        target
+                            .pid
+                            .is_some_and(|pid| match self.last_samples.get(&pid) {
+                                Some(at) => now >= *at + schedule.interval_for(target, now),
+                                None => true,
+                            })
+                    })
+                    .cloned()
+                    .collect(),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        let overhead_due = self
+            .last_overhead
+            .is_none_or(|at| now >= at + OVERHEAD_INTERVAL);
+        if due.is_empty() && !overhead_due {
+            return Vec::new();
+        }
+
+        // Only the due targets are read, so each one's CPU and disk rates
+        // cover its own interval
+        let pids: Vec<Pid> = due
+            .iter()
+            .filter_map(|t| t.pid)
+            .chain(
+                self.own_pid
+                    .filter(|pid| overhead_due && !due.iter().any(|t| t.pid == Some(*pid))),
+            )
+            .collect();
+        // Sources without a PID of their own have nothing to read for the
+        // overhead alone, and a scripted source would skip a step
+        let mut readings = if pids.is_empty() {
+            Vec::new()
+        } else {
+            self.source.sample(&pids)
+        };
+        let taken_at = Instant::now();
+
+        let mut events = Vec::new();
+        if overhead_due {
+            events.push(SamplerEvent::Overhead(
+                readings
+                    .iter()
+                    .find(|r| Some(r.pid) == self.own_pid)
+                    .map(|own| Overhead {
+                        cpu_usage: own.cpu_usage,
+                        memory_mb: own.memory_mb,
+                    }),
+            ));
+            self.last_overhead = Some(taken_at);
+        }
+        if due.is_empty() {
+            return events;
+        }
+
+        let mut sampled_pids = Vec::new();
+        let mut processes = Vec::new();
+        for target in due {
+            let Some(pid) = target.pid else {
+                continue;
+            };
+            sampled_pids.push(pid);
+            // Don't retry an exited target before the main loop unbinds it
+            self.last_samples.insert(pid, taken_at);
+            // A reused PID belongs to a different program
+            let Some(i) = readings
+                .iter()
+                .position(|r| r.pid == pid && r.name == target.name)
+            else {
+                continue;
+            };
+            let reading = readings.swap_remove(i);
+            processes.push(
+                ProcessInfo {
+                    threads: reading.threads,
+                    read_kb_s: reading.read_kb_s,
+                    write_kb_s: reading.write_kb_s,
+                    ..ProcessInfo::new(
+                        target.name.clone(),
+                        pid,
+                        reading.cpu_usage,
+                        reading.memory_mb,
+                    )
+                }
+                .with_labels(&target),
+            );
+        }
+        self.last_samples
+            .retain(|pid, _| targets.iter().any(|t| t.pid == Some(*pid)));
+
+        events.push(SamplerEvent::Sample(Sample {
+            taken_at,
+            sampled_pids,
+            processes,
+        }));
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::{FakeSource, ProcessSample};
+    use std::sync::{Arc, Mutex};
+
+    const OWN_PID: u32 = 999;
+
+    /// Answers for every PID asked for, logging each call's PIDs.
+    #[derive(Clone, Default)]
+    struct LoggingSource {
+        calls: Arc<Mutex<Vec<Vec<u32>>>>,
+    }
+
+    impl LoggingSource {
+        fn take_calls(&self) -> Vec<Vec<u32>> {
+            std::mem::take(&mut self.calls.lock().unwrap())
+        }
+    }
+
+    impl MetricsSource for LoggingSource {
+        fn processes(&mut self) -> Vec<(Pid, String)> {
+            Vec::new()
+        }
+
+        fn sample(&mut self, pids: &[Pid]) -> Vec<ProcessSample> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(pids.iter().map(|pid| pid.as_u32()).collect());
+            pids.iter()
+                .map(|pid| ProcessSample {
+                    pid: *pid,
+                    name: format!("p{pid}"),
+                    cpu_usage: 1.0,
+                    memory_mb: 1.0,
+                    threads: 1,
+                    read_kb_s: 0.0,
+                    write_kb_s: 0.0,
+                })
+                .collect()
+        }
+
+        fn own_pid(&self) -> Option<Pid> {
+            Some(Pid::from_u32(OWN_PID))
+        }
+    }
+
+    fn target(pid: u32, interval: Option<Duration>) -> WatchTarget {
+        WatchTarget {
+            interval,
+            ..WatchTarget::new(format!("p{pid}"), Pid::from_u32(pid))
+        }
+    }
+
+    /// A sampler whose targets were all just sampled, as was the overhead.
+    fn started(targets: Vec<WatchTarget>) -> (Sampler, LoggingSource) {
+        let source = LoggingSource::default();
+        let mut sampler = Sampler::new(Box::new(source.clone()));
+        sampler.schedule = Some(Schedule::new(targets, Duration::from_secs(2)));
+        sampler.sample_due();
+        source.take_calls();
+        (sampler, source)
+    }
+
+    /// Moves the previous samples of `pids`, or of the overhead for
+    /// `OWN_PID`, back by `by`.
+    fn rewind(sampler: &mut Sampler, pids: &[u32], by: Duration) {
+        for pid in pids {
+            if *pid == OWN_PID {
+                sampler.last_overhead = sampler.last_overhead.map(|at| at - by);
+            } else if let Some(at) = sampler.last_samples.get_mut(&Pid::from_u32(*pid)) {
+                *at -= by;
+            }
+        }
+    }
+
+    fn sampled_pids(events: &[SamplerEvent]) -> Vec<u32> {
+        events
+            .iter()
+            .flat_map(|event| match event {
+                SamplerEvent::Sample(sample) => sample.sampled_pids.clone(),
+                SamplerEvent::Overhead(_) => Vec::new(),
+            })
+            .map(|pid| pid.as_u32())
+            .collect()
+    }
+
+    #[test]
+    fn reads_only_the_due_targets() {
+        let fast = target(100, Some(Duration::from_secs(1)));
+        let slow = target(200, Some(Duration::from_secs(60)));
+        let (mut sampler, source) = started(vec![fast, slow]);
+        assert!(sampler.sample_due().is_empty());
+        assert!(source.take_calls().is_empty());
+
+        rewind(&mut sampler, &[100], Duration::from_secs(1));
+        let events = sampler.sample_due();
+        assert_eq!(sampled_pids(&events), [100]);
+        assert_eq!(source.take_calls(), [[100]]);
+
+        // The overhead is read along with the due targets, or on its own
+        rewind(&mut sampler, &[100, 200, OWN_PID], Duration::from_secs(60));
+        let events = sampler.sample_due();
+        assert_eq!(sampled_pids(&events), [100, 200]);
+        assert_eq!(source.take_calls(), [[100, 200, OWN_PID]]);
+        rewind(&mut sampler, &[OWN_PID], Duration::from_secs(2));
+        let events = sampler.sample_due();
+        assert!(matches!(events[..], [SamplerEvent::Overhead(Some(_))]));
+        assert_eq!(source.take_calls(), [[OWN_PID]]);
+    }
+
+    #[test]
+    fn waits_for_the_earliest_due_target() {
+        let (mut sampler, _) = started(vec![
+            target(100, Some(Duration::from_secs(30))),
+            target(200, Some(Duration::from_secs(10))),
+        ]);
+        sampler.last_overhead = Some(Instant::now() + Duration::from_secs(60));
+        let wait = sampler.next_due() - Instant::now();
+        assert!(
+            wait > Duration::from_secs(9) && wait <= Duration::from_secs(10),
+            "{wait:?}"
+        );
+
+        // Targets without a running process aren't waited for
+        let (mut sampler, _) = started(vec![WatchTarget {
+            pid: None,
+            ..target(100, None)
+        }]);
+        sampler.last_overhead = Some(Instant::now());
+        let wait = sampler.next_due() - Instant::now();
+        assert!(wait > Duration::from_secs(1), "{wait:?}");
+    }
+
+    #[test]
+    fn picks_each_targets_interval() {
+        let mut schedule = Schedule::new(Vec::new(), Duration::from_secs(2));
+        schedule
+            .intervals
+            .insert("p100".to_string(), Duration::from_secs(5));
+        let now = Instant::now();
+        let configured = target(100, None);
+        let own = target(100, Some(Duration::from_secs(7)));
+        assert_eq!(
+            schedule.interval_for(&target(200, None), now),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            schedule.interval_for(&configured, now),
+            Duration::from_secs(5)
+        );
+        assert_eq!(schedule.interval_for(&own, now), Duration::from_secs(7));
+        let endless = target(100, Some(Duration::MAX));
+        assert_eq!(schedule.interval_for(&endless, now), MAX_INTERVAL);
+
+        // A running burst overrides them all, an ended one none
+        schedule.burst = Some((now + Duration::from_secs(1), Duration::from_millis(100)));
+        assert_eq!(schedule.interval_for(&own, now), Duration::from_millis(100));
+        schedule.burst = Some((now, Duration::from_millis(100)));
+        assert_eq!(schedule.interval_for(&own, now), Duration::from_secs(7));
+    }
+
+    #[test]
+    fn forgets_targets_that_are_no_longer_watched() {
+        let (mut sampler, source) = started(vec![target(100, None), target(200, None)]);
+        sampler.schedule = Some(Schedule::new(
+            vec![target(200, None)],
+            Duration::from_secs(2),
+        ));
+        rewind(&mut sampler, &[200], Duration::from_secs(2));
+        sampler.sample_due();
+        assert_eq!(source.take_calls(), [[200]]);
+        assert_eq!(
+            sampler.last_samples.keys().copied().collect::<Vec<_>>(),
+            [Pid::from_u32(200)]
+        );
+
+        // A target added later is due right away
+        sampler.schedule = Some(Schedule::new(
+            vec![target(200, None), target(300, None)],
+            Duration::from_secs(2),
+        ));
+        assert_eq!(sampled_pids(&sampler.sample_due()), [300]);
+    }
+
+    #[test]
+    fn scripts_advance_once_per_scheduled_sample() {
+        let mut source = FakeSource::parse(
+            r#"
+            [[processes]]
+            pid = 100
+            name = "nginx"
+            cpu = [1.0, 2.0, 3.0]
+            "#,
+        )
+        .unwrap();
+        let mut sampler = Sampler::new(Box::new(source.clone()));
+        let target = WatchTarget {
+            pid: Some(Pid::from(100)),
+            ..WatchTarget::new("nginx".to_string(), Pid::from(100))
+        };
+        sampler.schedule = Some(Schedule::new(vec![target], Duration::from_secs(60)));
+
+        let events = sampler.sample_due();
+        let Some(SamplerEvent::Sample(sample)) = events.last() else {
+            panic!("expected a sample, got {events:?}");
+        };
+        assert_eq!(sample.processes[0].cpu_usage, 1.0);
+
+        // Only the overhead is due, which a fixture has none of
+        sampler.last_overhead = None;
+        let events = sampler.sample_due();
+        assert!(matches!(events[..], [SamplerEvent::Overhead(None)]));
+
+        assert_eq!(source.sample(&[Pid::from(100)])[0].cpu_usage, 2.0);
+    }
+}
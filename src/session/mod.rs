@@ -0,0 +1,122 @@
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail, eyre},
+};
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf};
+use sysinfo::Pid;
+
+use crate::domain::{
+    column::{Column, SortOrder},
+    process::WatchTarget,
+    state::AppState,
+};
+
+pub const DEFAULT_SESSION: &str = "default";
+
+/// Watch targets and table layout, saved on exit and restored on start.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Session {
+    pub columns: Option<Vec<Column>>,
+    pub sort: SortOrder,
+    pub targets: Vec<WatchTarget>,
+}
+
+impl Session {
+    pub fn capture(state: &AppState) -> Self {
+        Self {
+            columns: Some(state.config.columns.clone()),
+            sort: state.sort,
+            targets: state
+                .watch_targets()
+                .iter()
+                .filter(|t| !t.from_watchlist)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Applies the session to the state, binding targets to running
+    /// processes by name.
+    pub fn restore(self, state: &mut AppState, running: &[(Pid, String)]) {
+        if let Some(columns) = self.columns.filter(|c| !c.is_empty()) {
+            state.config.columns = columns;
+        }
+        state.sort = self.sort;
+        let manager = state.process_manager();
+        for target in self.targets {
+            manager.add_target(target);
+        }
+        state.resolve_targets(running);
+    }
+
+    /// Loads a named session, or `None` if it was never saved.
+    pub fn load(name: &str) -> Result<Option<Self>> {
+        let path = path(name)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to read session {}", path.display()))?;
+        let session = toml::from_str(&content)
+            .wrap_err_with(|| format!("invalid session file {}", path.display()))?;
+        Ok(Some(session))
+    }
+
+    pub fn save(&self, name: &str) -> Result<PathBuf> {
+        let path = path(name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, toml::to_string_pretty(self)?)
+            .wrap_err_with(|| format!("failed to write session {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// `$XDG_STATE_HOME/catz/sessions`, or `~/.local/state/catz/sessions`.
+pub fn sessions_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(base.join("catz").join("sessions"))
+}
+
+fn path(name: &str) -> Result<PathBuf> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        bail!("invalid session name {name:?}: use letters, digits, '-', '_' and '.'");
+    }
+    let dir = sessions_dir().ok_or_else(|| eyre!("cannot locate the session directory"))?;
+    Ok(dir.join(format!("{name}.toml")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn leaves_out_watchlist_targets() {
+        let mut state = AppState::new(Config::default());
+        let manager = state.process_manager();
+        manager.add_process("nginx".to_string(), Pid::from(1));
+        manager.add_target(WatchTarget {
+            from_watchlist: true,
+            ..WatchTarget::new("redis".to_string(), Pid::from(2))
+        });
+
+        let session = Session::capture(&state);
+        let names: Vec<&str> = session.targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["nginx"]);
+    }
+}
@@ -0,0 +1,145 @@
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail},
+};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use sysinfo::Pid;
+
+use super::{MetricsSource, ProcessSample};
+
+/// Processes scripted by a fixture file. Every `sample` call moves the
+/// script one step forward; clones share the same script and position.
+///
+/// ```toml
+/// [[processes]]
+/// pid = 100
+/// name = "nginx"
+/// cpu = [5.0, 40.0, 12.5]   # one value per step, the last one repeats
+/// memory_mb = [120.0]
+/// threads = [4, 8]           # 0 when left out
+/// read_kb_s = [0.0, 512.0]  # disk I/O, 0 when left out
+/// write_kb_s = [64.0]
+/// start = 2                 # first step the process exists (default 0)
+/// exit = 6                  # step at which it is gone (default never)
+/// ```
+#[derive(Debug, Clone)]
+pub struct FakeSource {
+    script: Arc<Mutex<Script>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Script {
+    processes: Vec<FakeProcess>,
+    #[serde(skip)]
+    step: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FakeProcess {
+    pid: u32,
+    name: String,
+    #[serde(default)]
+    cpu: Vec<f32>,
+    #[serde(default)]
+    memory_mb: Vec<f64>,
+    #[serde(default)]
+    threads: Vec<u32>,
+    #[serde(default)]
+    read_kb_s: Vec<f64>,
+    #[serde(default)]
+    write_kb_s: Vec<f64>,
+    #[serde(default)]
+    start: usize,
+    exit: Option<usize>,
+}
+
+impl FakeProcess {
+    fn alive_at(&self, step: usize) -> bool {
+        step >= self.start && self.exit.is_none_or(|exit| step < exit)
+    }
+}
+
+impl FakeSource {
+    /// A script without processes, for when nothing should be sampled.
+    pub fn empty() -> Self {
+        Self {
+            script: Arc::new(Mutex::new(Script {
+                processes: Vec::new(),
+                step: 0,
+            })),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read fixture {}", path.display()))?;
+        Self::parse(&content).wrap_err_with(|| format!("invalid fixture {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let script: Script = toml::from_str(content)?;
+        for (i, process) in script.processes.iter().enumerate() {
+            if script.processes[..i].iter().any(|p| p.pid == process.pid) {
+                bail!("pid {} is listed more than once", process.pid);
+            }
+        }
+        Ok(Self {
+            script: Arc::new(Mutex::new(script)),
+        })
+    }
+}
+
+impl MetricsSource for FakeSource {
+    fn processes(&mut self) -> Vec<(Pid, String)> {
+        let script = self.script.lock().unwrap();
+        script
+            .processes
+            .iter()
+            .filter(|p| p.alive_at(script.step))
+            .map(|p| (Pid::from_u32(p.pid), p.name.clone()))
+            .collect()
+    }
+
+    fn sample(&mut self, pids: &[Pid]) -> Vec<ProcessSample> {
+        let mut script = self.script.lock().unwrap();
+        let step = script.step;
+        script.step += 1;
+        script
+            .processes
+            .iter()
+            .filter(|p| p.alive_at(step) && pids.contains(&Pid::from_u32(p.pid)))
+            .map(|p| ProcessSample {
+                pid: Pid::from_u32(p.pid),
+                name: p.name.clone(),
+                cpu_usage: value_at(&p.cpu, step - p.start),
+                memory_mb: value_at(&p.memory_mb, step - p.start),
+                threads: value_at(&p.threads, step - p.start),
+                read_kb_s: value_at(&p.read_kb_s, step - p.start),
+                write_kb_s: value_at(&p.write_kb_s, step - p.start),
+            })
+            .collect()
+    }
+
+    /// CatZ isn't part of the script, and reading its overhead would move
+    /// the script forward outside the schedule.
+    fn own_pid(&self) -> Option<Pid> {
+        None
+    }
+}
+
+/// The scripted value for a step, holding the last value once the script
+/// runs out.
+fn value_at<T: Copy + Default>(values: &[T], step: usize) -> T {
+    values
+        .get(step)
+        .or(values.last())
+        .copied()
+        .unwrap_or_default()
+}
@@ -0,0 +1,92 @@
+use std::{path::PathBuf, time::Duration};
+use sysinfo::Pid;
+
+use crate::utils::MAX_INTERVAL;
+
+mod fake;
+mod procfs;
+mod remote;
+mod system;
+
+pub use crate::agent::{Address, RemoteHost};
+pub use fake::FakeSource;
+pub use procfs::ProcfsSource;
+pub use remote::RemoteSource;
+pub use system::SysinfoSource;
+
+/// A reading of one process.
+#[derive(Debug, Clone)]
+pub struct ProcessSample {
+    pub pid: Pid,
+    pub name: String,
+    /// Percent of one CPU since the previous sample of this process
+    pub cpu_usage: f32,
+    pub memory_mb: f64,
+    /// 0 when the source can't tell
+    pub threads: u32,
+    /// Disk reads in KB per second since the previous sample of this process
+    pub read_kb_s: f64,
+    /// Disk writes in KB per second since the previous sample of this process
+    pub write_kb_s: f64,
+}
+
+/// How long the previous reading of a PID is kept. Watched PIDs are read
+/// at least once per [`MAX_INTERVAL`], so older ones are no longer watched.
+const FORGET_AFTER: Duration = MAX_INTERVAL.saturating_mul(2);
+
+/// KB read and written per second between two readings of total bytes
+/// read and written `elapsed` apart.
+fn io_rates(elapsed: Duration, before: (u64, u64), now: (u64, u64)) -> (f64, f64) {
+    let seconds = elapsed.as_secs_f64();
+    if seconds <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let rate = |before: u64, now: u64| now.saturating_sub(before) as f64 / 1024.0 / seconds;
+    (rate(before.0, now.0), rate(before.1, now.1))
+}
+
+/// Where process names and metrics come from.
+pub trait MetricsSource: Send {
+    /// Lists every running process with its name.
+    fn processes(&mut self) -> Vec<(Pid, String)>;
+
+    /// Reads the given processes. Processes that no longer exist are
+    /// left out. PIDs may be read at different intervals; rates cover the
+    /// time since the previous reading of the same PID.
+    fn sample(&mut self, pids: &[Pid]) -> Vec<ProcessSample>;
+
+    /// CatZ's own PID as seen by this source, used to report its overhead.
+    /// `None` when the processes live on another machine.
+    fn own_pid(&self) -> Option<Pid> {
+        sysinfo::get_current_pid().ok()
+    }
+
+    /// Whether PIDs from this source are live processes on this machine,
+    /// so they can be signalled and rescheduled. Scripted PIDs and those
+    /// from a copied `/proc` tree would hit unrelated local processes.
+    fn is_local(&self) -> bool {
+        false
+    }
+}
+
+/// Chooses the source used by the TUI; each thread opens its own.
+#[derive(Debug, Clone)]
+pub enum SourceKind {
+    Sysinfo,
+    /// A procfs tree, usually `/proc`
+    Procfs(PathBuf),
+    Fake(FakeSource),
+    /// One or more `catz agent`s, possibly on other machines
+    Remote(Vec<RemoteHost>),
+}
+
+impl SourceKind {
+    pub fn open(&self) -> Box<dyn MetricsSource> {
+        match self {
+            SourceKind::Sysinfo => Box::new(SysinfoSource::new()),
+            SourceKind::Procfs(root) => Box::new(ProcfsSource::new(root.clone())),
+            SourceKind::Fake(fake) => Box::new(fake.clone()),
+            SourceKind::Remote(hosts) => Box::new(RemoteSource::new(hosts.clone())),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use sysinfo::Pid;
+
+use super::{FORGET_AFTER, MetricsSource, ProcessSample, io_rates};
+
+/// Reads processes straight from a procfs tree. The root can point at a
+/// copy of `/proc`, e.g. one captured from another machine.
+pub struct ProcfsSource {
+    root: PathBuf,
+    clock_ticks: f64,
+    /// Readings of each PID at its previous sample
+    last: HashMap<Pid, Previous>,
+}
+
+struct Previous {
+    uptime: f64,
+    ticks: u64,
+    /// Total bytes read and written, unless `io` couldn't be read
+    io: Option<(u64, u64)>,
+}
+
+impl ProcfsSource {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            clock_ticks: clock_ticks(),
+            last: HashMap::new(),
+        }
+    }
+
+    fn uptime(&self) -> Option<f64> {
+        let content = fs::read_to_string(self.root.join("uptime")).ok()?;
+        content.split_whitespace().next()?.parse().ok()
+    }
+}
+
+impl MetricsSource for ProcfsSource {
+    fn processes(&mut self) -> Vec<(Pid, String)> {
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| {
+                let pid: usize = entry.ok()?.file_name().to_str()?.parse().ok()?;
+                let stat = read_stat(&self.root, pid)?;
+                Some((Pid::from(pid), stat.name))
+            })
+            .collect()
+    }
+
+    fn sample(&mut self, pids: &[Pid]) -> Vec<ProcessSample> {
+        let uptime = self.uptime();
+        let samples: Vec<ProcessSample> = pids
+            .iter()
+            .filter_map(|pid| {
+                let Some(stat) = read_stat(&self.root, pid.as_u32() as usize) else {
+                    self.last.remove(pid);
+                    return None;
+                };
+                let io = read_io(&self.root, pid.as_u32() as usize);
+                let (mut cpu_usage, mut read_kb_s, mut write_kb_s) = (0.0, 0.0, 0.0);
+                if let (Some(now), Some(previous)) = (uptime, self.last.get(pid))
+                    && now > previous.uptime
+                {
+                    let elapsed = now - previous.uptime;
+                    let seconds =
+                        stat.ticks.saturating_sub(previous.ticks) as f64 / self.clock_ticks;
+                    cpu_usage = (seconds / elapsed * 100.0) as f32;
+                    if let (Some(before), Some(io)) = (previous.io, io) {
+                        (read_kb_s, write_kb_s) =
+                            io_rates(Duration::from_secs_f64(elapsed), before, io);
+                    }
+                }
+                if let Some(uptime) = uptime {
+                    let ticks = stat.ticks;
+                    self.last.insert(*pid, Previous { uptime, ticks, io });
+                }
+                let status = read_status(&self.root, pid.as_u32() as usize).unwrap_or_default();
+                Some(ProcessSample {
+                    pid: *pid,
+                    name: stat.name,
+                    cpu_usage,
+                    memory_mb: status.rss_kb as f64 / 1024.0,
+                    threads: status.threads,
+                    read_kb_s,
+                    write_kb_s,
+                })
+            })
+            .collect();
+        if let Some(now) = uptime {
+            self.last
+                .retain(|_, previous| now - previous.uptime < FORGET_AFTER.as_secs_f64());
+        }
+        samples
+    }
+
+    /// CatZ only shows up in the live `/proc`, not in a copied tree.
+    fn own_pid(&self) -> Option<Pid> {
+        let live = fs::canonicalize(&self.root).is_ok_and(|root| root == Path::new("/proc"));
+        live.then(|| sysinfo::get_current_pid().ok()).flatten()
+    }
+}
+
+struct Stat {
+    name: String,
+    /// User plus system time in clock ticks
+    ticks: u64,
+}
+
+/// Parses `<root>/<pid>/stat`. The name is wrapped in parentheses and may
+/// itself contain spaces or parentheses, so fields are counted from the
+/// last `)`.
+fn read_stat(root: &Path, pid: usize) -> Option<Stat> {
+    let content = fs::read_to_string(root.join(pid.to_string()).join("stat")).ok()?;
+    let open = content.find('(')?;
+    let close = content.rfind(')')?;
+    let name = content.get(open + 1..close)?.to_string();
+    // Fields after the name start at field 3 (state); utime and stime are
+    // fields 14 and 15
+    let fields: Vec<&str> = content.get(close + 1..)?.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(Stat {
+        name,
+        ticks: utime + stime,
+    })
+}
+
+#[derive(Default)]
+pub(super) struct Status {
+    pub rss_kb: u64,
+    pub threads: u32,
+}
+
+/// Parses `<root>/<pid>/status`. Kernel threads have no `VmRSS`.
+pub(super) fn read_status(root: &Path, pid: usize) -> Option<Status> {
+    let content = fs::read_to_string(root.join(pid.to_string()).join("status")).ok()?;
+    let field = |name: &str| -> Option<&str> {
+        let line = content.lines().find(|line| line.starts_with(name))?;
+        line.split_whitespace().nth(1)
+    };
+    Some(Status {
+        rss_kb: field("VmRSS:").and_then(|kb| kb.parse().ok()).unwrap_or(0),
+        threads: field("Threads:").and_then(|n| n.parse().ok()).unwrap_or(0),
+    })
+}
+
+/// Total bytes read from and written to storage, from `<root>/<pid>/io`,
+/// which only the process's owner and root can read.
+fn read_io(root: &Path, pid: usize) -> Option<(u64, u64)> {
+    let content = fs::read_to_string(root.join(pid.to_string()).join("io")).ok()?;
+    let field = |name: &str| -> Option<u64> {
+        let line = content.lines().find(|line| line.starts_with(name))?;
+        line[name.len()..].trim().parse().ok()
+    };
+    Some((field("read_bytes:")?, field("write_bytes:")?))
+}
+
+#[cfg(unix)]
+fn clock_ticks() -> f64 {
+    // SAFETY: sysconf has no memory-safety preconditions
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as f64,
+        _ => 100.0,
+    }
+}
+
+#[cfg(not(unix))]
+fn clock_ticks() -> f64 {
+    100.0
+}
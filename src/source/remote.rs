@@ -0,0 +1,216 @@
+use std::thread;
+use sysinfo::Pid;
+
+use super::{MetricsSource, ProcessSample};
+use crate::{
+    agent::{
+        Client, RemoteHost,
+        protocol::{Request, Response},
+    },
+    domain::process::{MAX_HOSTS, host_pid, split_host_pid},
+};
+
+/// Reads processes from one or more `catz agent`s. PIDs are made unique
+/// across agents with [`host_pid`], numbering agents in the given order;
+/// processes whose PIDs don't fit are left out.
+///
+/// Connections are opened on first use and reopened after they drop; while
+/// an agent is unreachable none of its processes are reported. Agents are
+/// asked in parallel, so a slow one delays a sample by at most the client
+/// timeout rather than once per agent.
+pub struct RemoteSource {
+    hosts: Vec<(RemoteHost, Option<Client>)>,
+}
+
+impl RemoteSource {
+    /// At most [`MAX_HOSTS`] hosts fit in one table.
+    pub fn new(hosts: Vec<RemoteHost>) -> Self {
+        assert!(hosts.len() <= MAX_HOSTS, "too many hosts");
+        Self {
+            hosts: hosts.into_iter().map(|host| (host, None)).collect(),
+        }
+    }
+
+    /// Sends each host its request, if it has one, all at once and returns
+    /// the responses by host number.
+    fn request_all(
+        &mut self,
+        request: impl Fn(usize) -> Option<Request>,
+    ) -> Vec<(usize, Response)> {
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .hosts
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(host, (remote, client))| {
+                    let request = request(host)?;
+                    Some((host, scope.spawn(move || send(remote, client, &request))))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .filter_map(|(host, handle)| Some((host, handle.join().ok()??)))
+                .collect()
+        })
+    }
+}
+
+/// Sends a request on the host's connection, opening it first if needed.
+fn send(remote: &RemoteHost, client: &mut Option<Client>, request: &Request) -> Option<Response> {
+    if client.is_none() {
+        *client = Client::connect(&remote.address).ok();
+    }
+    let response = client.as_mut()?.request(request);
+    if response.is_err() {
+        *client = None;
+    }
+    response.ok()
+}
+
+impl MetricsSource for RemoteSource {
+    fn processes(&mut self) -> Vec<(Pid, String)> {
+        let mut running = Vec::new();
+        for (host, response) in self.request_all(|_| Some(Request::Processes)) {
+            if let Response::Processes { processes } = response {
+                running.extend(
+                    processes
+                        .into_iter()
+                        .filter_map(|p| Some((host_pid(host, p.pid)?, p.name))),
+                );
+            }
+        }
+        running
+    }
+
+    fn sample(&mut self, pids: &[Pid]) -> Vec<ProcessSample> {
+        let responses = self.request_all(|host| {
+            let pids: Vec<u32> = pids
+                .iter()
+                .map(|pid| split_host_pid(*pid))
+                .filter(|(h, _)| *h == host)
+                .map(|(_, pid)| pid)
+                .collect();
+            (!pids.is_empty()).then_some(Request::Sample { pids })
+        });
+        let mut samples = Vec::new();
+        for (host, response) in responses {
+            if let Response::Samples { samples: readings } = response {
+                samples.extend(readings.into_iter().filter_map(|reading| {
+                    Some(ProcessSample {
+                        pid: host_pid(host, reading.pid)?,
+                        ..ProcessSample::from(reading)
+                    })
+                }));
+            }
+        }
+        samples
+    }
+
+    fn own_pid(&self) -> Option<Pid> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread, time::Instant};
+
+    use super::*;
+    use crate::{
+        agent::{Address, Agent, Listener, TIMEOUT},
+        config::Config,
+        source::{FakeSource, SourceKind},
+    };
+
+    const FIXTURE: &str = r#"
+        [[processes]]
+        pid = 100
+        name = "nginx"
+        cpu = [5.0, 40.0]
+        memory_mb = [120.0]
+
+        [[processes]]
+        pid = 200
+        name = "postgres"
+        cpu = [1.0]
+        memory_mb = [300.0]
+    "#;
+
+    /// Starts an agent sampling the fixture on a free localhost port.
+    fn spawn_agent() -> RemoteHost {
+        let listener = Listener::bind(&Address::Tcp("127.0.0.1:0".to_string())).unwrap();
+        let address: Address = listener.address().parse().unwrap();
+        let source = SourceKind::Fake(FakeSource::parse(FIXTURE).unwrap());
+        let agent = Agent::new(Config::default(), source);
+        thread::spawn(move || agent.serve(vec![listener]));
+        RemoteHost {
+            name: "local".to_string(),
+            address,
+        }
+    }
+
+    #[test]
+    fn reads_processes_from_a_local_agent() {
+        let host = spawn_agent();
+        let mut client = Client::connect(&host.address).unwrap();
+        let mut source = RemoteSource::new(vec![host.clone(), host]);
+
+        let mut running = source.processes();
+        running.sort();
+        assert_eq!(
+            running,
+            [
+                (host_pid(0, 100).unwrap(), "nginx".to_string()),
+                (host_pid(0, 200).unwrap(), "postgres".to_string()),
+                (host_pid(1, 100).unwrap(), "nginx".to_string()),
+                (host_pid(1, 200).unwrap(), "postgres".to_string()),
+            ]
+        );
+
+        let nginx = host_pid(1, 100).unwrap();
+        let samples = source.sample(&[nginx]);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].pid, nginx);
+        assert_eq!(samples[0].name, "nginx");
+        assert_eq!(samples[0].memory_mb, 120.0);
+
+        let add = Request::Add {
+            pattern: "postgres".to_string(),
+            interval: None,
+        };
+        match client.request(&add).unwrap() {
+            Response::Targets { targets } => {
+                assert_eq!(targets.len(), 1);
+                assert_eq!(targets[0].target.name, "postgres");
+            }
+            response => panic!("unexpected response {response:?}"),
+        }
+        assert!(matches!(
+            client.request(&Request::Shutdown).unwrap(),
+            Response::Ok
+        ));
+    }
+
+    #[test]
+    fn silent_agents_time_out_together() {
+        // Accept connections but never answer
+        let listeners: Vec<TcpListener> = (0..3)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let mut source = RemoteSource::new(
+            listeners
+                .iter()
+                .map(|listener| RemoteHost {
+                    name: "silent".to_string(),
+                    address: Address::Tcp(listener.local_addr().unwrap().to_string()),
+                })
+                .collect(),
+        );
+
+        // Asked one after another, they would take three timeouts
+        let start = Instant::now();
+        assert!(source.processes().is_empty());
+        assert!(start.elapsed() < TIMEOUT * 2);
+        drop(listeners);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{collections::HashMap, time::Instant};
+use sysinfo::{Pid, ProcessRefreshKind, System};
+
+use super::{FORGET_AFTER, MetricsSource, ProcessSample, io_rates};
+
+/// Reads processes through `sysinfo`.
+///
+/// Every sampled PID gets a `System` of its own. Refreshing some PIDs of a
+/// `System` drops the others from it, and measures CPU usage since its
+/// previous refresh of any of them, so PIDs read at different intervals
+/// can't share one.
+#[derive(Default)]
+pub struct SysinfoSource {
+    /// Lists the running processes
+    sys: System,
+    sampled: HashMap<Pid, Sampled>,
+}
+
+#[derive(Default)]
+struct Sampled {
+    sys: System,
+    /// Time and total bytes read and written at the previous sample
+    last_io: Option<(Instant, u64, u64)>,
+}
+
+impl SysinfoSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MetricsSource for SysinfoSource {
+    fn processes(&mut self) -> Vec<(Pid, String)> {
+        self.sys
+            .refresh_processes_specifics(ProcessRefreshKind::new());
+        self.sys
+            .processes()
+            .iter()
+            .map(|(pid, process)| (*pid, process.name().to_string()))
+            .collect()
+    }
+
+    fn sample(&mut self, pids: &[Pid]) -> Vec<ProcessSample> {
+        let mut samples = Vec::new();
+        for pid in pids {
+            let sampled = self.sampled.entry(*pid).or_default();
+            sampled.sys.refresh_pids_specifics(
+                &[*pid],
+                ProcessRefreshKind::new()
+                    .with_cpu()
+                    .with_memory()
+                    .with_disk_usage(),
+            );
+            let now = Instant::now();
+            let Some(process) = sampled.sys.process(*pid) else {
+                self.sampled.remove(pid);
+                continue;
+            };
+            let disk = process.disk_usage();
+            let io = (disk.total_read_bytes, disk.total_written_bytes);
+            let (read_kb_s, write_kb_s) = sampled
+                .last_io
+                .replace((now, io.0, io.1))
+                .map_or((0.0, 0.0), |(before, read, written)| {
+                    io_rates(now - before, (read, written), io)
+                });
+            samples.push(ProcessSample {
+                pid: *pid,
+                name: process.name().to_string(),
+                cpu_usage: process.cpu_usage(),
+                memory_mb: process.memory() as f64 / 1024.0 / 1024.0,
+                threads: threads(*pid),
+                read_kb_s,
+                write_kb_s,
+            });
+        }
+        let now = Instant::now();
+        self.sampled.retain(|_, sampled| {
+            sampled
+                .last_io
+                .is_some_and(|(at, _, _)| now - at < FORGET_AFTER)
+        });
+        samples
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+}
+
+/// sysinfo only lists threads when refreshing every process, so they are
+/// counted in `/proc` where there is one.
+#[cfg(target_os = "linux")]
+fn threads(pid: Pid) -> u32 {
+    super::procfs::read_status(std::path::Path::new("/proc"), pid.as_u32() as usize)
+        .map_or(0, |status| status.threads)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn threads(_pid: Pid) -> u32 {
+    0
+}
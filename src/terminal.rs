@@ -1,5 +1,6 @@
 use color_eyre::Result;
 use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
@@ -8,21 +9,29 @@ use std::io::{self, stdout};
 
 pub struct TerminalHandler {
     pub terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    mouse: bool,
 }
 
 impl TerminalHandler {
-    pub fn new() -> Result<Self> {
+    /// Takes over the terminal, capturing the mouse if `mouse` is set.
+    pub fn new(mouse: bool) -> Result<Self> {
         enable_raw_mode()?;
         let mut stdout = stdout();
         execute!(stdout, EnterAlternateScreen)?;
+        if mouse {
+            execute!(stdout, EnableMouseCapture)?;
+        }
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
 
-        Ok(Self { terminal })
+        Ok(Self { terminal, mouse })
     }
 
     pub fn cleanup(&mut self) -> Result<()> {
         disable_raw_mode()?;
+        if self.mouse {
+            execute!(self.terminal.backend_mut(), DisableMouseCapture)?;
+        }
         execute!(self.terminal.backend_mut(), LeaveAlternateScreen)?;
         self.terminal.show_cursor()?;
         Ok(())
@@ -32,7 +41,7 @@ impl TerminalHandler {
 pub fn setup_panic_hook() -> Result<()> {
     ctrlc::set_handler(move || {
         let _ = disable_raw_mode();
-        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
         std::process::exit(0);
     })?;
     Ok(())
@@ -0,0 +1,51 @@
+use ratatui::{
+    prelude::*,
+    style::Style,
+    widgets::{Block, Borders, Clear, List, ListItem},
+};
+
+use crate::{
+    config::Theme,
+    domain::column::{Column, SortOrder},
+};
+
+pub fn render_column_picker(
+    frame: &mut Frame,
+    choices: &[(Column, bool)],
+    selected: usize,
+    sort: &SortOrder,
+    theme: &Theme,
+    area: Rect,
+) {
+    let items: Vec<ListItem> = choices
+        .iter()
+        .enumerate()
+        .map(|(i, (column, visible))| {
+            let sorted = match sort.column {
+                Some(c) if c == *column && sort.descending => " ↓",
+                Some(c) if c == *column => " ↑",
+                _ => "",
+            };
+            let content = format!(
+                "{} [{}] {}{}",
+                if i == selected { ">" } else { " " },
+                if *visible { "x" } else { " " },
+                column.header(),
+                sorted
+            );
+            let style = if i == selected {
+                Style::default().fg(theme.selection)
+            } else if *visible {
+                Style::default()
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            ListItem::new(content).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(Block::default().borders(Borders::ALL).title("Columns"));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(list, area);
+}
@@ -1,2 +1,7 @@
+pub mod column_picker;
+pub mod process_chart;
+pub mod process_detail;
 pub mod process_table;
 pub mod save_dialog;
+pub mod scrollbar;
+pub mod signal_menu;
@@ -0,0 +1,150 @@
+use ratatui::{
+    prelude::*,
+    style::{Color, Style},
+    symbols,
+    widgets::{
+        Axis, Block, Borders, Chart, Dataset, GraphType,
+        block::{Position, Title},
+    },
+};
+
+use crate::{
+    config::Theme,
+    domain::{
+        history::{History, Marker, Metric},
+        process::ProcessInfo,
+    },
+};
+
+pub fn render_process_charts(
+    frame: &mut Frame,
+    history: &History,
+    frame_limit: usize,
+    theme: &Theme,
+    process: &ProcessInfo,
+    area: Rect,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
+    render_metric_chart(
+        frame,
+        history,
+        frame_limit,
+        process,
+        Metric::Cpu,
+        theme,
+        chunks[0],
+    );
+    render_metric_chart(
+        frame,
+        history,
+        frame_limit,
+        process,
+        Metric::Memory,
+        theme,
+        chunks[1],
+    );
+}
+
+fn render_metric_chart(
+    frame: &mut Frame,
+    history: &History,
+    frame_limit: usize,
+    process: &ProcessInfo,
+    metric: Metric,
+    theme: &Theme,
+    area: Rect,
+) {
+    let (title, color) = match metric {
+        Metric::Cpu => ("CPU%", theme.cpu_chart),
+        Metric::Memory => ("Memory(MB)", theme.memory_chart),
+        Metric::Threads => ("Threads", theme.accent),
+        Metric::DiskRead => ("Read(KB/s)", theme.accent),
+        Metric::DiskWrite => ("Write(KB/s)", theme.accent),
+    };
+    let points = history.series(process.pid, metric, frame_limit);
+
+    let (x_min, x_max) = history
+        .time_range()
+        .map(|(start, end)| (start.as_secs_f64(), end.as_secs_f64()))
+        .unwrap_or((0.0, 1.0));
+    let x_max = x_max.max(x_min + 1.0);
+    let y_max = points.iter().map(|(_, y)| *y).fold(0.0, f64::max).max(1.0) * 1.1;
+
+    // Markers up to the last frame shown, drawn as vertical lines
+    let shown_until = frame_limit
+        .checked_sub(1)
+        .and_then(|last| history.frame(last.min(history.len().saturating_sub(1))))
+        .map(|frame| frame.elapsed);
+    let markers: Vec<&Marker> = history
+        .markers()
+        .iter()
+        .filter(|marker| shown_until.is_some_and(|until| marker.elapsed <= until))
+        .collect();
+    let marker_lines: Vec<[(f64, f64); 2]> = markers
+        .iter()
+        .map(|marker| {
+            let x = marker.elapsed.as_secs_f64().max(x_min);
+            [(x, 0.0), (x, y_max)]
+        })
+        .collect();
+
+    let mut datasets = vec![
+        Dataset::default()
+            .name(process.name.as_str())
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(color))
+            .data(&points),
+    ];
+    datasets.extend(marker_lines.iter().map(|line| {
+        Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(theme.accent))
+            .data(line)
+    }));
+
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("{} - {}", title, process.name));
+    // Names go in the bottom border; the legend has no room for them
+    if !markers.is_empty() {
+        let names = markers
+            .iter()
+            .map(|marker| format!("│{:.1}s {}", marker.elapsed.as_secs_f64(), marker.text))
+            .collect::<Vec<_>>()
+            .join(" ");
+        block = block.title(
+            Title::from(Span::styled(
+                format!(" {names} "),
+                Style::default().fg(theme.accent),
+            ))
+            .position(Position::Bottom),
+        );
+    }
+
+    let axis_style = Style::default().fg(Color::Gray);
+    let chart = Chart::new(datasets)
+        .block(block)
+        .x_axis(
+            Axis::default()
+                .style(axis_style)
+                .bounds([x_min, x_max])
+                .labels(vec![
+                    Span::raw(format!("{:.0}s", x_min)),
+                    Span::raw(format!("{:.0}s", x_max)),
+                ]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(axis_style)
+                .bounds([0.0, y_max])
+                .labels(vec![Span::raw("0"), Span::raw(format!("{:.1}", y_max))]),
+        );
+
+    frame.render_widget(chart, area);
+}
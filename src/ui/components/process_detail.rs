@@ -0,0 +1,82 @@
+use ratatui::{
+    prelude::*,
+    style::{Color, Style},
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::domain::{
+    control::{self, SchedulingInfo},
+    process::ProcessInfo,
+};
+
+pub fn render_process_detail(
+    frame: &mut Frame,
+    process: &ProcessInfo,
+    info: Option<&SchedulingInfo>,
+    input: Option<(&str, &str)>,
+    area: Rect,
+) {
+    let unavailable = || "unavailable".to_string();
+    let label = |text: &'static str| Span::styled(text, Style::default().fg(Color::Yellow));
+
+    let mut lines = vec![
+        Line::from(vec![
+            label("Name:        "),
+            Span::raw(process.name.as_str()),
+        ]),
+        Line::from(vec![
+            label("PID:         "),
+            Span::raw(match &process.host {
+                Some(host) => format!("{} on {host}", process.local_pid()),
+                None => process.pid.to_string(),
+            }),
+        ]),
+        Line::from(vec![
+            label("CPU%:        "),
+            Span::raw(format!("{:.1}", process.cpu_usage)),
+        ]),
+        Line::from(vec![
+            label("Memory(MB):  "),
+            Span::raw(format!("{:.1}", process.memory_mb)),
+        ]),
+        Line::from(vec![
+            label("Nice:        "),
+            Span::raw(
+                info.and_then(|i| i.nice)
+                    .map(|n| n.to_string())
+                    .unwrap_or_else(unavailable),
+            ),
+        ]),
+        Line::from(vec![
+            label("Policy:      "),
+            Span::raw(
+                info.and_then(|i| i.policy)
+                    .map(str::to_string)
+                    .unwrap_or_else(unavailable),
+            ),
+        ]),
+        Line::from(vec![
+            label("Allowed CPUs:"),
+            Span::raw(" "),
+            Span::raw(
+                info.and_then(|i| i.affinity.as_deref())
+                    .map(control::format_cpu_list)
+                    .unwrap_or_else(unavailable),
+            ),
+        ]),
+    ];
+
+    if let Some((prompt, buffer)) = input {
+        lines.push(Line::from(vec![
+            Span::styled(prompt, Style::default().fg(Color::Cyan)),
+            Span::raw(format!("{}_", buffer)),
+        ]));
+    }
+
+    let detail = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Details - {}", process.name)),
+    );
+    frame.render_widget(detail, area);
+}
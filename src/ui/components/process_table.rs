@@ -1,60 +1,263 @@
 use ratatui::{
     prelude::*,
-    style::{Color, Style},
-    widgets::{Block, Borders, Cell, Row, Table},
+    style::Style,
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
 };
 
-use crate::domain::process::ProcessInfo;
+use super::scrollbar::render_scrollbar;
+use crate::{
+    config::Config,
+    domain::{
+        column::{Column, SortOrder},
+        filter::Filter,
+        process::{HostTotal, ProcessInfo},
+    },
+};
 
+/// Renders the rows with the one selected in `table_state` highlighted,
+/// scrolled so that it stays on screen.
 pub fn render_process_table(
     frame: &mut Frame,
     processes: &[ProcessInfo],
-    selected_index: usize,
+    sort: &SortOrder,
+    filter: Option<&Filter>,
+    config: &Config,
     area: Rect,
+    table_state: &mut TableState,
 ) {
-    let header = Row::new(vec![
-        Cell::from("Name").style(Style::default().fg(Color::Yellow)),
-        Cell::from("PID").style(Style::default().fg(Color::Yellow)),
-        Cell::from("CPU%").style(Style::default().fg(Color::Yellow)),
-        Cell::from("Memory(MB)").style(Style::default().fg(Color::Yellow)),
-    ])
+    if processes.is_empty() {
+        table_state.select(None);
+    }
+    let selected_index = table_state.selected();
+    let theme = &config.theme;
+    let mut details = Vec::new();
+    if let Some(sort) = sort.describe() {
+        details.push(format!("sorted by {sort}"));
+    }
+    if let Some(filter) = filter {
+        let matches = processes.iter().filter(|p| filter.matches(p)).count();
+        details.push(format!(
+            "/{}: {matches} of {} match",
+            filter.text,
+            processes.len()
+        ));
+    }
+    let title = if details.is_empty() {
+        "Processes".to_string()
+    } else {
+        format!("Processes ({})", details.join(", "))
+    };
+    let header = Row::new(
+        config
+            .columns
+            .iter()
+            .map(|column| {
+                let mut header = column.header().to_string();
+                if sort.column == Some(*column) {
+                    header.push_str(if sort.descending { " ↓" } else { " ↑" });
+                }
+                aligned(*column, header).style(Style::default().fg(theme.accent))
+            })
+            .collect::<Vec<_>>(),
+    )
     .height(1);
 
-    let table = if processes.is_empty() {
-        Table::new(
+    let (table, row_count) = if processes.is_empty() {
+        let table = Table::new(
             vec![Row::new(vec![
-                Cell::from("No processes monitored. Press 'a' to add a process.")
-                    .style(Style::default().fg(Color::Yellow)),
+                Cell::from(format!(
+                    "No processes monitored. Press '{}' to add a process.",
+                    config.keybindings.add
+                ))
+                .style(Style::default().fg(theme.accent)),
             ])],
             [Constraint::Percentage(100)],
-        )
+        );
+        (table, 1)
     } else {
-        let rows = processes.iter().enumerate().map(|(i, process)| {
-            let style = if i == selected_index {
-                Style::default().fg(Color::Yellow)
-            } else {
-                Style::default()
-            };
-            Row::new(vec![
-                Cell::from(process.name.as_str()).style(style),
-                Cell::from(format!("{:>5.1}", process.pid)).style(style),
-                Cell::from(format!("{:>5.1}", process.cpu_usage)).style(style),
-                Cell::from(format!("{:.1}", process.memory_mb)).style(style),
-            ])
+        let mut rows: Vec<Row> = processes
+            .iter()
+            .enumerate()
+            .map(|(i, process)| {
+                // Matches stand out and the other rows fade while filtering
+                let style = match filter.map(|f| f.matches(process)) {
+                    Some(true) => Style::default()
+                        .fg(theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                    Some(false) => Style::default().fg(Color::DarkGray),
+                    None => Style::default(),
+                };
+                let style = if Some(i) == selected_index {
+                    style.fg(theme.selection)
+                } else {
+                    style
+                };
+                Row::new(
+                    config
+                        .columns
+                        .iter()
+                        .map(|column| {
+                            let cell = render_cell(*column, process);
+                            if exceeds_threshold(*column, process, config) {
+                                cell.style(style.fg(theme.warning))
+                            } else {
+                                cell.style(style)
+                            }
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .height(1)
+            })
+            .collect();
+
+        // Totals of programs watched on several hosts, below the processes
+        let totals = HostTotal::across_hosts(processes);
+        if !totals.is_empty() {
+            rows.push(Row::new(vec![Cell::from("")]).height(1));
+        }
+        rows.extend(totals.iter().map(|total| {
+            Row::new(
+                config
+                    .columns
+                    .iter()
+                    .map(|column| render_total_cell(*column, total))
+                    .collect::<Vec<_>>(),
+            )
+            .style(Style::default().fg(theme.accent))
             .height(1)
-        });
-
-        Table::new(rows.collect::<Vec<_>>(), [
-            Constraint::Percentage(40), // Name
-            Constraint::Percentage(20), // PID
-            Constraint::Percentage(20), // CPU%
-            Constraint::Percentage(20), // Memory(MB)
-        ])
-        .header(header)
+        }));
+
+        let count = rows.len();
+        (
+            Table::new(rows, widths(&config.columns)).header(header),
+            count,
+        )
     };
 
-    frame.render_widget(
-        table.block(Block::default().borders(Borders::ALL).title("Processes")),
+    // The host totals follow the last process, so selecting it scrolls to
+    // the end of the table, even if that takes the selection off the top
+    let rows_area = rows_area(area);
+    if !processes.is_empty() && selected_index == Some(processes.len() - 1) {
+        *table_state.offset_mut() = row_count.saturating_sub(rows_area.height as usize);
+        // Table scrolls back to its selected row, so point it at the first
+        // row on screen instead. Rows are styled above, so this doesn't show
+        if selected_index < Some(table_state.offset()) {
+            table_state.select(Some(table_state.offset()));
+        }
+    }
+    frame.render_stateful_widget(
+        table.block(Block::default().borders(Borders::ALL).title(title)),
         area,
+        table_state,
     );
+    render_scrollbar(frame, row_count, table_state.offset(), rows_area);
+}
+
+/// The part of the table's area that rows are drawn in, below the header.
+pub fn rows_area(area: Rect) -> Rect {
+    let inner = area.inner(&Margin::new(1, 1));
+    Rect {
+        y: inner.y + 1,
+        height: inner.height.saturating_sub(1),
+        ..inner
+    }
+}
+
+/// Shares the table width between columns by their weight.
+fn widths(columns: &[Column]) -> Vec<Constraint> {
+    let total_weight: u16 = columns.iter().map(Column::weight).sum();
+    columns
+        .iter()
+        .map(|column| Constraint::Ratio(column.weight() as u32, total_weight as u32))
+        .collect()
+}
+
+/// The column whose header is at a terminal position within the table's
+/// area, laid out the way [`Table`] does.
+pub fn header_column_at(columns: &[Column], area: Rect, x: u16, y: u16) -> Option<Column> {
+    let inner = area.inner(&Margin::new(1, 1));
+    if y != inner.y || inner.height == 0 {
+        return None;
+    }
+    let cells = Layout::horizontal(widths(columns))
+        .spacing(1)
+        .split(Rect::new(inner.x, inner.y, inner.width, 1));
+    columns
+        .iter()
+        .zip(cells.iter())
+        .find(|(_, cell)| (cell.x..cell.x + cell.width).contains(&x))
+        .map(|(column, _)| *column)
+}
+
+/// Numbers are right-aligned under their header.
+fn aligned(column: Column, content: String) -> Cell<'static> {
+    let alignment = if column.is_numeric() {
+        Alignment::Right
+    } else {
+        Alignment::Left
+    };
+    Cell::from(Text::from(content).alignment(alignment))
+}
+
+fn render_cell(column: Column, process: &ProcessInfo) -> Cell<'static> {
+    aligned(column, column.format(process))
+}
+
+fn render_total_cell(column: Column, total: &HostTotal) -> Cell<'static> {
+    let content = match column {
+        Column::Name => format!("Σ {}", total.name),
+        Column::Host => format!("{} hosts", total.hosts),
+        Column::Cpu => format!("{:.1}", total.cpu_usage),
+        Column::Memory => format!("{:.1}", total.memory_mb),
+        Column::Pid | Column::Group | Column::Threads | Column::Read | Column::Write => {
+            String::new()
+        }
+    };
+    aligned(column, content)
+}
+
+fn exceeds_threshold(column: Column, process: &ProcessInfo, config: &Config) -> bool {
+    match column {
+        Column::Cpu => config
+            .thresholds
+            .cpu
+            .is_some_and(|limit| process.cpu_usage > limit),
+        Column::Memory => config
+            .thresholds
+            .memory_mb
+            .is_some_and(|limit| process.memory_mb > limit),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_header_under_the_cursor() {
+        // Name takes twice the width of the others: 2/4 of the 78 inner
+        // columns, then a column of spacing before each following cell
+        let columns = [Column::Name, Column::Pid, Column::Cpu];
+        let area = Rect::new(0, 0, 80, 10);
+        assert_eq!(header_column_at(&columns, area, 1, 1), Some(Column::Name));
+        assert_eq!(header_column_at(&columns, area, 30, 1), Some(Column::Name));
+        assert_eq!(header_column_at(&columns, area, 50, 1), Some(Column::Pid));
+        assert_eq!(header_column_at(&columns, area, 78, 1), Some(Column::Cpu));
+    }
+
+    #[test]
+    fn ignores_clicks_outside_the_header() {
+        let columns = [Column::Name, Column::Pid];
+        let area = Rect::new(0, 0, 80, 10);
+        // The top border, the first row, the left border and a table
+        // with no room for rows
+        assert_eq!(header_column_at(&columns, area, 10, 0), None);
+        assert_eq!(header_column_at(&columns, area, 10, 2), None);
+        assert_eq!(header_column_at(&columns, area, 0, 1), None);
+        assert_eq!(
+            header_column_at(&columns, Rect::new(0, 0, 80, 2), 10, 1),
+            None
+        );
+    }
 }
@@ -3,30 +3,9 @@ use ratatui::{
     style::{Color, Style},
     widgets::{Block, Borders, Clear, Paragraph},
 };
-use std::fs::File;
-use std::io::Write;
+use std::path::Path;
 
-use crate::domain::process::ProcessInfo;
-
-pub fn save_stats_to_csv(
-    stats: &[(String, Vec<ProcessInfo>)],
-    filename: &str,
-) -> std::io::Result<()> {
-    let mut file = File::create(filename)?;
-    writeln!(file, "Process Name,PID,CPU %,Memory (MB)")?;
-
-    for (name, data) in stats {
-        for process_info in data {
-            writeln!(
-                file,
-                "{},{},{:.2},{:.2}",
-                name, process_info.pid, process_info.cpu_usage, process_info.memory_mb
-            )?;
-        }
-    }
-
-    Ok(())
-}
+use crate::{recording::RecordingFormat, report};
 
 pub fn render_save_dialog(frame: &mut Frame, filename: &str, area: Rect) {
     let popup = Paragraph::new(format!(
@@ -39,8 +18,14 @@ pub fn render_save_dialog(frame: &mut Frame, filename: &str, area: Rect) {
             .title("Save Statistics"),
     );
 
-    let error_msg = if !filename.ends_with(".csv") && !filename.is_empty() {
-        Paragraph::new("Filename must end with .csv").style(Style::default().fg(Color::Red))
+    // File names without an extension get the configured export format
+    let path = Path::new(filename);
+    let error_msg = if path.extension().is_some()
+        && RecordingFormat::from_path(path).is_none()
+        && !report::is_report_path(path)
+    {
+        Paragraph::new("Filename must end with .csv, .json, .ndjson, .db or .html (report)")
+            .style(Style::default().fg(Color::Red))
     } else {
         Paragraph::new("")
     };
@@ -62,8 +47,8 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_width = (r.width * percent_x / 100).max(min_width);
     let popup_height = (r.height * percent_y / 100).max(min_height);
 
-    let popup_x = ((r.width - popup_width) / 2).max(0);
-    let popup_y = ((r.height - popup_height) / 2).max(0);
+    let popup_x = r.width.saturating_sub(popup_width) / 2;
+    let popup_y = r.height.saturating_sub(popup_height) / 2;
 
     Rect::new(
         r.x + popup_x,
@@ -0,0 +1,26 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Scrollbar, ScrollbarOrientation, ScrollbarState},
+};
+
+/// Draws a scrollbar over the right border next to `area`, the rows of a
+/// list, when `rows` don't fit in it. `offset` is the first row on screen.
+pub fn render_scrollbar(frame: &mut Frame, rows: usize, offset: usize, area: Rect) {
+    let visible = area.height as usize;
+    if rows <= visible {
+        return;
+    }
+    // One position per possible offset, so the thumb reaches the bottom
+    // when the last row is on screen
+    let mut scrollbar = ScrollbarState::new(rows - visible + 1)
+        .viewport_content_length(visible)
+        .position(offset);
+    frame.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight),
+        Rect {
+            width: area.width + 1,
+            ..area
+        },
+        &mut scrollbar,
+    );
+}
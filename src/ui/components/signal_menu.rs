@@ -0,0 +1,56 @@
+use ratatui::{
+    prelude::*,
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
+};
+
+use crate::domain::{control::Signal, process::ProcessInfo};
+
+pub fn render_signal_menu(frame: &mut Frame, target: &ProcessInfo, selected: usize, area: Rect) {
+    let items: Vec<ListItem> = Signal::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, signal)| {
+            let content = format!("{:<8} {}", signal.name(), signal.description());
+            let style = if i == selected {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            ListItem::new(if i == selected {
+                format!("> {}", content)
+            } else {
+                format!("  {}", content)
+            })
+            .style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Send signal to {} ({})", target.name, target.pid)),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(list, area);
+}
+
+pub fn render_signal_confirm(frame: &mut Frame, target: &ProcessInfo, signal: Signal, area: Rect) {
+    let prompt = Paragraph::new(format!(
+        "Send {} to {} ({})?\n(y/Enter to confirm, n/q to cancel)",
+        signal.name(),
+        target.name,
+        target.pid
+    ))
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Confirm")
+            .style(Style::default().fg(Color::Red)),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(prompt, area);
+}
@@ -2,78 +2,291 @@ pub mod components;
 
 use ratatui::{
     prelude::*,
-    style::{Color, Style},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    style::Style,
+    widgets::{
+        Block, Borders, HighlightSpacing, List, ListItem, ListState, Paragraph, TableState, Wrap,
+        block::Title,
+    },
 };
 
-use crate::domain::state::{AppMode, AppState};
-use components::{process_table, save_dialog};
+use crate::domain::{
+    column::Column,
+    control::Signal,
+    state::{AppMode, AppState},
+};
+use components::{
+    column_picker, process_chart, process_detail, process_table, save_dialog,
+    scrollbar::render_scrollbar, signal_menu,
+};
+
+/// Height of the per-process chart panel below the table.
+const CHART_HEIGHT: u16 = 12;
+/// Height of the detail panel, including the input prompt line.
+const DETAIL_HEIGHT: u16 = 10;
 
-pub fn render(frame: &mut Frame, state: &AppState) {
-    let main_layout = Layout::default()
+/// Scroll positions kept between frames, so a list only scrolls when its
+/// selection would otherwise leave the screen.
+#[derive(Debug, Default)]
+pub struct ViewState {
+    table: TableState,
+    picker: ListState,
+    /// Rows of the table or process list on screen, which PageUp and
+    /// PageDown move by
+    pub page_rows: usize,
+}
+
+fn main_layout(size: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3), // Title
             Constraint::Min(1),    // Main content
             Constraint::Length(1), // Status line
         ])
-        .split(frame.size());
+        .split(size)
+}
+
+/// Areas of the process table and, with a process selected, its chart.
+fn table_layout(state: &AppState, area: Rect) -> (Rect, Option<Rect>) {
+    let (history, _) = state.chart_history();
+    if history.is_empty() || state.selected_process_info().is_none() {
+        return (area, None);
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(CHART_HEIGHT)])
+        .split(area);
+    (chunks[0], Some(chunks[1]))
+}
+
+/// The table column whose header is at a terminal position, if the
+/// process table is on screen.
+pub fn header_column_at(state: &AppState, size: Rect, x: u16, y: u16) -> Option<Column> {
+    if !matches!(
+        state.mode,
+        AppMode::Normal | AppMode::Stats | AppMode::Replay
+    ) {
+        return None;
+    }
+    let (table_area, _) = table_layout(state, main_layout(size)[1]);
+    process_table::header_column_at(&state.config.columns, table_area, x, y)
+}
+
+pub fn render(frame: &mut Frame, state: &AppState, view: &mut ViewState) {
+    let main_layout = main_layout(frame.size());
 
     render_title(frame, state, main_layout[0]);
-    render_main_content(frame, state, main_layout[1]);
+    render_main_content(frame, state, view, main_layout[1]);
     render_status_line(frame, state, main_layout[2]);
 
     if state.mode == AppMode::SavePrompt {
         let area = save_dialog::centered_rect(60, 20, frame.size());
         save_dialog::render_save_dialog(frame, &state.save_filename, area);
     }
+
+    if state.mode == AppMode::Columns {
+        let choices = state.column_choices();
+        let area = save_dialog::centered_rect(40, 50, frame.size());
+        column_picker::render_column_picker(
+            frame,
+            &choices,
+            state.selected_column,
+            &state.sort,
+            &state.config.theme,
+            area,
+        );
+    }
+
+    if let Some(target) = &state.target_process {
+        match state.mode {
+            AppMode::SignalMenu => {
+                let area = save_dialog::centered_rect(50, 40, frame.size());
+                signal_menu::render_signal_menu(frame, target, state.selected_signal, area);
+            }
+            AppMode::ConfirmSignal => {
+                let area = save_dialog::centered_rect(50, 20, frame.size());
+                let signal = Signal::ALL[state.selected_signal];
+                signal_menu::render_signal_confirm(frame, target, signal, area);
+            }
+            _ => {}
+        }
+    }
 }
 
 fn render_title(frame: &mut Frame, state: &AppState, area: Rect) {
+    let keys = &state.config.keybindings;
+    let burst = match state.burst_remaining() {
+        Some(remaining) => format!(" - BURST {}s left", remaining.as_millis().div_ceil(1000)),
+        None => String::new(),
+    };
+    let name = match state.hosts.as_slice() {
+        [] => "CatZ".to_string(),
+        [host] => format!("CatZ @ {host}"),
+        hosts => format!("CatZ @ {} hosts", hosts.len()),
+    };
     let title_text = match state.mode {
         AppMode::InputPattern => {
             format!("CatZ - Adding pattern: {}_", state.input_buffer)
         }
         AppMode::SelectProcess => format!(
-            "CatZ - Select process to monitor ({} matches)\n(↑↓:select, Enter:confirm, q:cancel)",
+            "CatZ - Select process to monitor ({} matches)\n(↑↓ PgUp/PgDn Home/End:select, Enter:confirm, q:cancel)",
             state.candidate_processes.len()
         ),
-        AppMode::Stats => "CatZ - STATS MODE (s:stop stats, q:quit)".to_string(),
-        _ => "CatZ\n(a:add process, s:stats, q:quit)".to_string(),
+        AppMode::AliasInput | AppMode::GroupInput => format!(
+            "CatZ - {} for {}: {}_",
+            if state.mode == AppMode::AliasInput {
+                "Alias"
+            } else {
+                "Group"
+            },
+            state
+                .selected_process_info()
+                .map(|p| p.name.as_str())
+                .unwrap_or_default(),
+            state.input_buffer
+        ),
+        AppMode::IntervalInput => format!(
+            "CatZ - Interval for {}: {}_",
+            state
+                .selected_process_info()
+                .map(|p| p.name.as_str())
+                .unwrap_or_default(),
+            state.input_buffer
+        ),
+        AppMode::MarkerInput => format!("CatZ - Marker: {}_", state.input_buffer),
+        AppMode::FilterInput => format!("CatZ - Filter: /{}_", state.input_buffer),
+        AppMode::Stats => format!(
+            "{name} - STATS MODE{burst} ({}:stop stats, {}:marker, {}:quit)",
+            keys.stats, keys.mark, keys.quit
+        ),
+        AppMode::Replay => match &state.replay {
+            Some(replay) => {
+                let position = replay
+                    .current_frame()
+                    .map(|f| f.elapsed.as_secs_f64())
+                    .unwrap_or_default();
+                let total = replay
+                    .history()
+                    .time_range()
+                    .map(|(_, end)| end.as_secs_f64())
+                    .unwrap_or_default();
+                format!(
+                    "CatZ - REPLAY {} - {} {}x  {:.1}s / {:.1}s  (frame {}/{})",
+                    replay.source,
+                    if replay.is_playing() { "▶" } else { "⏸" },
+                    replay.speed(),
+                    position,
+                    total,
+                    replay.cursor() + 1,
+                    replay.history().len()
+                )
+            }
+            None => "CatZ - REPLAY".to_string(),
+        },
+        _ => format!(
+            "{name}{burst}\n({}:add process, {}:stats, {}:quit)",
+            keys.add, keys.stats, keys.quit
+        ),
     };
 
+    let mut block = Block::default().borders(Borders::ALL);
+    if let Some(overhead) = &state.overhead {
+        block = block.title(
+            Title::from(format!(
+                " catz: {:.1}% CPU, {:.1} MB ",
+                overhead.cpu_usage, overhead.memory_mb
+            ))
+            .alignment(Alignment::Right),
+        );
+    }
     let title = Paragraph::new(title_text)
-        .block(Block::default().borders(Borders::ALL))
+        .block(block)
         .wrap(Wrap { trim: true })
         .alignment(Alignment::Center);
     frame.render_widget(title, area);
 }
 
-pub fn render_main_content(frame: &mut Frame, state: &AppState, area: Rect) {
+pub fn render_main_content(frame: &mut Frame, state: &AppState, view: &mut ViewState, area: Rect) {
     match state.mode {
-        AppMode::SelectProcess => render_process_selection(frame, state, area),
-        _ => process_table::render_process_table(
-            frame,
-            state.processes(),
-            state.selected_monitored_process,
-            area,
-        ),
+        AppMode::SelectProcess => render_process_selection(frame, state, view, area),
+        AppMode::Detail | AppMode::ReniceInput | AppMode::AffinityInput => {
+            render_detail(frame, state, area)
+        }
+        _ => {
+            let (history, frame_limit) = state.chart_history();
+            let (table_area, chart_area) = table_layout(state, area);
+
+            view.page_rows = process_table::rows_area(table_area).height as usize;
+            view.table.select(Some(state.selected_monitored_process));
+            process_table::render_process_table(
+                frame,
+                state.processes(),
+                &state.sort,
+                state.filter.as_ref(),
+                &state.config,
+                table_area,
+                &mut view.table,
+            );
+            if let (Some(process), Some(chart_area)) = (state.selected_process_info(), chart_area) {
+                process_chart::render_process_charts(
+                    frame,
+                    history,
+                    frame_limit,
+                    &state.config.theme,
+                    process,
+                    chart_area,
+                );
+            }
+        }
     }
 }
 
-fn render_process_selection(frame: &mut Frame, state: &AppState, area: Rect) {
+fn render_detail(frame: &mut Frame, state: &AppState, area: Rect) {
+    let Some(target) = &state.target_process else {
+        return;
+    };
+    // Prefer the latest sample over the snapshot taken when the view opened
+    let process = state
+        .processes()
+        .iter()
+        .find(|p| p.pid == target.pid)
+        .unwrap_or(target);
+    let input = match state.mode {
+        AppMode::ReniceInput => Some(("New nice value: ", state.input_buffer.as_str())),
+        AppMode::AffinityInput => Some(("Allowed CPUs: ", state.input_buffer.as_str())),
+        _ => None,
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(DETAIL_HEIGHT),
+            Constraint::Min(CHART_HEIGHT),
+        ])
+        .split(area);
+    process_detail::render_process_detail(
+        frame,
+        process,
+        state.scheduling_info.as_ref(),
+        input,
+        chunks[0],
+    );
+    let (history, frame_limit) = state.chart_history();
+    process_chart::render_process_charts(
+        frame,
+        history,
+        frame_limit,
+        &state.config.theme,
+        process,
+        chunks[1],
+    );
+}
+
+fn render_process_selection(frame: &mut Frame, state: &AppState, view: &mut ViewState, area: Rect) {
     let processes: Vec<ListItem> = state
         .candidate_processes
         .iter()
-        .enumerate()
-        .map(|(i, p)| {
-            let content = if i == state.selected_process {
-                format!("> {}", p)
-            } else {
-                format!("  {}", p)
-            };
-            ListItem::new(content)
-        })
+        .map(|p| ListItem::new(p.as_str()))
         .collect();
 
     let process_list = List::new(processes)
@@ -82,18 +295,258 @@ fn render_process_selection(frame: &mut Frame, state: &AppState, area: Rect) {
                 .borders(Borders::ALL)
                 .title("Select Process"),
         )
-        .highlight_style(Style::default().fg(Color::Yellow));
+        .highlight_style(Style::default().fg(state.config.theme.selection))
+        .highlight_symbol("> ")
+        .highlight_spacing(HighlightSpacing::Always);
 
-    frame.render_widget(process_list, area);
+    let rows_area = area.inner(&Margin::new(1, 1));
+    view.page_rows = rows_area.height as usize;
+    view.picker.select(Some(state.selected_process));
+    frame.render_stateful_widget(process_list, area, &mut view.picker);
+    render_scrollbar(
+        frame,
+        state.candidate_processes.len(),
+        view.picker.offset(),
+        rows_area,
+    );
 }
 
 fn render_status_line(frame: &mut Frame, state: &AppState, area: Rect) {
-    let status = match state.mode {
-        AppMode::InputPattern => "Enter process name pattern (Enter to search)",
-        AppMode::SelectProcess => "↑↓:select process, Enter:confirm, q:cancel",
-        _ => "a:add process, d:remove selected process, s:stats mode, q:quit",
+    let keys = &state.config.keybindings;
+    let main_hints = format!(
+        "{}:add process, {}:remove, Enter:details, {}:signal, {}:alias, {}:group, {}:interval, {}:burst, {}:marker, {}/{}:sort, {}:columns, {}:filter, {}/{}:next/prev match, PgUp/PgDn/Home/End:scroll, {}:save session, {}:stats mode, {}:quit",
+        keys.add,
+        keys.remove,
+        keys.signal,
+        keys.alias,
+        keys.group,
+        keys.interval,
+        keys.burst,
+        keys.mark,
+        keys.sort,
+        keys.reverse_sort,
+        keys.columns,
+        keys.filter,
+        keys.next_match,
+        keys.previous_match,
+        keys.save_session,
+        keys.stats,
+        keys.quit
+    );
+    let status = match (&state.status_message, state.mode) {
+        (Some(message), _) => message.as_str(),
+        (None, AppMode::InputPattern) => "Enter process name pattern (Enter to search)",
+        (None, AppMode::SelectProcess) => {
+            "↑↓ PgUp/PgDn Home/End:select process, Enter:confirm, q:cancel"
+        }
+        (None, AppMode::Replay) => {
+            "space:play/pause, +/-:speed, ←→:step, []:seek, g/G:start/end, p/P:CPU/memory peak, q:quit"
+        }
+        (None, AppMode::SignalMenu) => "↑↓:select signal, Enter:choose, q:cancel",
+        (None, AppMode::ConfirmSignal) => "y:send signal, n:cancel",
+        (None, AppMode::Columns) => {
+            "↑↓:select, space:show/hide, K/J:move up/down, Enter:sort (again to reverse), c/q:close"
+        }
+        (None, AppMode::AliasInput | AppMode::GroupInput) => {
+            "Enter a name (empty to clear), Enter:apply, Esc:cancel"
+        }
+        (None, AppMode::Detail)
+            if state
+                .target_process
+                .as_ref()
+                .is_some_and(|p| p.host.is_some()) =>
+        {
+            "q:back (signals and scheduling only work on local processes)"
+        }
+        (None, AppMode::Detail) => "n:renice, c:CPU affinity, k:send signal, q:back",
+        (None, AppMode::IntervalInput) => {
+            "Enter an interval such as 500ms or 5s (empty for default), Enter:apply, Esc:cancel"
+        }
+        (None, AppMode::FilterInput) => {
+            "Name substring and conditions such as cpu>20 mem>=512 host=web1 host~=web (empty to clear), Enter:apply, Esc:cancel"
+        }
+        (None, AppMode::MarkerInput) => {
+            "Name the marker, e.g. \"deployed v2\", Enter:add, Esc:cancel"
+        }
+        (None, AppMode::ReniceInput) => "Enter nice value (-20 to 19), Enter:apply, Esc:cancel",
+        (None, AppMode::AffinityInput) => "Enter CPU list such as 0-3,6, Enter:apply, Esc:cancel",
+        (None, _) => main_hints.as_str(),
     };
 
-    let status_widget = Paragraph::new(status).style(Style::default().fg(Color::Yellow));
+    let status_widget =
+        Paragraph::new(status).style(Style::default().fg(state.config.theme.accent));
     frame.render_widget(status_widget, area);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        domain::process::ProcessInfo,
+        source::{FakeSource, MetricsSource},
+    };
+    use crossterm::event::KeyCode;
+    use ratatui::{Terminal, backend::TestBackend};
+    use std::time::Instant;
+    use sysinfo::Pid;
+
+    use crate::event::handle_key_events;
+
+    const FIXTURE: &str = r#"
+        [[processes]]
+        pid = 100
+        name = "nginx"
+        cpu = [5.0, 40.0]
+        memory_mb = [120.0, 130.5]
+
+        [[processes]]
+        pid = 200
+        name = "postgres"
+        cpu = [1.5]
+        memory_mb = [300.0]
+
+        [[processes]]
+        pid = 300
+        name = "nginx-reload"
+        cpu = [0.5]
+        memory_mb = [8.0]
+        exit = 1
+    "#;
+
+    fn press(state: &mut AppState, source: &mut FakeSource, keys: &str) {
+        for key in keys.chars() {
+            let key = if key == '\n' {
+                KeyCode::Enter
+            } else {
+                KeyCode::Char(key)
+            };
+            handle_key_events(key, state, source, 10).unwrap();
+        }
+    }
+
+    /// Samples every watched process once, as the sampler thread would.
+    fn sample(state: &mut AppState, source: &mut FakeSource) {
+        let pids: Vec<Pid> = state.watch_targets().iter().filter_map(|t| t.pid).collect();
+        let processes = source
+            .sample(&pids)
+            .into_iter()
+            .map(|s| ProcessInfo::new(s.name, s.pid, s.cpu_usage, s.memory_mb))
+            .collect();
+        state.apply_samples(&pids, processes);
+    }
+
+    fn draw(state: &AppState, view: &mut ViewState) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| render(f, state, view)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn row<'a>(screen: &'a str, name: &str) -> &'a str {
+        screen
+            .lines()
+            .find(|line| line.trim_start_matches('│').starts_with(name))
+            .unwrap_or_else(|| panic!("no row for {name} in\n{screen}"))
+    }
+
+    #[test]
+    fn empty_table_explains_how_to_add_processes() {
+        let state = AppState::new(Config::default());
+        let screen = draw(&state, &mut ViewState::default());
+        assert!(screen.contains("No processes monitored. Press 'a' to add a process."));
+    }
+
+    #[test]
+    fn added_processes_show_scripted_samples() {
+        let mut source = FakeSource::parse(FIXTURE).unwrap();
+        let mut state = AppState::new(Config::default());
+        let mut view = ViewState::default();
+
+        press(&mut state, &mut source, "anginx\n");
+        assert_eq!(state.mode, AppMode::SelectProcess);
+        assert_eq!(state.candidate_processes, ["nginx", "nginx-reload"]);
+        let screen = draw(&state, &mut view);
+        assert!(screen.contains("(2 matches)"));
+        assert!(screen.contains("> nginx "));
+
+        press(&mut state, &mut source, "\napostgres\n\n");
+        assert_eq!(state.watch_targets().len(), 2);
+
+        sample(&mut state, &mut source);
+        let screen = draw(&state, &mut view);
+        let nginx = row(&screen, "nginx");
+        assert!(nginx.contains("100") && nginx.contains("5.0") && nginx.contains("120.0"));
+        let postgres = row(&screen, "postgres");
+        assert!(postgres.contains("200") && postgres.contains("1.5"));
+
+        // Each sample moves the script one step, the last value repeating
+        sample(&mut state, &mut source);
+        sample(&mut state, &mut source);
+        let screen = draw(&state, &mut view);
+        let nginx = row(&screen, "nginx");
+        assert!(nginx.contains("40.0") && nginx.contains("130.5"));
+        assert!(row(&screen, "postgres").contains("300.0"));
+    }
+
+    #[test]
+    fn exited_processes_are_unbound() {
+        let mut source = FakeSource::parse(FIXTURE).unwrap();
+        let mut state = AppState::new(Config::default());
+        press(&mut state, &mut source, "areload\n\n");
+        sample(&mut state, &mut source);
+        assert_eq!(state.processes().len(), 1);
+
+        // The script ends the process at step 1
+        sample(&mut state, &mut source);
+        assert!(state.processes().is_empty());
+        assert_eq!(state.watch_targets()[0].pid, None);
+    }
+
+    #[test]
+    fn scripted_processes_cant_be_signalled() {
+        let mut source = FakeSource::parse(FIXTURE).unwrap();
+        let mut state = AppState::new(Config::default());
+        press(&mut state, &mut source, "apostgres\n\n");
+        sample(&mut state, &mut source);
+
+        press(&mut state, &mut source, "k");
+        assert_eq!(state.mode, AppMode::Normal);
+        assert_eq!(
+            state.status_message.as_deref(),
+            Some("Signals and scheduling only work on local processes")
+        );
+        press(&mut state, &mut source, "\nn");
+        assert_eq!(state.mode, AppMode::Detail);
+        assert!(state.scheduling_info.is_none());
+    }
+
+    #[test]
+    fn failed_saves_keep_the_stats() {
+        let mut source = FakeSource::parse(FIXTURE).unwrap();
+        let mut state = AppState::new(Config::default());
+        press(&mut state, &mut source, "apostgres\n\ns");
+        sample(&mut state, &mut source);
+        let processes = state.processes().to_vec();
+        state.record_frame(Instant::now(), &processes).unwrap();
+        assert_eq!(state.stats_data.len(), 1);
+
+        press(&mut state, &mut source, "s/dev/null/stats.csv\n");
+        assert_eq!(state.mode, AppMode::SavePrompt);
+        assert_eq!(state.save_filename, "/dev/null/stats.csv");
+        assert_eq!(state.stats_data.len(), 1);
+        assert!(
+            state
+                .status_message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("Failed to save stats"))
+        );
+    }
+}
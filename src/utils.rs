@@ -1,11 +1,166 @@
-pub fn calculate_cpu_percentage(old_time: f32, new_time: f32, elapsed_ms: u64) -> f32 {
-    if elapsed_ms == 0 {
-        return 0.0;
-    }
-    let cpu_delta = new_time - old_time;
-    // Convert elapsed_ms to seconds and calculate percentage
-    let elapsed_secs = elapsed_ms as f32 / 1000.0;
-    let cpu_usage = (cpu_delta / elapsed_secs) * 100.0;
-    // Round to 1 decimal place and ensure non-negative
-    (cpu_usage * 10.0).round().max(0.0) / 10.0
+use std::{
+    io::{self, BufRead, Read},
+    time::Duration,
+};
+
+/// Parses a memory size such as "50MB", "1.5GiB" or "512" into megabytes.
+/// A bare number is taken to be megabytes already.
+pub fn parse_size_mb(input: &str) -> Result<f64, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size {input:?}"))?;
+    let factor = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "mb" | "mib" | "m" => 1.0,
+        "b" => 1.0 / 1024.0 / 1024.0,
+        "kb" | "kib" | "k" => 1.0 / 1024.0,
+        "gb" | "gib" | "g" => 1024.0,
+        other => return Err(format!("unknown size unit {other:?} in {input:?}")),
+    };
+    Ok(value * factor)
+}
+
+/// Longest sampling interval accepted.
+pub const MAX_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Parses a sampling interval such as "250ms", "1.5s" or "2m". A bare
+/// number is taken to be seconds, as in earlier versions.
+pub fn parse_interval(input: &str) -> Result<Duration, String> {
+    let interval = parse_duration(input)?;
+    if interval > MAX_INTERVAL {
+        return Err(format!(
+            "interval {:?} is longer than the maximum of 1h",
+            input.trim()
+        ));
+    }
+    Ok(interval)
+}
+
+/// Parses a duration written like an interval, without its upper limit.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let duration = match input.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => humantime::parse_duration(input)
+            .or_else(|_| {
+                input
+                    .strip_suffix('s')
+                    .and_then(|secs| secs.parse::<f64>().ok())
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                    .ok_or(())
+            })
+            .map_err(|_| format!("invalid duration {input:?}, expected e.g. 500ms or 2s"))?,
+    };
+    if duration.is_zero() {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(duration)
+}
+
+/// Reads a line of at most `limit` bytes without its line ending, or
+/// `None` at the end of input. Longer lines are an `InvalidData` error,
+/// so a client that never sends a newline can't grow the buffer.
+pub fn read_line_capped(reader: &mut impl BufRead, limit: usize) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    if reader
+        .by_ref()
+        .take(limit as u64 + 1)
+        .read_until(b'\n', &mut line)?
+        == 0
+    {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    } else if line.len() > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line is longer than {limit} bytes"),
+        ));
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_lines_up_to_the_limit() {
+        let mut input = io::Cursor::new("abcd\nab\r\nabcde\n");
+        assert_eq!(
+            read_line_capped(&mut input, 4).unwrap().as_deref(),
+            Some("abcd")
+        );
+        assert_eq!(
+            read_line_capped(&mut input, 4).unwrap().as_deref(),
+            Some("ab")
+        );
+        assert!(read_line_capped(&mut input, 4).is_err());
+
+        let mut input = io::Cursor::new("last");
+        assert_eq!(
+            read_line_capped(&mut input, 4).unwrap().as_deref(),
+            Some("last")
+        );
+        assert_eq!(read_line_capped(&mut input, 4).unwrap(), None);
+    }
+
+    #[test]
+    fn parses_sizes_in_megabytes() {
+        assert_eq!(parse_size_mb("512"), Ok(512.0));
+        assert_eq!(parse_size_mb("50MB"), Ok(50.0));
+        assert_eq!(parse_size_mb(" 1.5 GiB "), Ok(1536.0));
+        assert_eq!(parse_size_mb("512k"), Ok(0.5));
+        assert_eq!(parse_size_mb("1048576b"), Ok(1.0));
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        for input in ["", "MB", "-5MB", "1.2.3", "5 parsecs", "5TB"] {
+            assert!(parse_size_mb(input).is_err(), "{input:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(parse_interval("2"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_interval("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_interval(" 1.5s "), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_interval("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_interval("1h"), Ok(MAX_INTERVAL));
+    }
+
+    #[test]
+    fn rejects_invalid_intervals() {
+        for input in [
+            "",
+            "0",
+            "0ms",
+            "fast",
+            "-1s",
+            "NaNs",
+            "infs",
+            "1e30s",
+            "2h",
+            "99999999999",
+        ] {
+            assert!(parse_interval(input).is_err(), "{input:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn durations_may_exceed_the_interval_limit() {
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert!(parse_duration("1e30s").is_err());
+    }
 }
@@ -0,0 +1,289 @@
+//! A small HTTP server with a live dashboard for browsers.
+//!
+//! `GET /` serves a self-contained HTML page, and `GET /events` streams
+//! every sampled frame as a Server-Sent Event holding a
+//! [`FrameSnapshot`] in JSON. New clients first receive the frames already
+//! sampled, so their charts don't start empty. Browsers that fall
+//! [`QUEUE`] frames behind are disconnected, and reconnect on their own.
+
+use color_eyre::{Result, eyre::WrapErr};
+use std::{
+    collections::VecDeque,
+    io::{BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{agent::protocol::FrameSnapshot, domain::history::Frame, utils};
+
+const DASHBOARD: &str = include_str!("dashboard.html");
+/// Frames replayed to a client when it connects.
+const BACKLOG: usize = 600;
+/// Frames waiting to be written to one browser.
+const QUEUE: usize = 64;
+/// Most bytes of a request line and its headers together.
+const MAX_HEAD: u64 = 16 * 1024;
+/// How long reading a request or writing to a browser may take.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+/// Idle time after which a comment is sent, so proxies keep the stream open.
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Default)]
+struct Clients {
+    /// Recent frames as `data:` payloads
+    backlog: VecDeque<String>,
+    streams: Vec<SyncSender<String>>,
+}
+
+/// Serves the dashboard; frames passed to [`publish`](Self::publish) reach
+/// every connected browser.
+pub struct WebServer {
+    address: SocketAddr,
+    clients: Arc<Mutex<Clients>>,
+}
+
+impl WebServer {
+    /// Starts listening on a new thread.
+    pub fn start(address: &str) -> Result<Self> {
+        let listener = TcpListener::bind(address)
+            .wrap_err_with(|| format!("failed to serve the dashboard on {address}"))?;
+        let address = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Clients::default()));
+        let shared = Arc::clone(&clients);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let clients = Arc::clone(&shared);
+                thread::spawn(move || handle_connection(stream, &clients));
+            }
+        });
+        Ok(Self { address, clients })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    pub fn publish(&self, frame: &Frame) {
+        let Ok(payload) = serde_json::to_string(&FrameSnapshot::from(frame)) else {
+            return;
+        };
+        let mut clients = self.clients.lock().unwrap();
+        if clients.backlog.len() >= BACKLOG {
+            clients.backlog.pop_front();
+        }
+        clients.backlog.push_back(payload.clone());
+        // Browsers that went away or can't keep up are dropped here
+        clients
+            .streams
+            .retain(|stream| stream.try_send(payload.clone()).is_ok());
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, clients: &Mutex<Clients>) {
+    if stream.set_read_timeout(Some(IO_TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(IO_TIMEOUT)).is_err()
+    {
+        return;
+    }
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut head = BufReader::new(reader).take(MAX_HEAD);
+    let Ok(Some(request_line)) = utils::read_line_capped(&mut head, MAX_HEAD as usize) else {
+        return;
+    };
+    // Skip the headers; none of them matter here
+    loop {
+        match utils::read_line_capped(&mut head, MAX_HEAD as usize) {
+            Ok(Some(header)) if !header.is_empty() => {}
+            Ok(Some(_)) => break,
+            // The headers didn't end within the limit
+            Ok(None) if head.limit() == 0 => {
+                let _ = respond(
+                    &mut stream,
+                    "431 Request Header Fields Too Large",
+                    "text/plain",
+                    "request headers are too large\n",
+                );
+                return;
+            }
+            _ => return,
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+    let _ = match (method, path) {
+        (Some("GET"), Some("/")) => respond(&mut stream, "200 OK", "text/html", DASHBOARD),
+        (Some("GET"), Some("/events")) => {
+            let (sender, receiver) = mpsc::sync_channel(QUEUE);
+            let backlog: Vec<String> = {
+                let mut clients = clients.lock().unwrap();
+                clients.streams.push(sender);
+                clients.backlog.iter().cloned().collect()
+            };
+            stream_events(&mut stream, backlog, &receiver)
+        }
+        (Some("GET"), _) => respond(&mut stream, "404 Not Found", "text/plain", "not found\n"),
+        _ => respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            "only GET is supported\n",
+        ),
+    };
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Writes frames to a browser until it disconnects.
+fn stream_events(
+    stream: &mut TcpStream,
+    backlog: Vec<String>,
+    frames: &Receiver<String>,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n"
+    )?;
+    for payload in backlog {
+        write!(stream, "data: {payload}\n\n")?;
+    }
+    stream.flush()?;
+    loop {
+        match frames.recv_timeout(KEEPALIVE) {
+            Ok(payload) => write!(stream, "data: {payload}\n\n")?,
+            Err(RecvTimeoutError::Timeout) => write!(stream, ": keepalive\n\n")?,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        stream.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::process::ProcessInfo;
+    use std::io::BufRead;
+    use sysinfo::Pid;
+
+    fn server() -> WebServer {
+        WebServer::start("127.0.0.1:0").unwrap()
+    }
+
+    fn connect(server: &WebServer, request: &[u8]) -> TcpStream {
+        let mut stream = TcpStream::connect(server.address).unwrap();
+        stream.set_read_timeout(Some(IO_TIMEOUT)).unwrap();
+        stream.write_all(request).unwrap();
+        stream
+    }
+
+    /// Sends a request and reads the response until the server closes.
+    fn get(server: &WebServer, request: &[u8]) -> String {
+        let mut response = String::new();
+        connect(server, request)
+            .read_to_string(&mut response)
+            .unwrap();
+        response
+    }
+
+    fn frame(cpu_usage: f32) -> Frame {
+        Frame {
+            elapsed: Duration::from_secs(1),
+            processes: vec![ProcessInfo::new(
+                "nginx".to_string(),
+                Pid::from_u32(100),
+                cpu_usage,
+                10.0,
+            )],
+        }
+    }
+
+    /// Reads `data:` lines off an event stream.
+    fn events(reader: &mut impl BufRead, count: usize) -> Vec<String> {
+        let mut events = Vec::new();
+        while events.len() < count {
+            let mut line = String::new();
+            assert!(reader.read_line(&mut line).unwrap() > 0, "stream ended");
+            if let Some(payload) = line.strip_prefix("data: ") {
+                events.push(payload.trim_end().to_string());
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn routes_requests() {
+        let server = server();
+        let page = get(&server, b"GET / HTTP/1.1\r\nHost: catz\r\n\r\n");
+        assert!(page.starts_with("HTTP/1.1 200 OK\r\n"), "{page}");
+        assert!(page.contains("Content-Type: text/html"));
+        assert!(page.ends_with(DASHBOARD));
+
+        let missing = get(&server, b"GET /nope HTTP/1.1\r\n\r\n");
+        assert!(
+            missing.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{missing}"
+        );
+        let post = get(&server, b"POST / HTTP/1.1\r\nContent-Length: 0\r\n\r\n");
+        assert!(
+            post.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+            "{post}"
+        );
+    }
+
+    #[test]
+    fn replays_the_backlog_then_streams_frames() {
+        let server = server();
+        server.publish(&frame(1.0));
+        server.publish(&frame(2.0));
+
+        let stream = connect(&server, b"GET /events HTTP/1.1\r\n\r\n");
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader.read_line(&mut status).unwrap();
+        assert_eq!(status, "HTTP/1.1 200 OK\r\n");
+        let backlog = events(&mut reader, 2);
+        assert!(backlog[0].contains("\"cpu_usage\":1.0"), "{}", backlog[0]);
+        assert!(backlog[1].contains("\"cpu_usage\":2.0"), "{}", backlog[1]);
+
+        // The client is registered once the backlog was read
+        server.publish(&frame(3.0));
+        let live = events(&mut reader, 1);
+        assert!(live[0].contains("\"cpu_usage\":3.0"), "{}", live[0]);
+    }
+
+    #[test]
+    fn caps_the_request_head() {
+        let server = server();
+        let response = get(&server, &vec![b'x'; MAX_HEAD as usize]);
+        assert!(
+            response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"),
+            "{response}"
+        );
+
+        let mut request = b"GET / HTTP/1.1\r\n".to_vec();
+        request.extend(b"X-Padding: ".iter().chain(&vec![b'x'; MAX_HEAD as usize]));
+        let mut stream = connect(&server, &request[..MAX_HEAD as usize]);
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 431"), "{response}");
+    }
+}
@@ -0,0 +1,103 @@
+//! Uses CatZ the way other programs do, through its public API only.
+
+use std::{
+    env, fs,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use catz::{
+    domain::{
+        history::{Frame, History, Marker},
+        process::{ProcessManager, WatchTarget},
+    },
+    guard::{ByteSize, GuardError, ResourceGuard},
+    recording::{Recording, Thresholds},
+    sampler::{SamplerEvent, SamplerHandle, Schedule},
+    source::{FakeSource, MetricsSource, SourceKind},
+};
+
+const FIXTURE: &str = r#"
+    [[processes]]
+    pid = 100
+    name = "nginx"
+    cpu = [10.0, 20.0, 30.0]
+    memory_mb = [100.0, 150.0]
+    threads = [4]
+    exit = 3
+
+    [[processes]]
+    pid = 200
+    name = "postgres"
+    cpu = [5.0]
+    memory_mb = [400.0]
+"#;
+
+#[test]
+fn samples_targets_into_a_recording() {
+    let mut source = FakeSource::parse(FIXTURE).unwrap();
+    let running = source.processes();
+    let [(pid, name)] = &ProcessManager::find_matching_processes("ngin", &running)[..] else {
+        panic!("expected nginx only");
+    };
+    let target = WatchTarget::new(name.clone(), *pid);
+
+    let (events, receiver) = mpsc::channel();
+    let sampler = SamplerHandle::spawn(Box::new(source), events);
+    sampler.update(Schedule::new(vec![target], Duration::from_millis(1)));
+
+    let started = Instant::now();
+    let mut history = History::new();
+    while history.len() < 3 {
+        let event = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        if let SamplerEvent::Sample(sample) = event {
+            history.push(Frame {
+                elapsed: sample.taken_at - started,
+                processes: sample.processes,
+            });
+        }
+    }
+    history.add_marker(Marker {
+        elapsed: Instant::now() - started,
+        text: "done".to_string(),
+    });
+
+    let recording = Recording::from_history(&history, None, &Thresholds::default());
+    let cpu: Vec<f32> = recording
+        .samples_for("nginx")
+        .map(|sample| sample.cpu_usage)
+        .collect();
+    assert_eq!(cpu, [10.0, 20.0, 30.0]);
+    assert_eq!(recording.process_names(), ["nginx"]);
+
+    let path = env::temp_dir().join(format!("catz-library-{}.ndjson", std::process::id()));
+    recording.save(&path).unwrap();
+    let loaded = Recording::load(&path);
+    fs::remove_file(&path).unwrap();
+    let loaded = loaded.unwrap();
+    assert_eq!(loaded.samples.len(), 3);
+    assert_eq!(loaded.samples[2].memory_mb, 150.0);
+    assert_eq!(loaded.samples[0].threads, 4);
+    assert_eq!(loaded.markers[0].text, "done");
+}
+
+#[test]
+fn guards_a_process_against_its_budget() {
+    let source = SourceKind::Fake(FakeSource::parse(FIXTURE).unwrap());
+    let mut guard = ResourceGuard::watch_with(100, &source)
+        .interval(Duration::from_millis(1))
+        .max_rss(120.mib());
+    assert!(guard.wait(Duration::from_secs(10)));
+    let Err(GuardError::Exceeded(report)) = guard.finish() else {
+        panic!("expected the memory budget to be exceeded");
+    };
+    assert_eq!(report.name, "nginx");
+    assert_eq!(report.peak_rss_mb, 150.0);
+
+    let mut guard = ResourceGuard::watch_with(200, &source)
+        .interval(Duration::from_millis(1))
+        .max_rss(1.gib());
+    assert!(!guard.wait(Duration::from_millis(50)));
+    let report = guard.finish().unwrap();
+    assert!(report.passed());
+}